@@ -20,6 +20,7 @@ format() {
 
 format "$CI_DIR"/../automap
 format "$CI_DIR"/../masq_lib
+format "$CI_DIR"/../masq_derive
 format "$CI_DIR"/../node
 format "$CI_DIR"/../dns_utility
 format "$CI_DIR"/../masq
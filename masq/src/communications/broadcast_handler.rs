@@ -271,6 +271,7 @@ mod tests {
         .start(Box::new(factory));
         let message = UiNodeCrashedBroadcast {
             process_id: 1234,
+            exit_code_opt: None,
             crash_reason: CrashReason::Unrecognized("Unknown crash reason".to_string()),
         }
         .tmb(0);
@@ -490,6 +491,7 @@ neighborhood-mode             standard
     fn crash_notifier_handle_broadcast_has_a_synchronizer_correctly_implemented() {
         let crash_notifier_body = UiNodeCrashedBroadcast {
             process_id: 100,
+            exit_code_opt: None,
             crash_reason: CrashReason::NoInformation,
         };
 
@@ -491,6 +491,7 @@ impl ConnectionManagerThread {
     fn send_daemon_crashed(inner: &CmsInner) {
         let crash_msg = UiNodeCrashedBroadcast {
             process_id: 0,
+            exit_code_opt: None,
             crash_reason: CrashReason::DaemonCrashed,
         };
         inner.broadcast_handle.send(crash_msg.tmb(0))
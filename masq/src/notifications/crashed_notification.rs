@@ -44,7 +44,10 @@ impl CrashNotifier {
             }
             CrashReason::NoInformation => panic!("Should never get here"),
             CrashReason::Unrecognized(msg) => msg,
+            CrashReason::ExitedWithCode(code) => format!("the Node exited with code {}", code),
             CrashReason::DaemonCrashed => panic!("Should never get here"),
+            CrashReason::Panic(msg) => format!("the Node panicked: {}", msg),
+            CrashReason::Message(msg) => msg,
         }
     }
 
@@ -74,6 +77,7 @@ mod tests {
         let stderr = ByteArrayWriter::new();
         let msg = UiNodeCrashedBroadcast {
             process_id: 12345,
+            exit_code_opt: None,
             crash_reason: CrashReason::ChildWaitFailure("Couldn't wait".to_string()),
         };
         let term_interface = TerminalWrapper::new(Arc::new(TerminalPassiveMock::new()));
@@ -91,6 +95,7 @@ mod tests {
         let stderr = ByteArrayWriter::new();
         let msg = UiNodeCrashedBroadcast {
             process_id: 12345,
+            exit_code_opt: None,
             crash_reason: CrashReason::Unrecognized("Just...failed!\n\n".to_string()),
         };
         let term_interface = TerminalWrapper::new(Arc::new(TerminalPassiveMock::new()));
@@ -101,6 +106,60 @@ mod tests {
         assert_eq!(stderr.get_string(), "".to_string());
     }
 
+    #[test]
+    pub fn handles_exit_code_failure() {
+        running_test();
+        let mut stdout = ByteArrayWriter::new();
+        let stderr = ByteArrayWriter::new();
+        let msg = UiNodeCrashedBroadcast {
+            process_id: 12345,
+            exit_code_opt: Some(137),
+            crash_reason: CrashReason::ExitedWithCode(137),
+        };
+        let term_interface = TerminalWrapper::new(Arc::new(TerminalPassiveMock::new()));
+
+        CrashNotifier::handle_broadcast(msg, &mut stdout, &term_interface);
+
+        assert_eq! (stdout.get_string(), "\nThe Node running as process 12345 terminated:\n------\nthe Node exited with code 137\n------\nThe Daemon is once more accepting setup changes.\n\n".to_string());
+        assert_eq!(stderr.get_string(), "".to_string());
+    }
+
+    #[test]
+    pub fn handles_panic_failure() {
+        running_test();
+        let mut stdout = ByteArrayWriter::new();
+        let stderr = ByteArrayWriter::new();
+        let msg = UiNodeCrashedBroadcast {
+            process_id: 12345,
+            exit_code_opt: Some(101),
+            crash_reason: CrashReason::Panic("attempt to divide by zero".to_string()),
+        };
+        let term_interface = TerminalWrapper::new(Arc::new(TerminalPassiveMock::new()));
+
+        CrashNotifier::handle_broadcast(msg, &mut stdout, &term_interface);
+
+        assert_eq! (stdout.get_string(), "\nThe Node running as process 12345 terminated:\n------\nthe Node panicked: attempt to divide by zero\n------\nThe Daemon is once more accepting setup changes.\n\n".to_string());
+        assert_eq!(stderr.get_string(), "".to_string());
+    }
+
+    #[test]
+    pub fn handles_message_failure() {
+        running_test();
+        let mut stdout = ByteArrayWriter::new();
+        let stderr = ByteArrayWriter::new();
+        let msg = UiNodeCrashedBroadcast {
+            process_id: 12345,
+            exit_code_opt: None,
+            crash_reason: CrashReason::Message("Ran out of disk space".to_string()),
+        };
+        let term_interface = TerminalWrapper::new(Arc::new(TerminalPassiveMock::new()));
+
+        CrashNotifier::handle_broadcast(msg, &mut stdout, &term_interface);
+
+        assert_eq! (stdout.get_string(), "\nThe Node running as process 12345 terminated:\n------\nRan out of disk space\n------\nThe Daemon is once more accepting setup changes.\n\n".to_string());
+        assert_eq!(stderr.get_string(), "".to_string());
+    }
+
     #[test]
     pub fn handles_no_information_failure() {
         running_test();
@@ -108,6 +167,7 @@ mod tests {
         let stderr = ByteArrayWriter::new();
         let msg = UiNodeCrashedBroadcast {
             process_id: 12345,
+            exit_code_opt: None,
             crash_reason: CrashReason::NoInformation,
         };
         let term_interface = TerminalWrapper::new(Arc::new(TerminalPassiveMock::new()));
@@ -125,6 +185,7 @@ mod tests {
         let mut stdout = ByteArrayWriter::new();
         let msg = UiNodeCrashedBroadcast {
             process_id: 12345,
+            exit_code_opt: None,
             crash_reason: CrashReason::DaemonCrashed,
         };
         let term_interface = TerminalWrapper::new(Arc::new(TerminalPassiveMock::new()));
@@ -0,0 +1,171 @@
+// Copyright (c) 2019, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, DeriveInput, Lit, Meta};
+
+// Generates the same ToMessageBody/FromMessageBody impls as the conversation_message!
+// and fire_and_forget_message! macros in masq_lib::messages, driven by attributes:
+//
+//     #[derive(Serialize, Deserialize, UiMessage)]
+//     #[opcode = "checkPassword"]
+//     #[message_path = "conversation"]
+//     pub struct UiCheckPasswordRequest { ... }
+#[proc_macro_derive(UiMessage, attributes(opcode, message_path))]
+pub fn derive_ui_message(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let opcode = string_attribute(input, "opcode")?;
+    let is_conversational = match string_attribute(input, "message_path")?.as_str() {
+        "conversation" => true,
+        "fireAndForget" => false,
+        other => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                format!(
+                    "Unrecognized message_path '{}': expected 'conversation' or 'fireAndForget'",
+                    other
+                ),
+            ))
+        }
+    };
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let (path, context_id) = if is_conversational {
+        (
+            quote! { ::masq_lib::ui_gateway::MessagePath::Conversation(context_id) },
+            quote! {
+                match &body.path {
+                    ::masq_lib::ui_gateway::MessagePath::Conversation(context_id) => *context_id,
+                    ::masq_lib::ui_gateway::MessagePath::FireAndForget => {
+                        return Err(::masq_lib::messages::UiMessageError::UnexpectedMessage(body))
+                    }
+                }
+            },
+        )
+    } else {
+        (
+            quote! { ::masq_lib::ui_gateway::MessagePath::FireAndForget },
+            quote! {
+                match &body.path {
+                    ::masq_lib::ui_gateway::MessagePath::FireAndForget => 0,
+                    ::masq_lib::ui_gateway::MessagePath::Conversation(_) => {
+                        return Err(::masq_lib::messages::UiMessageError::UnexpectedMessage(body))
+                    }
+                }
+            },
+        )
+    };
+    Ok(quote! {
+        impl #impl_generics ::masq_lib::messages::ToMessageBody for #name #type_generics #where_clause {
+            #[allow(unused_variables)]
+            fn tmb(self, context_id: u64) -> ::masq_lib::ui_gateway::MessageBody {
+                let json = ::masq_lib::serde_json::to_string(&self).expect("Serialization problem");
+                ::masq_lib::ui_gateway::MessageBody {
+                    opcode: #opcode.to_string(),
+                    path: #path,
+                    payload: Ok(json),
+                }
+            }
+
+            fn opcode(&self) -> &'static str {
+                Self::type_opcode()
+            }
+
+            fn is_conversational(&self) -> bool {
+                Self::type_is_conversational()
+            }
+        }
+
+        impl #impl_generics ::masq_lib::messages::FromMessageBody for #name #type_generics #where_clause {
+            fn fmb(
+                body: ::masq_lib::ui_gateway::MessageBody,
+            ) -> Result<(Self, u64), ::masq_lib::messages::UiMessageError> {
+                if body.opcode != #opcode {
+                    return Err(::masq_lib::messages::UiMessageError::UnexpectedMessage(body));
+                };
+                let payload = match &body.payload {
+                    Ok(json) => match ::masq_lib::serde_json::from_str::<Self>(json) {
+                        Ok(item) => item,
+                        Err(e) => {
+                            return Err(::masq_lib::messages::UiMessageError::DeserializationError(
+                                format!("{:?}", e),
+                                body,
+                            ))
+                        }
+                    },
+                    Err(_) => return Err(::masq_lib::messages::UiMessageError::PayloadError(body)),
+                };
+                let context_id = #context_id;
+                Ok((payload, context_id))
+            }
+        }
+
+        impl #impl_generics #name #type_generics #where_clause {
+            pub fn type_opcode() -> &'static str {
+                #opcode
+            }
+
+            pub fn type_is_conversational() -> bool {
+                #is_conversational
+            }
+        }
+    })
+}
+
+fn string_attribute(input: &DeriveInput, name: &str) -> syn::Result<String> {
+    let attribute = input
+        .attrs
+        .iter()
+        .find(|attribute| attribute.path.is_ident(name))
+        .ok_or_else(|| {
+            syn::Error::new_spanned(
+                &input.ident,
+                format!("#[derive(UiMessage)] requires #[{} = \"...\"]", name),
+            )
+        })?;
+    match attribute.parse_meta()? {
+        Meta::NameValue(name_value) => match name_value.lit {
+            Lit::Str(value) => Ok(value.value()),
+            lit => Err(syn::Error::new_spanned(
+                lit,
+                format!("#[{}] must be a string", name),
+            )),
+        },
+        meta => Err(syn::Error::new_spanned(
+            meta,
+            format!("Expected #[{} = \"...\"]", name),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_code_reaches_serde_json_only_through_masq_lib() {
+        let input: DeriveInput = syn::parse_str(
+            r#"
+            #[opcode = "sample"]
+            #[message_path = "conversation"]
+            struct UiSampleRequest {}
+            "#,
+        )
+        .unwrap();
+
+        let result = expand(&input).unwrap().to_string();
+
+        let all_mentions = result.matches("serde_json").count();
+        let masq_lib_mentions = result.matches(":: masq_lib :: serde_json").count();
+        assert_eq!(all_mentions, 2);
+        assert_eq!(masq_lib_mentions, all_mentions);
+    }
+}
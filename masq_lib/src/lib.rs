@@ -1,5 +1,12 @@
 // Copyright (c) 2019, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
 
+// Lets #[derive(UiMessage)] name ::masq_lib paths from inside this crate as well.
+extern crate self as masq_lib;
+
+// Code generated by #[derive(UiMessage)] reaches serde_json through here, so crates using the
+// derive don't need their own serde_json dependency.
+pub use serde_json;
+
 // These must be before the rest of the modules
 // in order to be able to use the macros.
 #[macro_use]
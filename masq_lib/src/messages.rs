@@ -66,6 +66,8 @@ impl fmt::Display for UiMessageError {
     }
 }
 
+pub use masq_derive::UiMessage;
+
 pub trait ToMessageBody: serde::Serialize {
     fn tmb(self, context_id: u64) -> MessageBody;
     fn opcode(&self) -> &str;
@@ -396,12 +398,17 @@ pub enum CrashReason {
     NoInformation,
     Unrecognized(String),
     DaemonCrashed,
+    ExitedWithCode(i32),
+    Panic(String),
+    Message(String),
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct UiNodeCrashedBroadcast {
     #[serde(rename = "processId")]
     pub process_id: u32,
+    #[serde(rename = "exitCodeOpt")]
+    pub exit_code_opt: Option<i32>,
     #[serde(rename = "crashReason")]
     pub crash_reason: CrashReason,
 }
@@ -459,8 +466,12 @@ pub struct UiCheckPasswordResponse {
 }
 conversation_message!(UiCheckPasswordResponse, "checkPassword");
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
-pub struct UiConfigurationChangedBroadcast {}
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct UiConfigurationChangedBroadcast {
+    #[serde(rename = "changedParameters")]
+    #[serde(default)]
+    pub changed_parameters: Vec<String>,
+}
 fire_and_forget_message!(UiConfigurationChangedBroadcast, "configurationChanged");
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -1208,4 +1219,169 @@ mod tests {
             Err("Unrecognized ordering: 'upside-down'".to_string())
         );
     }
+
+    #[test]
+    fn ui_shutdown_request_and_response_survive_round_trip() {
+        let request_body = UiShutdownRequest {}.tmb(4321);
+        let response_body = UiShutdownResponse {}.tmb(4321);
+
+        assert_eq!(
+            request_body,
+            MessageBody {
+                opcode: "shutdown".to_string(),
+                path: Conversation(4321),
+                payload: Ok("{}".to_string())
+            }
+        );
+        assert_eq!(response_body.opcode, "shutdown");
+        assert_eq!(response_body.path, Conversation(4321));
+        assert_eq!(
+            UiShutdownRequest::fmb(request_body),
+            Ok((UiShutdownRequest {}, 4321))
+        );
+        assert_eq!(
+            UiShutdownResponse::fmb(response_body),
+            Ok((UiShutdownResponse {}, 4321))
+        );
+    }
+
+    #[test]
+    fn ui_financials_request_and_response_survive_round_trip() {
+        let request = UiFinancialsRequest {
+            stats_required: true,
+            top_records_opt: None,
+            custom_queries_opt: None,
+        };
+        let make_response = || UiFinancialsResponse {
+            stats_opt: Some(UiFinancialStatistics {
+                total_unpaid_and_pending_payable_gwei: 1111,
+                total_paid_payable_gwei: 2222,
+                total_unpaid_receivable_gwei: -3333,
+                total_paid_receivable_gwei: 4444,
+            }),
+            query_results_opt: None,
+        };
+
+        let request_body = request.clone().tmb(1357);
+        let response_body = make_response().tmb(1357);
+
+        assert_eq!(request_body.opcode, "financials");
+        assert_eq!(request_body.path, Conversation(1357));
+        assert_eq!(response_body.opcode, "financials");
+        assert_eq!(response_body.path, Conversation(1357));
+        assert!(response_body
+            .payload
+            .as_ref()
+            .unwrap()
+            .contains("\"totalUnpaidReceivableGwei\":-3333"));
+        assert_eq!(UiFinancialsRequest::fmb(request_body), Ok((request, 1357)));
+        assert_eq!(
+            UiFinancialsResponse::fmb(response_body),
+            Ok((make_response(), 1357))
+        );
+    }
+
+    #[test]
+    fn ui_node_crashed_broadcast_survives_round_trip_with_exit_code() {
+        let subject = UiNodeCrashedBroadcast {
+            process_id: 1234,
+            exit_code_opt: Some(137),
+            crash_reason: CrashReason::ExitedWithCode(137),
+        };
+
+        let body = subject.clone().tmb(0);
+        let result = UiNodeCrashedBroadcast::fmb(body.clone());
+
+        assert_eq!(body.path, FireAndForget);
+        assert!(body
+            .payload
+            .as_ref()
+            .unwrap()
+            .contains("\"exitCodeOpt\":137"));
+        assert_eq!(result, Ok((subject, 0)));
+    }
+
+    #[test]
+    fn ui_node_crashed_broadcast_survives_round_trip_with_a_message() {
+        let subject = UiNodeCrashedBroadcast {
+            process_id: 1234,
+            exit_code_opt: None,
+            crash_reason: CrashReason::Message("Ran out of disk space".to_string()),
+        };
+
+        let body = subject.clone().tmb(0);
+        let result = UiNodeCrashedBroadcast::fmb(body.clone());
+
+        assert_eq!(
+            body.payload,
+            Ok(r#"{"processId":1234,"exitCodeOpt":null,"crashReason":{"Message":"Ran out of disk space"}}"#.to_string())
+        );
+        assert_eq!(result, Ok((subject, 0)));
+    }
+
+    #[test]
+    fn crash_reasons_serialize_as_before_and_round_trip() {
+        let reasons = vec![
+            CrashReason::NoInformation,
+            CrashReason::Panic("attempt to divide by zero".to_string()),
+            CrashReason::Message("Ran out of disk space".to_string()),
+        ];
+
+        let serialized = reasons
+            .iter()
+            .map(|reason| serde_json::to_string(reason).unwrap())
+            .collect::<Vec<String>>();
+        let deserialized = serialized
+            .iter()
+            .map(|json| serde_json::from_str::<CrashReason>(json).unwrap())
+            .collect::<Vec<CrashReason>>();
+
+        assert_eq!(
+            serialized,
+            vec![
+                r#""NoInformation""#.to_string(),
+                r#"{"Panic":"attempt to divide by zero"}"#.to_string(),
+                r#"{"Message":"Ran out of disk space"}"#.to_string(),
+            ]
+        );
+        assert_eq!(deserialized, reasons);
+    }
+
+    #[test]
+    fn ui_configuration_changed_broadcast_survives_round_trip_with_changed_parameters() {
+        let subject = UiConfigurationChangedBroadcast {
+            changed_parameters: vec!["gas-price".to_string(), "start-block".to_string()],
+        };
+
+        let body = subject.clone().tmb(0);
+        let result = UiConfigurationChangedBroadcast::fmb(body.clone());
+
+        assert_eq!(body.opcode, "configurationChanged");
+        assert_eq!(
+            body.payload,
+            Ok(r#"{"changedParameters":["gas-price","start-block"]}"#.to_string())
+        );
+        assert_eq!(result, Ok((subject, 0)));
+    }
+
+    #[test]
+    fn ui_configuration_changed_broadcast_without_changed_parameters_can_be_deserialized() {
+        let message_body = MessageBody {
+            opcode: "configurationChanged".to_string(),
+            path: FireAndForget,
+            payload: Ok("{}".to_string()),
+        };
+
+        let result = UiConfigurationChangedBroadcast::fmb(message_body);
+
+        assert_eq!(
+            result,
+            Ok((
+                UiConfigurationChangedBroadcast {
+                    changed_parameters: vec![]
+                },
+                0
+            ))
+        );
+    }
 }
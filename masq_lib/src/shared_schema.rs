@@ -129,7 +129,7 @@ pub const REAL_USER_HELP: &str =
     "The user whose identity Node will assume when dropping privileges after bootstrapping. Since Node refuses to \
      run with root privilege after bootstrapping, you might want to use this if you start the Node as root, or if \
      you start the Node using pkexec or some other method that doesn't populate the SUDO_xxx variables. Use a value \
-     like <uid>:<gid>:<home directory>.";
+     like <uid>:<gid>:<home directory>, or the name of an existing user.";
 pub const SCANS_HELP: &str =
     "The Node, when running, performs various periodic scans, including scanning for payables that need to be paid, \
     for pending payables that have arrived (and are no longer pending), for incoming receivables that need to be \
@@ -576,7 +576,7 @@ pub mod common_validators {
     }
 
     pub fn validate_real_user(triple: String) -> Result<(), String> {
-        if Regex::new("^[0-9]*:[0-9]*:.*$")
+        if Regex::new("^([0-9]*:[0-9]*:.*|[a-z_][a-z0-9_.-]*|S-1(-[0-9]+)+)$")
             .expect("Failed to compile regular expression")
             .is_match(&triple)
         {
@@ -962,6 +962,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn validate_real_user_accepts_triples_user_names_and_sids() {
+        vec![
+            "123:456:/home/booga",
+            "::",
+            "booga",
+            "S-1-5-21-1004336348-1177238915-1001",
+        ]
+        .into_iter()
+        .for_each(|value| {
+            assert_eq!(
+                common_validators::validate_real_user(value.to_string()),
+                Ok(())
+            )
+        });
+        vec!["Booga", "S-1-", "123:booga"]
+            .into_iter()
+            .for_each(|value| {
+                assert_eq!(
+                    common_validators::validate_real_user(value.to_string()),
+                    Err(value.to_string())
+                )
+            });
+    }
+
     #[test]
     fn validate_ui_port_complains_about_non_numeric_ui_port() {
         let result = common_validators::validate_ui_port(String::from("booga"));
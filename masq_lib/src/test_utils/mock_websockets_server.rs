@@ -6,33 +6,206 @@ use crate::ui_traffic_converter::UiTrafficConverter;
 use crate::utils::localhost;
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use lazy_static::lazy_static;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::Write;
 use std::net::SocketAddr;
-use std::net::TcpStream;
+use std::net::{TcpListener, TcpStream};
 use std::ops::Not;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::thread::JoinHandle;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use websocket::message::CloseData;
 use websocket::result::WebSocketError;
-use websocket::sync::{Client, Server};
+use websocket::sync::server::upgrade::{IntoWs, Upgrade};
+use websocket::sync::Client;
 use websocket::{OwnedMessage, WebSocketResult};
 
 lazy_static! {
     static ref MWSS_INDEX: Mutex<u64> = Mutex::new(0);
 }
 
+// RFC 6455 close code for a connection the server won't serve
+pub const POLICY_VIOLATION_CLOSE_CODE: u16 = 1008;
+// How long a surplus client gets to send its handshake before it's dropped unanswered
+const SURPLUS_HANDSHAKE_TIMEOUT: Duration = Duration::from_millis(100);
+
 pub struct MockWebSocketsServer {
     log: bool,
     port: u16,
     pub protocol: String,
+    accepted_protocols: Vec<String>,
+    reject_status_opt: Option<u16>,
     responses_arc: Arc<Mutex<Vec<OwnedMessage>>>,
+    context_responses: RefCell<HashMap<u64, VecDeque<OwnedMessage>>>,
     signal_sender: RefCell<Option<Sender<()>>>,
+    broadcast_interval_opt: Option<Duration>,
+    broadcast_opcode_filter_opt: Option<String>,
+    expected_requests: Vec<ExpectedRequest>,
+    handshake_delay_opt: Option<Duration>,
+    echo_mode: bool,
+    required_headers: Vec<(String, String)>,
+    ping_interval_opt: Option<Duration>,
+    start_delay_opt: Option<Duration>,
+    response_rules: Vec<(ResponsePredicate, MessageBody)>,
+    release_gate_opt: Option<Receiver<ReleaseOrder>>,
+    released_all: Cell<bool>,
+    black_hole: bool,
+    close_after_messages_opt: Option<usize>,
+}
+
+type ResponsePredicate = Box<dyn Fn(&MessageBody) -> bool + Send>;
+
+enum ReleaseOrder {
+    One,
+    All,
+}
+
+// Dropping the handle opens the gate for good, so a forgotten release can't wedge the server.
+pub struct ReleaseHandle {
+    release_tx: Sender<ReleaseOrder>,
+}
+
+impl ReleaseHandle {
+    pub fn release_one(&self) {
+        let _ = self.release_tx.send(ReleaseOrder::One);
+    }
+
+    pub fn release_all(&self) {
+        let _ = self.release_tx.send(ReleaseOrder::All);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExpectedRequest {
+    pub opcode: String,
+    pub context_id_opt: Option<u64>,
+}
+
+impl ExpectedRequest {
+    pub fn new(opcode: &str, context_id_opt: Option<u64>) -> Self {
+        Self {
+            opcode: opcode.to_string(),
+            context_id_opt,
+        }
+    }
+
+    fn is_met_by(&self, recorded: &Result<MessageBody, String>) -> bool {
+        match recorded {
+            Ok(body) if body.opcode == self.opcode => match (self.context_id_opt, &body.path) {
+                (None, _) => true,
+                (Some(expected_id), MessagePath::Conversation(id)) => expected_id == *id,
+                (Some(_), MessagePath::FireAndForget) => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self.context_id_opt {
+            Some(context_id) => format!("'{}' in context {}", self.opcode, context_id),
+            None => format!("'{}'", self.opcode),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PingStats {
+    pub pings_sent: usize,
+    pub round_trip_times: Vec<Duration>,
+}
+
+impl PingStats {
+    pub fn pongs_received(&self) -> usize {
+        self.round_trip_times.len()
+    }
+
+    pub fn pongs_within(&self, deadline: Duration) -> usize {
+        self.round_trip_times
+            .iter()
+            .filter(|round_trip_time| **round_trip_time <= deadline)
+            .count()
+    }
+}
+
+// Positions index the response queue; signal_before_opt indexes to_send.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct BroadcastPlan {
+    to_send: Vec<usize>,
+    held_back: Vec<usize>,
+    signal_before_opt: Option<usize>,
+}
+
+struct PingTracker {
+    interval: Duration,
+    last_ping_opt: Option<Instant>,
+    next_payload: u64,
+    outstanding: HashMap<u64, Instant>,
+    stats_arc: Arc<Mutex<PingStats>>,
+}
+
+impl PingTracker {
+    fn new(interval: Duration, stats_arc: Arc<Mutex<PingStats>>) -> Self {
+        Self {
+            interval,
+            last_ping_opt: None,
+            next_payload: 0,
+            outstanding: HashMap::new(),
+            stats_arc,
+        }
+    }
+
+    // Returns false if the Ping couldn't be sent, which means the client has gone away.
+    fn ping_if_due(&mut self, client: &mut Client<TcpStream>) -> bool {
+        let due = match self.last_ping_opt {
+            Some(last_ping) => last_ping.elapsed() >= self.interval,
+            None => true,
+        };
+        if !due {
+            return true;
+        }
+        let payload = self.next_payload;
+        self.next_payload += 1;
+        if client
+            .send_message(&OwnedMessage::Ping(payload.to_be_bytes().to_vec()))
+            .is_err()
+        {
+            return false;
+        }
+        let now = Instant::now();
+        self.last_ping_opt = Some(now);
+        self.outstanding.insert(payload, now);
+        self.stats_arc.lock().unwrap().pings_sent += 1;
+        true
+    }
+
+    fn record_pong(&mut self, payload: &[u8]) {
+        let sent_opt = <[u8; 8]>::try_from(payload)
+            .ok()
+            .and_then(|bytes| self.outstanding.remove(&u64::from_be_bytes(bytes)));
+        if let Some(sent) = sent_opt {
+            self.stats_arc
+                .lock()
+                .unwrap()
+                .round_trip_times
+                .push(sent.elapsed());
+        }
+    }
 }
 
 pub struct MockWebSocketsServerStopHandle {
     index: u64,
     log: bool,
+    expected_requests: Vec<ExpectedRequest>,
+    received_headers_arc: Arc<Mutex<Vec<(String, String)>>>,
+    negotiation_arc: Arc<Mutex<(Vec<String>, String)>>,
+    ping_stats_arc: Arc<Mutex<PingStats>>,
+    bytes_received_arc: Arc<Mutex<u64>>,
+    rejected_connections_arc: Arc<Mutex<usize>>,
+    responses_arc: Arc<Mutex<Vec<OwnedMessage>>>,
     requests_arc: Arc<Mutex<Vec<Result<MessageBody, String>>>>,
     looping_rx: Receiver<()>,
     stop_tx: Sender<bool>,
@@ -45,11 +218,48 @@ impl MockWebSocketsServer {
             log: false,
             port,
             protocol: NODE_UI_PROTOCOL.to_string(),
+            accepted_protocols: vec![],
+            reject_status_opt: None,
             responses_arc: Arc::new(Mutex::new(vec![])),
+            context_responses: RefCell::new(HashMap::new()),
             signal_sender: RefCell::new(None),
+            broadcast_interval_opt: None,
+            broadcast_opcode_filter_opt: None,
+            expected_requests: vec![],
+            handshake_delay_opt: None,
+            echo_mode: false,
+            required_headers: vec![],
+            ping_interval_opt: None,
+            start_delay_opt: None,
+            response_rules: vec![],
+            release_gate_opt: None,
+            released_all: Cell::new(false),
+            black_hole: false,
+            close_after_messages_opt: None,
         }
     }
 
+    // A session file is a JSON array of messages in wire format, queued as responses in order.
+    pub fn from_recording(port: u16, path: PathBuf) -> Self {
+        let json = fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Couldn't read recorded session {:?}: {:?}", path, e));
+        let messages: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap_or_else(|e| {
+            panic!(
+                "Recorded session {:?} should be a JSON array of messages: {:?}",
+                path, e
+            )
+        });
+        messages
+            .into_iter()
+            .fold(Self::new(port), |server, message| match message {
+                serde_json::Value::Object(_) => server.queue_string(&message.to_string()),
+                other => panic!(
+                    "Recorded session {:?} contains a non-message: {}",
+                    path, other
+                ),
+            })
+    }
+
     pub fn port(&self) -> u16 {
         self.port
     }
@@ -62,16 +272,122 @@ impl MockWebSocketsServer {
         self.queue_owned_message(OwnedMessage::Text(string.to_string()))
     }
 
+    // Sent verbatim as the answer to a conversational request, for exercising the client's parser.
+    pub fn queue_malformed(self, raw: &str) -> Self {
+        self.queue_string(raw)
+    }
+
     pub fn queue_owned_message(self, msg: OwnedMessage) -> Self {
         self.responses_arc.lock().unwrap().push(msg);
         self
     }
 
+    // Answers a conversational request with this context ID ahead of anything in the global queue.
+    pub fn queue_response_for_context(self, context_id: u64, message: MessageBody) -> Self {
+        self.context_responses
+            .borrow_mut()
+            .entry(context_id)
+            .or_insert_with(VecDeque::new)
+            .push_back(OwnedMessage::Text(UiTrafficConverter::new_marshal(message)));
+        self
+    }
+
+    // Rules are tried in registration order against every conversational request and are never
+    // used up; the first match wins over both the per-context and the global queue.
+    pub fn respond_matching(
+        mut self,
+        predicate: impl Fn(&MessageBody) -> bool + Send + 'static,
+        body: MessageBody,
+    ) -> Self {
+        self.response_rules.push((Box::new(predicate), body));
+        self
+    }
+
+    // Listed in the server's order of preference; replaces the single protocol the server accepts.
+    pub fn accepted_protocols(mut self, protocols: Vec<String>) -> Self {
+        self.accepted_protocols = protocols;
+        self
+    }
+
+    // A handshake offering no acceptable protocol is refused with this status instead of 400.
+    pub fn reject_status(mut self, status: u16) -> Self {
+        self.reject_status_opt = Some(status);
+        self
+    }
+
     pub fn inject_signal_sender(self, sender: Sender<()>) -> Self {
         self.signal_sender.replace(Some(sender));
         self
     }
 
+    // Every queued response or broadcast waits for a release from the handle before it's sent.
+    pub fn with_release_gate(mut self) -> (Self, ReleaseHandle) {
+        let (release_tx, release_rx) = unbounded();
+        self.release_gate_opt = Some(release_rx);
+        (self, ReleaseHandle { release_tx })
+    }
+
+    pub fn broadcast_interval(mut self, interval: Duration) -> Self {
+        self.broadcast_interval_opt = Some(interval);
+        self
+    }
+
+    pub fn only_broadcasts_with_opcode(mut self, opcode: &str) -> Self {
+        self.broadcast_opcode_filter_opt = Some(opcode.to_string());
+        self
+    }
+
+    // Checked against the recording by MockWebSocketsServerStopHandle::stop_and_verify().
+    pub fn expect_requests(mut self, expected_requests: Vec<ExpectedRequest>) -> Self {
+        self.expected_requests = expected_requests;
+        self
+    }
+
+    // The TCP connection is accepted at once, but the WebSocket handshake stalls for this long.
+    pub fn handshake_delay(mut self, delay: Duration) -> Self {
+        self.handshake_delay_opt = Some(delay);
+        self
+    }
+
+    // Conversational requests are recorded but never answered, and the connection stays open,
+    // as with a Node that has hung; unlike an empty queue, not even the sentinel comes back.
+    pub fn black_hole_responses(mut self) -> Self {
+        self.black_hole = true;
+        self
+    }
+
+    // Once this many incoming messages are recorded, the server answers the last one as usual,
+    // then sends Close and stops serving.
+    pub fn close_after_messages(mut self, limit: usize) -> Self {
+        self.close_after_messages_opt = Some(limit);
+        self
+    }
+
+    // Every recognizable incoming message is sent straight back; queued responses are ignored.
+    pub fn echo_mode(mut self) -> Self {
+        self.echo_mode = true;
+        self
+    }
+
+    // A handshake without this header value gets 403 Forbidden and is recorded as an error.
+    pub fn require_header(mut self, name: &str, value: &str) -> Self {
+        self.required_headers
+            .push((name.to_string(), value.to_string()));
+        self
+    }
+
+    // The pongs that answer these pings are tallied by stop_with_ping_stats().
+    pub fn ping_interval(mut self, interval: Duration) -> Self {
+        self.ping_interval_opt = Some(interval);
+        self
+    }
+
+    // start() no longer needs a grace period, but this restores one for tests that want it.
+    pub fn start_delay(mut self, delay: Duration) -> Self {
+        self.start_delay_opt = Some(delay);
+        self
+    }
+
     pub fn write_logs(mut self) -> Self {
         self.log = true;
         self
@@ -84,30 +400,111 @@ impl MockWebSocketsServer {
             *guard += 1;
             index
         };
-        let server_arc = Arc::new(Mutex::new(
-            Server::bind(SocketAddr::new(localhost(), self.port)).unwrap(),
+        let listener_arc = Arc::new(Mutex::new(
+            TcpListener::bind(SocketAddr::new(localhost(), self.port)).unwrap(),
         ));
         let requests_arc = Arc::new(Mutex::new(vec![]));
         let inner_requests_arc = requests_arc.clone();
+        let responses_arc = self.responses_arc.clone();
         let inner_responses_arc = self.responses_arc.clone();
         let stop_pair: (Sender<bool>, Receiver<bool>) = unbounded();
         let (stop_tx, stop_rx) = stop_pair;
         let (ready_tx, ready_rx) = unbounded();
         let (looping_tx, looping_rx) = unbounded();
         let do_log = self.log;
+        let start_delay_opt = self.start_delay_opt;
+        let expected_requests = self.expected_requests.clone();
+        let received_headers_arc = Arc::new(Mutex::new(vec![]));
+        let inner_received_headers_arc = received_headers_arc.clone();
+        let negotiation_arc = Arc::new(Mutex::new((vec![], String::new())));
+        let inner_negotiation_arc = negotiation_arc.clone();
+        let ping_stats_arc = Arc::new(Mutex::new(PingStats::default()));
+        let bytes_received_arc = Arc::new(Mutex::new(0_u64));
+        let inner_bytes_received_arc = bytes_received_arc.clone();
+        let rejected_connections_arc = Arc::new(Mutex::new(0_usize));
+        let inner_rejected_connections_arc = rejected_connections_arc.clone();
+        let mut ping_tracker_opt = self
+            .ping_interval_opt
+            .map(|interval| PingTracker::new(interval, ping_stats_arc.clone()));
         log(do_log, index, "Starting background thread");
         let join_handle = thread::spawn(move || {
-            let mut server = server_arc.lock().unwrap();
+            let listener = listener_arc.lock().unwrap();
             let mut requests = inner_requests_arc.lock().unwrap();
-            ready_tx.send(()).unwrap();
             log(do_log, index, "Waiting for upgrade");
-            let upgrade = server.accept().unwrap();
-            if upgrade.protocols().iter().all(|p| *p != self.protocol) {
-                panic!("Unrecognized protocol(s): {:?}", upgrade.protocols())
+            // The listener is already bound, so a client connecting before accept() is reached
+            // waits in the backlog rather than being refused.
+            ready_tx.send(()).unwrap();
+            let upgrade = Self::read_handshake(&listener).unwrap();
+            let offered_protocols = upgrade.protocols().to_vec();
+            let chosen_protocol = match self.choose_protocol_opt(&offered_protocols) {
+                Some(protocol) => protocol,
+                None => {
+                    log(
+                        do_log,
+                        index,
+                        &format!(
+                            "Rejecting handshake: unrecognized protocol(s) {:?}",
+                            offered_protocols
+                        ),
+                    );
+                    requests.push(Err(format!(
+                        "unrecognized protocol(s) {:?}",
+                        offered_protocols
+                    )));
+                    match self.reject_status_opt {
+                        None => {
+                            let _ = upgrade.reject();
+                        }
+                        Some(status) => {
+                            let mut stream = upgrade.stream;
+                            let _ = stream.write_all(
+                                format!(
+                                    "HTTP/1.1 {} Rejected\r\nContent-Length: 0\r\n\r\n",
+                                    status
+                                )
+                                .as_bytes(),
+                            );
+                        }
+                    }
+                    let _ = looping_tx.send(());
+                    return;
+                }
+            };
+            *inner_negotiation_arc.lock().unwrap() = (offered_protocols, chosen_protocol.clone());
+            let mut upgrade = upgrade.use_protocol(chosen_protocol);
+            let received_headers = upgrade
+                .request
+                .headers
+                .iter()
+                .map(|header| (header.name().to_string(), header.value_string()))
+                .collect::<Vec<(String, String)>>();
+            if let Some(name) = self.mismatched_header_opt(&received_headers) {
+                log(
+                    do_log,
+                    index,
+                    &format!("Rejecting handshake: header {} mismatch", name),
+                );
+                requests.push(Err(format!("header {} mismatch", name)));
+                *inner_received_headers_arc.lock().unwrap() = received_headers;
+                let _ = upgrade
+                    .stream
+                    .write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n");
+                let _ = looping_tx.send(());
+                return;
+            }
+            *inner_received_headers_arc.lock().unwrap() = received_headers;
+            if let Some(delay) = self.handshake_delay_opt {
+                log(
+                    do_log,
+                    index,
+                    &format!("Delaying handshake for {:?}", delay),
+                );
+                thread::sleep(delay);
             }
             log(do_log, index, "Waiting for handshake");
             let mut client = upgrade.accept().unwrap();
             client.set_nonblocking(true).unwrap();
+            listener.set_nonblocking(true).unwrap();
             match looping_tx.send(()) {
                 Ok(_) => (),
                 Err(e) => {
@@ -124,17 +521,40 @@ impl MockWebSocketsServer {
             }
             log(do_log, index, "Entering background loop");
             loop {
-                log(do_log, index, "Checking for fire-and-forget messages");
-                self.handle_all_f_f_messages_introducing_the_queue(
-                    &mut client,
-                    &inner_responses_arc,
+                Self::reject_surplus_connections(
+                    &listener,
+                    &inner_rejected_connections_arc,
                     index,
                     do_log,
                 );
+                if let Some(ping_tracker) = ping_tracker_opt.as_mut() {
+                    if !ping_tracker.ping_if_due(&mut client) {
+                        log(do_log, index, "Couldn't send Ping; client is gone");
+                        break;
+                    }
+                }
+                if !self.echo_mode {
+                    log(do_log, index, "Checking for fire-and-forget messages");
+                    self.handle_all_f_f_messages_introducing_the_queue(
+                        &mut client,
+                        &inner_responses_arc,
+                        index,
+                        do_log,
+                    );
+                }
                 log(do_log, index, "Checking for message from client");
-                if let Some(incoming) =
-                    Self::handle_incoming_msg_raw(client.recv_message(), do_log, index)
-                {
+                let raw_incoming = client.recv_message();
+                if let Ok(OwnedMessage::Pong(payload)) = &raw_incoming {
+                    log(do_log, index, "Received Pong");
+                    if let Some(ping_tracker) = ping_tracker_opt.as_mut() {
+                        ping_tracker.record_pong(payload);
+                    }
+                } else if let Some(incoming) = Self::handle_incoming_msg_raw(
+                    raw_incoming,
+                    &inner_bytes_received_arc,
+                    do_log,
+                    index,
+                ) {
                     log(
                         do_log,
                         index,
@@ -143,16 +563,26 @@ impl MockWebSocketsServer {
                     requests.push(incoming.clone());
                     if let Ok(message_body) = incoming {
                         match message_body.path {
+                            _ if self.echo_mode => {
+                                log(do_log, index, "Echoing message back");
+                                client
+                                    .send_message(&OwnedMessage::Text(
+                                        UiTrafficConverter::new_marshal(message_body),
+                                    ))
+                                    .unwrap()
+                            }
                             MessagePath::Conversation(_) => {
-                                if Self::handle_conversational_incoming_message(
-                                    &mut client,
-                                    &inner_responses_arc,
-                                    index,
-                                    do_log,
-                                )
-                                .not()
+                                if self
+                                    .handle_conversational_incoming_message(
+                                        &mut client,
+                                        &inner_responses_arc,
+                                        &message_body,
+                                        index,
+                                        do_log,
+                                    )
+                                    .not()
                                 {
-                                    break; //"disconnect" received
+                                    break; //"disconnect" or "reset" received
                                 }
                             }
 
@@ -172,6 +602,15 @@ impl MockWebSocketsServer {
                         );
                         panic!("Unrecognizable incoming message received; you should refrain from sending some meaningless garbage to the server: {:?}", incoming)
                     }
+                    if Some(requests.len()) == self.close_after_messages_opt {
+                        log(
+                            do_log,
+                            index,
+                            &format!("Closing after {} incoming messages", requests.len()),
+                        );
+                        let _ = client.send_message(&OwnedMessage::Close(None));
+                        break;
+                    }
                 }
                 log(do_log, index, "Checking for termination directive");
                 if let Ok(kill) = stop_rx.try_recv() {
@@ -195,10 +634,19 @@ impl MockWebSocketsServer {
             log(do_log, index, "Background thread terminated");
         });
         ready_rx.recv().unwrap();
-        thread::sleep(Duration::from_millis(250));
+        if let Some(delay) = start_delay_opt {
+            thread::sleep(delay);
+        }
         MockWebSocketsServerStopHandle {
             index,
             log: do_log,
+            expected_requests,
+            received_headers_arc,
+            negotiation_arc,
+            ping_stats_arc,
+            bytes_received_arc,
+            rejected_connections_arc,
+            responses_arc,
             requests_arc,
             looping_rx,
             stop_tx,
@@ -208,9 +656,19 @@ impl MockWebSocketsServer {
 
     fn handle_incoming_msg_raw(
         incoming: WebSocketResult<OwnedMessage>,
+        bytes_received_arc: &Arc<Mutex<u64>>,
         do_log: bool,
         index: u64,
     ) -> Option<Result<MessageBody, String>> {
+        match &incoming {
+            Ok(OwnedMessage::Text(json)) => {
+                *bytes_received_arc.lock().unwrap() += json.len() as u64
+            }
+            Ok(OwnedMessage::Binary(bytes)) => {
+                *bytes_received_arc.lock().unwrap() += bytes.len() as u64
+            }
+            _ => (),
+        }
         match incoming {
             Err(WebSocketError::NoDataAvailable) => {
                 log(do_log, index, "No data available");
@@ -222,7 +680,7 @@ impl MockWebSocketsServer {
             }
             Err(e) => Some(Err(format!("Error serving WebSocket: {:?}", e))),
             Ok(OwnedMessage::Text(json)) => {
-                log(do_log, index, &format!("Received '{}'", json));
+                log(do_log, index, &format!("Received '{}'", prettify(&json)));
                 Some(match UiTrafficConverter::new_unmarshal_from_ui(&json, 0) {
                     Ok(msg) => Ok(msg.body),
                     Err(_) => Err(json),
@@ -242,59 +700,258 @@ impl MockWebSocketsServer {
         index: u64,
         do_log: bool,
     ) {
-        let mut counter = 0usize;
         let sender_opt = self.signal_sender.clone().take();
-        loop {
-            let signalization_required = sender_opt.is_some() && counter == 1;
+        let (broadcasts, signal_before_opt) = {
             let mut inner_responses_vec = inner_responses_arc.lock().unwrap();
-            if inner_responses_vec.is_empty() {
-                break;
+            let plan = self.plan_broadcasts(&inner_responses_vec, sender_opt.is_some());
+            plan.held_back.iter().for_each(|position| {
+                log(
+                    do_log,
+                    index,
+                    &format!(
+                        "Holding back a fire-and-forget message with opcode '{}'",
+                        Self::fire_and_forget_opcode_opt(&inner_responses_vec[*position])
+                            .expect("held back a non-broadcast")
+                    ),
+                )
+            });
+            if plan.to_send.len() + plan.held_back.len() < inner_responses_vec.len() {
+                log(
+                    do_log,
+                    index,
+                    "No fire-and-forget message found; heading over to conversational messages",
+                );
             }
-            let temporarily_owned = inner_responses_vec.remove(0);
-            if match &temporarily_owned {
-                OwnedMessage::Text(text) =>
-                    match UiTrafficConverter::new_unmarshal_to_ui(text, MessageTarget::AllClients)
-                    {
-                        Ok(msg) => match msg.body.path {
-                            MessagePath::FireAndForget => {
-                                if signalization_required {
-                                    log(do_log,index,"Sending a signal between the first two fire-and-forget messages");
-                                    sender_opt.as_ref().unwrap().send(()).unwrap()
-                                }
-                                client.send_message(&temporarily_owned).unwrap();
-                                log(do_log, index, "Sending a fire-and-forget message to the UI");
-                                true
-                            }
-                            _ => false
-                        }
-                        _ => false
-                    }
-                _ => false
-            }.not() {
-                inner_responses_vec.insert(0, temporarily_owned);
-                log(do_log, index, "No fire-and-forget message found; heading over to conversational messages");
-                break
+            (
+                Self::take_planned(&mut inner_responses_vec, &plan.to_send),
+                plan.signal_before_opt,
+            )
+        };
+        broadcasts
+            .into_iter()
+            .enumerate()
+            .for_each(|(send_index, broadcast)| {
+                if signal_before_opt == Some(send_index) {
+                    log(
+                        do_log,
+                        index,
+                        "Sending a signal between the first two fire-and-forget messages",
+                    );
+                    sender_opt.as_ref().unwrap().send(()).unwrap()
+                }
+                self.wait_for_release(index, do_log);
+                client.send_message(&broadcast).unwrap();
+                log(do_log, index, "Sending a fire-and-forget message to the UI");
+                thread::sleep(
+                    self.broadcast_interval_opt
+                        .unwrap_or_else(|| Duration::from_millis(1)),
+                );
+            })
+    }
+
+    // Broadcasts at the head of the queue go out now; the first conversational response stops
+    // the scan, so anything behind it waits for that conversation. Held-back broadcasts stay.
+    fn plan_broadcasts(&self, queue: &[OwnedMessage], signal_required: bool) -> BroadcastPlan {
+        let mut plan = BroadcastPlan::default();
+        for (position, message) in queue.iter().enumerate() {
+            match Self::fire_and_forget_opcode_opt(message) {
+                Some(opcode) if self.is_held_back(&opcode) => plan.held_back.push(position),
+                Some(_) => plan.to_send.push(position),
+                None => break,
+            }
+        }
+        if signal_required && plan.to_send.len() > 1 {
+            plan.signal_before_opt = Some(1);
+        }
+        plan
+    }
+
+    // Removes the messages at the given ascending positions, keeping their order. Removing from
+    // the back first keeps the earlier positions valid.
+    fn take_planned(queue: &mut Vec<OwnedMessage>, positions: &[usize]) -> Vec<OwnedMessage> {
+        let mut taken = positions
+            .iter()
+            .rev()
+            .map(|position| queue.remove(*position))
+            .collect::<Vec<OwnedMessage>>();
+        taken.reverse();
+        taken
+    }
+
+    fn choose_protocol_opt(&self, offered_protocols: &[String]) -> Option<String> {
+        let single_protocol = [self.protocol.clone()];
+        let accepted_protocols = if self.accepted_protocols.is_empty() {
+            &single_protocol[..]
+        } else {
+            &self.accepted_protocols[..]
+        };
+        accepted_protocols
+            .iter()
+            .find(|protocol| offered_protocols.contains(protocol))
+            .cloned()
+    }
+
+    fn mismatched_header_opt(&self, received_headers: &[(String, String)]) -> Option<String> {
+        self.required_headers
+            .iter()
+            .find(|(name, value)| {
+                find_header(received_headers, name).as_deref() != Some(value.as_str())
+            })
+            .map(|(name, _)| name.clone())
+    }
+
+    fn fire_and_forget_opcode_opt(message: &OwnedMessage) -> Option<String> {
+        match message {
+            OwnedMessage::Text(text) => {
+                match UiTrafficConverter::new_unmarshal_to_ui(text, MessageTarget::AllClients) {
+                    Ok(msg) if msg.body.path == MessagePath::FireAndForget => Some(msg.body.opcode),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn is_held_back(&self, opcode: &str) -> bool {
+        match &self.broadcast_opcode_filter_opt {
+            Some(only_opcode) => only_opcode != opcode,
+            None => false,
+        }
+    }
+
+    fn read_handshake(listener: &TcpListener) -> Result<Upgrade<TcpStream>, String> {
+        let (stream, _) = listener
+            .accept()
+            .map_err(|e| format!("Couldn't accept connection: {:?}", e))?;
+        stream
+            .into_ws()
+            .map_err(|(_, _, _, e)| format!("Couldn't read handshake: {:?}", e))
+    }
+
+    // The server serves a single client; anyone else is let in only to be told so and shown out.
+    fn reject_surplus_connections(
+        listener: &TcpListener,
+        rejected_connections_arc: &Arc<Mutex<usize>>,
+        index: u64,
+        do_log: bool,
+    ) {
+        while let Ok((stream, _)) = listener.accept() {
+            log(do_log, index, "Rejecting connection beyond capacity");
+            // A client that connects and then says nothing mustn't stall the loop
+            let _ = stream.set_nonblocking(false);
+            let _ = stream.set_read_timeout(Some(SURPLUS_HANDSHAKE_TIMEOUT));
+            if let Ok(upgrade) = stream.into_ws() {
+                if let Ok(mut client) = upgrade.accept() {
+                    let _ = client.send_message(&OwnedMessage::Close(Some(CloseData::new(
+                        POLICY_VIOLATION_CLOSE_CODE,
+                        "Server is already serving a client".to_string(),
+                    ))));
+                }
+            }
+            *rejected_connections_arc.lock().unwrap() += 1;
+        }
+    }
+
+    // With SO_LINGER at zero, dropping the socket sends RST instead of the orderly FIN.
+    #[cfg(not(target_os = "windows"))]
+    fn arrange_reset_on_drop(client: &Client<TcpStream>) {
+        use nix::sys::socket::{setsockopt, sockopt};
+        use std::os::unix::io::AsRawFd;
+        let linger = nix::libc::linger {
+            l_onoff: 1,
+            l_linger: 0,
+        };
+        setsockopt(client.stream_ref().as_raw_fd(), sockopt::Linger, &linger)
+            .expect("Couldn't set SO_LINGER");
+    }
+
+    // No SO_LINGER without an extra dependency here; the connection just closes.
+    #[cfg(target_os = "windows")]
+    fn arrange_reset_on_drop(_client: &Client<TcpStream>) {}
+
+    // The matching body is sent with the request's context ID, so one rule can serve many requests.
+    fn matching_response_opt(&self, request: &MessageBody) -> Option<OwnedMessage> {
+        self.response_rules
+            .iter()
+            .find(|(predicate, _)| predicate(request))
+            .map(|(_, body)| {
+                let mut body = body.clone();
+                body.path = request.path;
+                OwnedMessage::Text(UiTrafficConverter::new_marshal(body))
+            })
+    }
+
+    fn context_response_opt(&self, context_id: u64) -> Option<OwnedMessage> {
+        self.context_responses
+            .borrow_mut()
+            .get_mut(&context_id)
+            .and_then(|queue| queue.pop_front())
+    }
+
+    fn wait_for_release(&self, index: u64, do_log: bool) {
+        let release_rx = match &self.release_gate_opt {
+            Some(release_rx) if !self.released_all.get() => release_rx,
+            _ => return,
+        };
+        log(do_log, index, "Waiting for release before sending");
+        match release_rx.recv() {
+            Ok(ReleaseOrder::One) => log(do_log, index, "Released one message"),
+            Ok(ReleaseOrder::All) | Err(_) => {
+                log(do_log, index, "Released all messages");
+                self.released_all.set(true)
             }
-            thread::sleep(Duration::from_millis(1));
-            counter += 1;
-            //for true, we keep looping
         }
     }
 
     fn handle_conversational_incoming_message(
+        &self,
         client: &mut Client<TcpStream>,
         inner_responses_arc: &Arc<Mutex<Vec<OwnedMessage>>>,
+        request: &MessageBody,
         index: u64,
         do_log: bool,
     ) -> bool {
-        let mut temporary_access_to_inner_responses_arc = inner_responses_arc.lock().unwrap();
-        if temporary_access_to_inner_responses_arc.len() != 0 {
-            match temporary_access_to_inner_responses_arc.remove(0) {
+        if self.black_hole {
+            log(do_log, index, "Swallowing request without a response");
+            return true;
+        }
+        let context_id = match request.path {
+            MessagePath::Conversation(context_id) => context_id,
+            MessagePath::FireAndForget => 0,
+        };
+        let response_opt = if let Some(response) = self.matching_response_opt(request) {
+            log(do_log, index, "Found a response rule matching the request");
+            Some(response)
+        } else if let Some(response) = self.context_response_opt(context_id) {
+            log(
+                do_log,
+                index,
+                &format!("Found response queued for context {}", context_id),
+            );
+            Some(response)
+        } else {
+            let mut temporary_access_to_inner_responses_arc = inner_responses_arc.lock().unwrap();
+            temporary_access_to_inner_responses_arc
+                .iter()
+                .position(|message| match Self::fire_and_forget_opcode_opt(message) {
+                    Some(opcode) => !self.is_held_back(&opcode),
+                    None => true,
+                })
+                .map(|position| temporary_access_to_inner_responses_arc.remove(position))
+        };
+        if let Some(response) = response_opt {
+            self.wait_for_release(index, do_log);
+            match response {
                 OwnedMessage::Text(outgoing) => {
                     if outgoing == "disconnect" {
                         log(do_log, index, "Executing 'disconnect' directive");
                         return false;
                     }
+                    if outgoing == "reset" {
+                        log(do_log, index, "Executing 'reset' directive");
+                        Self::arrange_reset_on_drop(client);
+                        return false;
+                    }
                     if outgoing == "close" {
                         log(do_log, index, "Sending Close message");
                         client.send_message(&OwnedMessage::Close(None)).unwrap();
@@ -302,7 +959,7 @@ impl MockWebSocketsServer {
                         log(
                             do_log,
                             index,
-                            &format!("Responding with preset message: '{}'", &outgoing),
+                            &format!("Responding with preset message: '{}'", prettify(&outgoing)),
                         );
                         client.send_message(&OwnedMessage::Text(outgoing)).unwrap()
                     }
@@ -331,6 +988,109 @@ impl MockWebSocketsServerStopHandle {
         self.send_terminate_order(false)
     }
 
+    // Header names as they arrived in the handshake, in arrival order.
+    pub fn received_headers(&self) -> Vec<(String, String)> {
+        self.received_headers_arc.lock().unwrap().clone()
+    }
+
+    pub fn received_header(&self, name: &str) -> Option<String> {
+        find_header(&self.received_headers(), name)
+    }
+
+    // The protocols the client offered, in its order, and the one the server settled on.
+    pub fn negotiation(&self) -> (Vec<String>, String) {
+        self.negotiation_arc.lock().unwrap().clone()
+    }
+
+    // Payload bytes of every text and binary frame received so far; framing overhead isn't counted.
+    pub fn total_bytes_received(&self) -> u64 {
+        *self.bytes_received_arc.lock().unwrap()
+    }
+
+    // Connections turned away because the server was already serving a client.
+    pub fn rejected_connections(&self) -> usize {
+        *self.rejected_connections_arc.lock().unwrap()
+    }
+
+    pub fn stop_and_verify(self) -> Vec<Result<MessageBody, String>> {
+        let expected_requests = self.expected_requests.clone();
+        let requests = self.stop();
+        if let Err(diff) = Self::verify_requests(&expected_requests, &requests) {
+            panic!("Recorded requests don't match expectations:\n{}", diff)
+        }
+        requests
+    }
+
+    fn verify_requests(
+        expected_requests: &[ExpectedRequest],
+        requests: &[Result<MessageBody, String>],
+    ) -> Result<(), String> {
+        let count = expected_requests.len().max(requests.len());
+        let lines = (0..count)
+            .map(|index| {
+                let expected_opt = expected_requests.get(index);
+                let recorded_opt = requests.get(index);
+                let matches = match (expected_opt, recorded_opt) {
+                    (Some(expected), Some(recorded)) => expected.is_met_by(recorded),
+                    _ => false,
+                };
+                (
+                    matches,
+                    format!(
+                        "{} {}: expected {}, recorded {}",
+                        if matches { " " } else { "!" },
+                        index,
+                        expected_opt
+                            .map(|expected| expected.describe())
+                            .unwrap_or_else(|| "nothing".to_string()),
+                        recorded_opt
+                            .map(Self::describe_request)
+                            .unwrap_or_else(|| "nothing".to_string())
+                    ),
+                )
+            })
+            .collect::<Vec<(bool, String)>>();
+        if lines.iter().all(|(matches, _)| *matches) {
+            Ok(())
+        } else {
+            Err(lines
+                .into_iter()
+                .map(|(_, line)| line)
+                .collect::<Vec<String>>()
+                .join("\n"))
+        }
+    }
+
+    fn describe_request(request: &Result<MessageBody, String>) -> String {
+        match request {
+            Ok(body) => match body.path {
+                MessagePath::Conversation(context_id) => {
+                    format!("'{}' in context {}", body.opcode, context_id)
+                }
+                MessagePath::FireAndForget => format!("'{}' (fire-and-forget)", body.opcode),
+            },
+            Err(garbage) => format!("unrecognizable {}", garbage),
+        }
+    }
+
+    pub fn stop_with_ping_stats(self) -> (Vec<Result<MessageBody, String>>, PingStats) {
+        let ping_stats_arc = self.ping_stats_arc.clone();
+        let requests = self.stop();
+        let ping_stats = ping_stats_arc.lock().unwrap().clone();
+        (requests, ping_stats)
+    }
+
+    // Whatever is still in the global response queue after the stop was never asked for.
+    pub fn stop_with_leftovers(self) -> (Vec<Result<MessageBody, String>>, Vec<OwnedMessage>) {
+        let responses_arc = self.responses_arc.clone();
+        let requests = self.stop();
+        let leftovers = match responses_arc.lock() {
+            Ok(guard) => guard.clone(),
+            Err(poison_error) => poison_error.into_inner().clone(),
+        };
+        (requests, leftovers)
+    }
+
     pub fn kill(self) -> Vec<Result<MessageBody, String>> {
         let result = self.send_terminate_order(true);
         thread::sleep(Duration::from_millis(150));
@@ -374,6 +1134,20 @@ impl MockWebSocketsServerStopHandle {
     }
 }
 
+fn find_header(headers: &[(String, String)], name: &str) -> Option<String> {
+    headers
+        .iter()
+        .find(|(candidate, _)| candidate.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.clone())
+}
+
+fn prettify(json: &str) -> String {
+    match UiTrafficConverter::new_unmarshal(json) {
+        Ok(body) => UiTrafficConverter::new_marshal_pretty(body),
+        Err(_) => json.to_string(),
+    }
+}
+
 fn log(log: bool, index: u64, msg: &str) {
     if log {
         eprintln!("MockWebSocketsServer {}: {}", index, msg);
@@ -384,14 +1158,18 @@ fn log(log: bool, index: u64, msg: &str) {
 mod tests {
     use super::*;
     use crate::messages::{
-        CrashReason, FromMessageBody, ToMessageBody, UiChangePasswordRequest,
+        CrashReason, FromMessageBody, SerializableLogLevel, ToMessageBody, UiChangePasswordRequest,
         UiChangePasswordResponse, UiCheckPasswordRequest, UiCheckPasswordResponse,
-        UiConfigurationChangedBroadcast, UiDescriptorRequest, UiDescriptorResponse,
-        UiNewPasswordBroadcast, UiNodeCrashedBroadcast, NODE_UI_PROTOCOL,
+        UiConfigurationChangedBroadcast, UiDescriptorRequest, UiDescriptorResponse, UiLogBroadcast,
+        UiNewPasswordBroadcast, UiNodeCrashedBroadcast, UiUnmarshalError, NODE_UI_PROTOCOL,
     };
-    use crate::test_utils::ui_connection::UiConnection;
+    use crate::test_utils::ui_connection::{UiConnection, UiConnectionError};
+    use crate::test_utils::utils::ensure_node_home_directory_exists;
     use crate::utils::find_free_port;
+    use std::io::Read;
     use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::time::Instant;
+    use websocket::ClientBuilder;
 
     #[test]
     fn conversational_communication_happy_path_with_full_assertion() {
@@ -458,9 +1236,10 @@ mod tests {
         ////////////////////////////////////////////////////////////////////////////////////////////
         let conversation_number_one_response = UiCheckPasswordResponse { matches: false };
         let conversation_number_two_response = UiCheckPasswordResponse { matches: true };
-        let broadcast_number_one = UiConfigurationChangedBroadcast {}.tmb(0);
+        let broadcast_number_one = UiConfigurationChangedBroadcast::default().tmb(0);
         let broadcast_number_two = UiNodeCrashedBroadcast {
             process_id: 0,
+            exit_code_opt: None,
             crash_reason: CrashReason::NoInformation,
         }
         .tmb(0);
@@ -529,6 +1308,1047 @@ mod tests {
         )
     }
 
+    #[test]
+    fn broadcasts_can_be_spaced_out_in_time() {
+        let port = find_free_port();
+        let interval = Duration::from_millis(200);
+        let stop_handle = MockWebSocketsServer::new(port)
+            .broadcast_interval(interval)
+            .queue_response(UiNewPasswordBroadcast {}.tmb(0))
+            .queue_response(UiNewPasswordBroadcast {}.tmb(0))
+            .queue_response(UiNewPasswordBroadcast {}.tmb(0))
+            .start();
+        let mut connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+
+        let arrival_times = (0..3)
+            .map(|_| {
+                let _: UiNewPasswordBroadcast = connection.skip_until_received().unwrap();
+                Instant::now()
+            })
+            .collect::<Vec<Instant>>();
+
+        stop_handle.stop();
+        arrival_times.windows(2).for_each(|pair| {
+            let gap = pair[1].duration_since(pair[0]);
+            assert!(
+                gap >= interval.mul_f32(0.75),
+                "Broadcasts arrived only {:?} apart",
+                gap
+            )
+        });
+    }
+
+    #[test]
+    fn broadcasts_with_other_opcodes_are_held_back_when_filtering() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port)
+            .only_broadcasts_with_opcode("newPassword")
+            .queue_response(
+                UiNodeCrashedBroadcast {
+                    process_id: 0,
+                    exit_code_opt: None,
+                    crash_reason: CrashReason::NoInformation,
+                }
+                .tmb(0),
+            )
+            .queue_response(UiNewPasswordBroadcast {}.tmb(0))
+            .queue_response(
+                UiDescriptorResponse {
+                    node_descriptor_opt: Some("ae15fe6".to_string()),
+                }
+                .tmb(1),
+            )
+            .start();
+        let mut connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+
+        let _: UiNewPasswordBroadcast = connection.skip_until_received().unwrap();
+        let response: UiDescriptorResponse = connection
+            .transact_with_context_id(UiDescriptorRequest {}, 1)
+            .unwrap();
+
+        let requests = stop_handle.stop();
+        assert_eq!(response.node_descriptor_opt, Some("ae15fe6".to_string()));
+        assert_eq!(requests.len(), 1);
+    }
+
+    #[test]
+    fn interleaved_conversations_get_the_responses_queued_for_their_contexts() {
+        let port = find_free_port();
+        let server = MockWebSocketsServer::new(port)
+            .queue_response_for_context(
+                2,
+                UiDescriptorResponse {
+                    node_descriptor_opt: Some("second".to_string()),
+                }
+                .tmb(2),
+            )
+            .queue_response_for_context(
+                1,
+                UiDescriptorResponse {
+                    node_descriptor_opt: Some("first".to_string()),
+                }
+                .tmb(1),
+            )
+            .queue_response(
+                UiDescriptorResponse {
+                    node_descriptor_opt: Some("global".to_string()),
+                }
+                .tmb(3),
+            );
+        let stop_handle = server.start();
+        let mut connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+
+        let first: UiDescriptorResponse = connection
+            .transact_with_context_id(UiDescriptorRequest {}, 1)
+            .unwrap();
+        let third: UiDescriptorResponse = connection
+            .transact_with_context_id(UiDescriptorRequest {}, 3)
+            .unwrap();
+        let second: UiDescriptorResponse = connection
+            .transact_with_context_id(UiDescriptorRequest {}, 2)
+            .unwrap();
+
+        stop_handle.stop();
+        assert_eq!(first.node_descriptor_opt, Some("first".to_string()));
+        assert_eq!(second.node_descriptor_opt, Some("second".to_string()));
+        assert_eq!(third.node_descriptor_opt, Some("global".to_string()));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn reset_directive_drops_the_connection_abruptly() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port)
+            .queue_string("reset")
+            .start();
+        let mut connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+
+        let result: Result<UiDescriptorResponse, UiConnectionError> =
+            connection.transact(UiDescriptorRequest {});
+
+        stop_handle.stop();
+        match result {
+            Err(UiConnectionError::Transport(WebSocketError::IoError(e)))
+                if e.kind() == std::io::ErrorKind::ConnectionReset => {}
+            x => panic!("Expected a connection reset, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn a_second_client_is_turned_away_while_the_first_is_served() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port)
+            .queue_response(
+                UiDescriptorResponse {
+                    node_descriptor_opt: Some("served".to_string()),
+                }
+                .tmb(1),
+            )
+            .start();
+        let mut first = UiConnection::new(port, NODE_UI_PROTOCOL);
+        let mut second = UiConnection::new(port, NODE_UI_PROTOCOL);
+        let before = Instant::now();
+
+        let rejection = second.skip_until_received::<UiDescriptorResponse>();
+
+        let elapsed = before.elapsed();
+        let response: UiDescriptorResponse = first
+            .transact_with_context_id(UiDescriptorRequest {}, 1)
+            .unwrap();
+        let rejected_connections = stop_handle.rejected_connections();
+        stop_handle.stop();
+        match rejection {
+            Err(UiConnectionError::Closed(Some(close_data))) => {
+                assert_eq!(close_data.status_code, POLICY_VIOLATION_CLOSE_CODE)
+            }
+            x => panic!("Expected a policy close, got {:?}", x),
+        }
+        assert!(elapsed < Duration::from_millis(1000), "{:?}", elapsed);
+        assert_eq!(response.node_descriptor_opt, Some("served".to_string()));
+        assert_eq!(rejected_connections, 1);
+    }
+
+    #[test]
+    fn a_silent_surplus_client_does_not_stall_the_first() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port)
+            .queue_response(
+                UiDescriptorResponse {
+                    node_descriptor_opt: Some("served".to_string()),
+                }
+                .tmb(1),
+            )
+            .start();
+        let mut first = UiConnection::new(port, NODE_UI_PROTOCOL);
+        let _silent = TcpStream::connect(SocketAddr::new(localhost(), port)).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        let before = Instant::now();
+
+        let response: UiDescriptorResponse = first
+            .transact_with_context_id(UiDescriptorRequest {}, 1)
+            .unwrap();
+
+        let elapsed = before.elapsed();
+        let rejected_connections = stop_handle.rejected_connections();
+        stop_handle.stop();
+        assert_eq!(response.node_descriptor_opt, Some("served".to_string()));
+        assert!(elapsed < Duration::from_millis(1000), "{:?}", elapsed);
+        assert_eq!(rejected_connections, 1);
+    }
+
+    #[test]
+    fn requests_are_answered_by_the_first_matching_rule() {
+        let port = find_free_port();
+        let password_is = |expected: &'static str| {
+            move |request: &MessageBody| match UiCheckPasswordRequest::fmb(request.clone()) {
+                Ok((request, _)) => request.db_password_opt.as_deref() == Some(expected),
+                Err(_) => false,
+            }
+        };
+        let stop_handle = MockWebSocketsServer::new(port)
+            .respond_matching(
+                password_is("correct"),
+                UiCheckPasswordResponse { matches: true }.tmb(0),
+            )
+            .respond_matching(
+                |request| request.opcode == "checkPassword",
+                UiCheckPasswordResponse { matches: false }.tmb(0),
+            )
+            .queue_response(
+                UiDescriptorResponse {
+                    node_descriptor_opt: Some("from the queue".to_string()),
+                }
+                .tmb(4),
+            )
+            .start();
+        let mut connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+        let check = |password: &str| UiCheckPasswordRequest {
+            db_password_opt: Some(password.to_string()),
+        };
+
+        let right: UiCheckPasswordResponse = connection
+            .transact_with_context_id(check("correct"), 1)
+            .unwrap();
+        let wrong: UiCheckPasswordResponse = connection
+            .transact_with_context_id(check("booga"), 2)
+            .unwrap();
+        let right_again: UiCheckPasswordResponse = connection
+            .transact_with_context_id(check("correct"), 3)
+            .unwrap();
+        let unmatched: UiDescriptorResponse = connection
+            .transact_with_context_id(UiDescriptorRequest {}, 4)
+            .unwrap();
+
+        stop_handle.stop();
+        assert!(right.matches);
+        assert!(!wrong.matches);
+        assert!(right_again.matches);
+        assert_eq!(
+            unmatched.node_descriptor_opt,
+            Some("from the queue".to_string())
+        );
+    }
+
+    #[test]
+    fn echo_mode_reflects_messages_with_their_context_ids() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port)
+            .queue_response(
+                UiDescriptorResponse {
+                    node_descriptor_opt: Some("ignored".to_string()),
+                }
+                .tmb(1),
+            )
+            .echo_mode()
+            .start();
+        let mut connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+        let request = UiCheckPasswordRequest {
+            db_password_opt: Some("booga".to_string()),
+        };
+        let broadcast = UiNewPasswordBroadcast {};
+
+        let echoed_request: UiCheckPasswordRequest = connection
+            .transact_with_context_id(request.clone(), 1234)
+            .unwrap();
+        connection.send(broadcast.clone());
+        let echoed_broadcast: UiNewPasswordBroadcast = connection.skip_until_received().unwrap();
+
+        stop_handle.stop();
+        assert_eq!(echoed_request, request);
+        assert_eq!(echoed_broadcast, broadcast);
+    }
+
+    #[test]
+    fn handshake_headers_are_made_available() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port)
+            .require_header("Sec-WebSocket-Protocol", NODE_UI_PROTOCOL)
+            .start();
+        let _connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+
+        let protocol_opt = stop_handle.received_header("sec-websocket-protocol");
+
+        let requests = stop_handle.stop();
+        assert_eq!(protocol_opt, Some(NODE_UI_PROTOCOL.to_string()));
+        assert_eq!(requests, vec![]);
+    }
+
+    #[test]
+    fn handshake_without_required_header_is_forbidden() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port)
+            .require_header("Origin", "https://masq.ai")
+            .start();
+
+        let result = UiConnection::make(port, NODE_UI_PROTOCOL);
+
+        let received_origin_opt = stop_handle.received_header("Origin");
+        let requests = stop_handle.stop();
+        assert!(result.is_err());
+        assert_eq!(received_origin_opt, None);
+        assert_eq!(requests, vec![Err("header Origin mismatch".to_string())]);
+    }
+
+    #[test]
+    fn unrecognized_protocol_is_refused_with_bad_request() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port).start();
+
+        let result = UiConnection::make(port, "booga");
+
+        let requests = stop_handle.stop();
+        assert!(result.is_err());
+        assert_eq!(
+            requests,
+            vec![Err("unrecognized protocol(s) [\"booga\"]".to_string())]
+        );
+    }
+
+    #[test]
+    fn unrecognized_protocol_is_refused_with_the_configured_status() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port).reject_status(403).start();
+        let mut stream = TcpStream::connect(SocketAddr::new(localhost(), port)).unwrap();
+        stream
+            .write_all(
+                format!(
+                    "GET / HTTP/1.1\r\nHost: {}:{}\r\nUpgrade: websocket\r\n\
+                     Connection: Upgrade\r\nSec-WebSocket-Version: 13\r\n\
+                     Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                     Sec-WebSocket-Protocol: booga\r\n\r\n",
+                    localhost(),
+                    port
+                )
+                .as_bytes(),
+            )
+            .unwrap();
+        let mut response = String::new();
+
+        stream.read_to_string(&mut response).unwrap();
+
+        let requests = stop_handle.stop();
+        assert!(response.starts_with("HTTP/1.1 403 "), "{}", response);
+        assert_eq!(
+            requests,
+            vec![Err("unrecognized protocol(s) [\"booga\"]".to_string())]
+        );
+    }
+
+    #[test]
+    fn offered_and_chosen_protocols_are_recorded() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port).start();
+        let _client = ClientBuilder::new(&format!("ws://{}:{}", localhost(), port))
+            .unwrap()
+            .add_protocol("booga")
+            .add_protocol(NODE_UI_PROTOCOL)
+            .connect_insecure()
+            .unwrap();
+
+        let negotiation = stop_handle.negotiation();
+
+        stop_handle.stop();
+        assert_eq!(
+            negotiation,
+            (
+                vec!["booga".to_string(), NODE_UI_PROTOCOL.to_string()],
+                NODE_UI_PROTOCOL.to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn the_servers_preferred_protocol_wins_the_negotiation() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port)
+            .accepted_protocols(vec!["v2".to_string(), "v1".to_string()])
+            .start();
+        let client = ClientBuilder::new(&format!("ws://{}:{}", localhost(), port))
+            .unwrap()
+            .add_protocol("v1")
+            .add_protocol("v2")
+            .connect_insecure()
+            .unwrap();
+
+        let negotiation = stop_handle.negotiation();
+
+        stop_handle.stop();
+        assert_eq!(
+            negotiation,
+            (vec!["v1".to_string(), "v2".to_string()], "v2".to_string())
+        );
+        assert_eq!(client.protocols(), &["v2".to_string()]);
+    }
+
+    #[test]
+    fn accepted_protocols_fall_back_to_one_the_client_offers() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port)
+            .accepted_protocols(vec!["v3".to_string(), "v1".to_string()])
+            .start();
+        let _client = ClientBuilder::new(&format!("ws://{}:{}", localhost(), port))
+            .unwrap()
+            .add_protocol("v2")
+            .add_protocol("v1")
+            .connect_insecure()
+            .unwrap();
+
+        let (_, chosen_protocol) = stop_handle.negotiation();
+
+        stop_handle.stop();
+        assert_eq!(chosen_protocol, "v1".to_string());
+    }
+
+    #[test]
+    fn pings_are_sent_and_pongs_are_timed() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port)
+            .ping_interval(Duration::from_millis(100))
+            .start();
+        let mut client = ClientBuilder::new(&format!("ws://{}:{}", localhost(), port))
+            .unwrap()
+            .add_protocol(NODE_UI_PROTOCOL)
+            .connect_insecure()
+            .unwrap();
+
+        (0..3).for_each(|_| match client.recv_message().unwrap() {
+            OwnedMessage::Ping(payload) => {
+                client.send_message(&OwnedMessage::Pong(payload)).unwrap()
+            }
+            x => panic!("Expected Ping, got {:?}", x),
+        });
+
+        thread::sleep(Duration::from_millis(200));
+        let (requests, ping_stats) = stop_handle.stop_with_ping_stats();
+        assert_eq!(requests, vec![]);
+        assert!(ping_stats.pings_sent >= 3, "{:?}", ping_stats);
+        assert_eq!(ping_stats.pongs_received(), 3);
+        assert_eq!(ping_stats.pongs_within(Duration::from_secs(1)), 3);
+    }
+
+    #[test]
+    fn pinging_a_departed_client_ends_the_loop_without_a_panic() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port)
+            .ping_interval(Duration::from_millis(10))
+            .start();
+        let client = ClientBuilder::new(&format!("ws://{}:{}", localhost(), port))
+            .unwrap()
+            .add_protocol(NODE_UI_PROTOCOL)
+            .connect_insecure()
+            .unwrap();
+
+        drop(client);
+
+        let time_limit = Instant::now() + Duration::from_secs(5);
+        while !stop_handle.join_handle.is_finished() && Instant::now() < time_limit {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(stop_handle.join_handle.is_finished());
+        assert!(stop_handle.join_handle.join().is_ok());
+    }
+
+    #[test]
+    fn ping_stats_count_only_pongs_within_the_deadline() {
+        let subject = PingStats {
+            pings_sent: 4,
+            round_trip_times: vec![
+                Duration::from_millis(10),
+                Duration::from_millis(500),
+                Duration::from_millis(100),
+            ],
+        };
+
+        assert_eq!(subject.pongs_received(), 3);
+        assert_eq!(subject.pongs_within(Duration::from_millis(100)), 2);
+    }
+
+    #[test]
+    fn client_can_connect_as_soon_as_start_returns() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port)
+            .queue_response(
+                UiDescriptorResponse {
+                    node_descriptor_opt: None,
+                }
+                .tmb(1),
+            )
+            .start();
+
+        let mut connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+        let result: UiDescriptorResponse = connection
+            .transact_with_context_id(UiDescriptorRequest {}, 1)
+            .unwrap();
+
+        stop_handle.stop();
+        assert_eq!(result.node_descriptor_opt, None);
+    }
+
+    #[test]
+    fn start_delay_holds_start_back() {
+        let port = find_free_port();
+        let delay = Duration::from_millis(300);
+        let before = Instant::now();
+
+        let stop_handle = MockWebSocketsServer::new(port).start_delay(delay).start();
+
+        let elapsed = before.elapsed();
+        stop_handle.stop();
+        assert!(elapsed >= delay, "{:?}", elapsed);
+    }
+
+    fn broadcast() -> OwnedMessage {
+        OwnedMessage::Text(UiTrafficConverter::new_marshal(
+            UiNewPasswordBroadcast {}.tmb(0),
+        ))
+    }
+
+    fn crash_broadcast() -> OwnedMessage {
+        OwnedMessage::Text(UiTrafficConverter::new_marshal(
+            UiNodeCrashedBroadcast {
+                process_id: 1234,
+                crash_reason: CrashReason::NoInformation,
+                exit_code_opt: None,
+            }
+            .tmb(0),
+        ))
+    }
+
+    fn conversation() -> OwnedMessage {
+        OwnedMessage::Text(UiTrafficConverter::new_marshal(
+            UiDescriptorResponse {
+                node_descriptor_opt: None,
+            }
+            .tmb(1),
+        ))
+    }
+
+    #[test]
+    fn broadcast_plan_sends_leading_broadcasts_and_signals_before_the_second() {
+        let subject = MockWebSocketsServer::new(0);
+        let queue = vec![broadcast(), broadcast(), broadcast()];
+
+        let result = subject.plan_broadcasts(&queue, true);
+
+        assert_eq!(
+            result,
+            BroadcastPlan {
+                to_send: vec![0, 1, 2],
+                held_back: vec![],
+                signal_before_opt: Some(1),
+            }
+        );
+    }
+
+    #[test]
+    fn broadcast_plan_does_not_signal_for_a_lone_broadcast_or_when_not_asked() {
+        let subject = MockWebSocketsServer::new(0);
+
+        let lone = subject.plan_broadcasts(&[broadcast()], true);
+        let unasked = subject.plan_broadcasts(&[broadcast(), broadcast()], false);
+
+        assert_eq!(lone.signal_before_opt, None);
+        assert_eq!(unasked.signal_before_opt, None);
+    }
+
+    #[test]
+    fn broadcast_plan_stops_at_the_first_conversational_response() {
+        let subject = MockWebSocketsServer::new(0);
+        let queue = vec![broadcast(), conversation(), broadcast()];
+
+        let result = subject.plan_broadcasts(&queue, false);
+
+        assert_eq!(result.to_send, vec![0]);
+        assert_eq!(result.held_back, vec![]);
+    }
+
+    #[test]
+    fn broadcast_plan_sends_nothing_while_a_conversation_leads_the_queue() {
+        let subject = MockWebSocketsServer::new(0);
+        let queue = vec![conversation(), broadcast(), broadcast()];
+
+        let result = subject.plan_broadcasts(&queue, true);
+
+        assert_eq!(result, BroadcastPlan::default());
+    }
+
+    #[test]
+    fn planned_messages_are_taken_from_their_own_positions() {
+        let mut queue = vec![
+            crash_broadcast(),
+            broadcast(),
+            crash_broadcast(),
+            conversation(),
+        ];
+
+        let result = MockWebSocketsServer::take_planned(&mut queue, &[1, 3]);
+
+        assert_eq!(result, vec![broadcast(), conversation()]);
+        assert_eq!(queue, vec![crash_broadcast(), crash_broadcast()]);
+    }
+
+    #[test]
+    fn broadcasts_queued_behind_a_conversation_follow_its_response_in_order() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port)
+            .queue_response(
+                UiDescriptorResponse {
+                    node_descriptor_opt: None,
+                }
+                .tmb(1),
+            )
+            .queue_response(UiNewPasswordBroadcast {}.tmb(0))
+            .queue_response(
+                UiNodeCrashedBroadcast {
+                    process_id: 1234,
+                    crash_reason: CrashReason::NoInformation,
+                    exit_code_opt: None,
+                }
+                .tmb(0),
+            )
+            .start();
+        let mut connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+
+        let response: UiDescriptorResponse = connection
+            .transact_with_context_id(UiDescriptorRequest {}, 1)
+            .unwrap();
+        let first: UiNewPasswordBroadcast = connection.skip_until_received().unwrap();
+        let second: UiNodeCrashedBroadcast = connection.skip_until_received().unwrap();
+
+        stop_handle.stop();
+        assert_eq!(response.node_descriptor_opt, None);
+        assert_eq!(first, UiNewPasswordBroadcast {});
+        assert_eq!(second.process_id, 1234);
+    }
+
+    #[test]
+    fn broadcast_plan_skips_held_back_broadcasts_without_stopping() {
+        let subject = MockWebSocketsServer::new(0).only_broadcasts_with_opcode("newPassword");
+        let queue = vec![
+            crash_broadcast(),
+            broadcast(),
+            crash_broadcast(),
+            broadcast(),
+            conversation(),
+            broadcast(),
+        ];
+
+        let result = subject.plan_broadcasts(&queue, true);
+
+        assert_eq!(
+            result,
+            BroadcastPlan {
+                to_send: vec![1, 3],
+                held_back: vec![0, 2],
+                signal_before_opt: Some(1),
+            }
+        );
+    }
+
+    #[test]
+    fn handshake_can_be_delayed() {
+        let port = find_free_port();
+        let delay = Duration::from_millis(500);
+        let stop_handle = MockWebSocketsServer::new(port)
+            .handshake_delay(delay)
+            .start();
+        let before = Instant::now();
+
+        let _connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+
+        let elapsed = before.elapsed();
+        stop_handle.stop();
+        assert!(elapsed >= delay, "{:?}", elapsed);
+    }
+
+    #[test]
+    fn recorded_session_is_replayed_in_order() {
+        let home_dir = ensure_node_home_directory_exists(
+            "mock_websockets_server",
+            "recorded_session_is_replayed_in_order",
+        );
+        let path = home_dir.join("session.json");
+        let session = format!(
+            "[{},{}]",
+            UiTrafficConverter::new_marshal(
+                UiDescriptorResponse {
+                    node_descriptor_opt: Some("first".to_string())
+                }
+                .tmb(1)
+            ),
+            UiTrafficConverter::new_marshal(UiCheckPasswordResponse { matches: true }.tmb(2))
+        );
+        fs::write(&path, session).unwrap();
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::from_recording(port, path).start();
+        let mut connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+
+        let first: UiDescriptorResponse = connection
+            .transact_with_context_id(UiDescriptorRequest {}, 1)
+            .unwrap();
+        let second: UiCheckPasswordResponse = connection
+            .transact_with_context_id(
+                UiCheckPasswordRequest {
+                    db_password_opt: None,
+                },
+                2,
+            )
+            .unwrap();
+
+        stop_handle.stop();
+        assert_eq!(first.node_descriptor_opt, Some("first".to_string()));
+        assert!(second.matches);
+    }
+
+    #[test]
+    #[should_panic(expected = "contains a non-message: \"disconnect\"")]
+    fn recorded_session_with_non_messages_is_rejected() {
+        let home_dir = ensure_node_home_directory_exists(
+            "mock_websockets_server",
+            "recorded_session_with_non_messages_is_rejected",
+        );
+        let path = home_dir.join("session.json");
+        fs::write(&path, "[\"disconnect\"]").unwrap();
+
+        let _ = MockWebSocketsServer::from_recording(find_free_port(), path);
+    }
+
+    #[test]
+    fn expected_requests_are_verified_on_stop() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port)
+            .queue_response(
+                UiDescriptorResponse {
+                    node_descriptor_opt: None,
+                }
+                .tmb(4),
+            )
+            .expect_requests(vec![
+                ExpectedRequest::new("descriptor", Some(4)),
+                ExpectedRequest::new("unmarshalError", None),
+            ])
+            .start();
+        let mut connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+        let _: UiDescriptorResponse = connection
+            .transact_with_context_id(UiDescriptorRequest {}, 4)
+            .unwrap();
+        connection.send(UiUnmarshalError {
+            message: "message".to_string(),
+            bad_data: "bad data".to_string(),
+        });
+        thread::sleep(Duration::from_millis(200));
+
+        let requests = stop_handle.stop_and_verify();
+
+        assert_eq!(requests.len(), 2);
+    }
+
+    #[test]
+    fn mismatched_requests_are_reported_line_by_line() {
+        let expected_requests = vec![
+            ExpectedRequest::new("descriptor", Some(4)),
+            ExpectedRequest::new("checkPassword", Some(5)),
+            ExpectedRequest::new("unmarshalError", None),
+        ];
+        let requests = vec![
+            Ok(UiDescriptorRequest {}.tmb(4)),
+            Ok(UiDescriptorRequest {}.tmb(6)),
+        ];
+
+        let result = MockWebSocketsServerStopHandle::verify_requests(&expected_requests, &requests);
+
+        assert_eq!(
+            result,
+            Err(vec![
+                "  0: expected 'descriptor' in context 4, recorded 'descriptor' in context 4",
+                "! 1: expected 'checkPassword' in context 5, recorded 'descriptor' in context 6",
+                "! 2: expected 'unmarshalError', recorded nothing",
+            ]
+            .join("\n"))
+        );
+    }
+
+    #[test]
+    fn unexpected_extra_requests_fail_verification() {
+        let requests = vec![Err("Binary([1, 2, 3])".to_string())];
+
+        let result = MockWebSocketsServerStopHandle::verify_requests(&[], &requests);
+
+        assert_eq!(
+            result,
+            Err("! 0: expected nothing, recorded unrecognizable Binary([1, 2, 3])".to_string())
+        );
+    }
+
+    #[test]
+    fn bursts_of_fire_and_forget_messages_are_recorded_in_order() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port).start();
+        let mut connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+        let messages = (0..20)
+            .map(|index| UiUnmarshalError {
+                message: format!("message {}", index),
+                bad_data: format!("bad data {}", index),
+            })
+            .collect::<Vec<UiUnmarshalError>>();
+
+        connection.send_all(messages.clone());
+
+        thread::sleep(Duration::from_millis(2000));
+        let requests = stop_handle.stop();
+        let received = requests
+            .into_iter()
+            .map(|request| UiUnmarshalError::fmb(request.unwrap()).unwrap().0)
+            .collect::<Vec<UiUnmarshalError>>();
+        assert_eq!(received, messages);
+    }
+
+    #[test]
+    fn unrecognizable_frames_are_recorded_as_errors() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port).start();
+        let mut connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+
+        connection.send_owned_message(OwnedMessage::Binary(vec![1, 2, 3]));
+
+        thread::sleep(Duration::from_millis(200));
+        let requests = stop_handle.stop();
+        assert_eq!(requests, vec![Err("Binary([1, 2, 3])".to_string())]);
+    }
+
+    #[test]
+    fn server_closes_after_the_configured_number_of_messages() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port)
+            .queue_response(UiCheckPasswordResponse { matches: true }.tmb(1))
+            .queue_response(UiCheckPasswordResponse { matches: false }.tmb(2))
+            .close_after_messages(2)
+            .start();
+        let mut connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+        let request = UiCheckPasswordRequest {
+            db_password_opt: None,
+        };
+
+        let first: UiCheckPasswordResponse = connection
+            .transact_with_context_id(request.clone(), 1)
+            .unwrap();
+        let second: UiCheckPasswordResponse = connection
+            .transact_with_context_id(request.clone(), 2)
+            .unwrap();
+        let closing = connection.skip_until_received::<UiCheckPasswordResponse>();
+
+        let requests = stop_handle.stop();
+        assert!(first.matches);
+        assert!(!second.matches);
+        match closing {
+            Err(UiConnectionError::Closed(None)) => (),
+            x => panic!("Expected Err(Closed(None)), got {:?}", x),
+        }
+        assert_eq!(
+            requests,
+            vec![Ok(request.clone().tmb(1)), Ok(request.tmb(2))]
+        );
+    }
+
+    #[test]
+    fn malformed_response_is_sent_verbatim() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port)
+            .queue_malformed("{\"payload\": {\"matches\": tru")
+            .start();
+        let mut client = ClientBuilder::new(&format!("ws://{}:{}", localhost(), port))
+            .unwrap()
+            .add_protocol(NODE_UI_PROTOCOL)
+            .connect_insecure()
+            .unwrap();
+        let request = UiCheckPasswordRequest {
+            db_password_opt: None,
+        }
+        .tmb(1);
+        client
+            .send_message(&OwnedMessage::Text(UiTrafficConverter::new_marshal(
+                request.clone(),
+            )))
+            .unwrap();
+
+        let response = client.recv_message().unwrap();
+
+        let requests = stop_handle.stop();
+        assert_eq!(
+            response,
+            OwnedMessage::Text("{\"payload\": {\"matches\": tru".to_string())
+        );
+        assert_eq!(requests, vec![Ok(request)]);
+    }
+
+    #[test]
+    fn unconsumed_responses_are_returned_as_leftovers() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port)
+            .queue_response(UiCheckPasswordResponse { matches: true }.tmb(1))
+            .queue_response(UiCheckPasswordResponse { matches: false }.tmb(2))
+            .queue_string("booga")
+            .start();
+        let mut connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+        let request = UiCheckPasswordRequest {
+            db_password_opt: None,
+        };
+        let _: UiCheckPasswordResponse = connection
+            .transact_with_context_id(request.clone(), 1)
+            .unwrap();
+
+        let (requests, leftovers) = stop_handle.stop_with_leftovers();
+
+        assert_eq!(requests, vec![Ok(request.tmb(1))]);
+        assert_eq!(
+            leftovers,
+            vec![
+                OwnedMessage::Text(UiTrafficConverter::new_marshal(
+                    UiCheckPasswordResponse { matches: false }.tmb(2)
+                )),
+                OwnedMessage::Text("booga".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn queued_log_broadcasts_are_delivered_in_order() {
+        let port = find_free_port();
+        let broadcasts = vec![
+            UiLogBroadcast {
+                msg: "Starting up".to_string(),
+                log_level: SerializableLogLevel::Info,
+            },
+            UiLogBroadcast {
+                msg: "Running low on funds".to_string(),
+                log_level: SerializableLogLevel::Warn,
+            },
+            UiLogBroadcast {
+                msg: "Out of funds".to_string(),
+                log_level: SerializableLogLevel::Error,
+            },
+        ];
+        let stop_handle = broadcasts
+            .iter()
+            .fold(MockWebSocketsServer::new(port), |server, broadcast| {
+                server.queue_response(broadcast.clone().tmb(0))
+            })
+            .start();
+        let mut connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+
+        let received = (0..broadcasts.len())
+            .map(|_| connection.skip_until_received::<UiLogBroadcast>().unwrap())
+            .collect::<Vec<UiLogBroadcast>>();
+
+        let requests = stop_handle.stop();
+        assert_eq!(received, broadcasts);
+        assert_eq!(requests, vec![]);
+    }
+
+    #[test]
+    fn black_hole_records_requests_but_never_answers_them() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port)
+            .queue_response(UiCheckPasswordResponse { matches: true }.tmb(1))
+            .black_hole_responses()
+            .start();
+        let mut connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+        let request = UiCheckPasswordRequest {
+            db_password_opt: None,
+        };
+
+        let result: Result<UiCheckPasswordResponse, UiConnectionError> =
+            connection.transact_with_timeout(request.clone(), 1, Duration::from_millis(300));
+
+        let requests = stop_handle.stop();
+        match result {
+            Err(UiConnectionError::Timeout) => (),
+            x => panic!("Expected Err(Timeout), got {:?}", x),
+        }
+        assert_eq!(requests, vec![Ok(request.tmb(1))]);
+    }
+
+    #[test]
+    fn gated_responses_wait_for_their_release() {
+        let port = find_free_port();
+        let (server, release_handle) = MockWebSocketsServer::new(port)
+            .queue_response(UiCheckPasswordResponse { matches: true }.tmb(1))
+            .queue_response(UiCheckPasswordResponse { matches: false }.tmb(2))
+            .queue_response(UiCheckPasswordResponse { matches: true }.tmb(3))
+            .with_release_gate();
+        let stop_handle = server.start();
+        let mut connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+        let request = || UiCheckPasswordRequest {
+            db_password_opt: None,
+        };
+
+        let held: Result<UiCheckPasswordResponse, UiConnectionError> =
+            connection.transact_with_timeout(request(), 1, Duration::from_millis(300));
+        release_handle.release_one();
+        let first: UiCheckPasswordResponse = connection.skip_until_received().unwrap();
+        release_handle.release_all();
+        let second: UiCheckPasswordResponse = connection
+            .transact_with_timeout(request(), 2, Duration::from_millis(1000))
+            .unwrap();
+        let third: UiCheckPasswordResponse = connection
+            .transact_with_timeout(request(), 3, Duration::from_millis(1000))
+            .unwrap();
+
+        stop_handle.stop();
+        match held {
+            Err(UiConnectionError::Timeout) => (),
+            x => panic!("Expected Err(Timeout), got {:?}", x),
+        }
+        assert!(first.matches);
+        assert!(!second.matches);
+        assert!(third.matches);
+    }
+
+    #[test]
+    fn payload_bytes_of_text_and_binary_frames_are_counted() {
+        let port = find_free_port();
+        let stop_handle = MockWebSocketsServer::new(port).start();
+        let mut connection = UiConnection::new(port, NODE_UI_PROTOCOL);
+        let json = UiTrafficConverter::new_marshal(
+            UiUnmarshalError {
+                message: "message".to_string(),
+                bad_data: "bad data".to_string(),
+            }
+            .tmb(0),
+        );
+        let initial_count = stop_handle.total_bytes_received();
+
+        connection.send_string(json.clone());
+        connection.send_owned_message(OwnedMessage::Binary(vec![1, 2, 3]));
+
+        thread::sleep(Duration::from_millis(200));
+        let total_bytes_received = stop_handle.total_bytes_received();
+        stop_handle.stop();
+        assert_eq!(initial_count, 0);
+        assert_eq!(total_bytes_received, json.len() as u64 + 3);
+    }
+
     #[test]
     fn attempt_to_get_a_message_from_an_empty_queue_causes_a_panic() {
         let port = find_free_port();
@@ -542,7 +2362,7 @@ mod tests {
 
         //catch_unwind so that we have a chance to shut down the server manually, not letting its thread leak away
         let encapsulated_panic: Result<
-            Result<UiChangePasswordResponse, (u64, std::string::String)>,
+            Result<UiChangePasswordResponse, UiConnectionError>,
             Box<dyn std::any::Any + Send>,
         > = catch_unwind(AssertUnwindSafe(|| {
             connection.transact(conversation_request)
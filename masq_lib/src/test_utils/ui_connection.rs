@@ -2,13 +2,16 @@
 
 use crate::messages::{FromMessageBody, ToMessageBody, UiMessageError};
 use crate::test_utils::ui_connection::ReceiveResult::{Correct, MarshalError, TransactionError};
-use crate::ui_gateway::MessagePath::Conversation;
+use crate::ui_gateway::MessagePath::{Conversation, FireAndForget};
 use crate::ui_gateway::MessageTarget::ClientId;
 use crate::ui_gateway::NodeToUiMessage;
 use crate::ui_traffic_converter::UiTrafficConverter;
 use crate::utils::localhost;
-use std::io::Write;
+use std::io::{ErrorKind, Write};
 use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+use websocket::message::CloseData;
+use websocket::result::WebSocketError;
 use websocket::sync::Client;
 use websocket::{ClientBuilder, OwnedMessage};
 
@@ -49,6 +52,11 @@ impl UiConnection {
         self.send_with_context_id(payload, context_id)
     }
 
+    // Nothing is awaited between frames; they go out in order, one frame per payload.
+    pub fn send_all<T: ToMessageBody>(&mut self, payloads: Vec<T>) {
+        payloads.into_iter().for_each(|payload| self.send(payload))
+    }
+
     pub fn send_with_context_id<T: ToMessageBody>(&mut self, payload: T, context_id: u64) {
         let outgoing_msg = payload.tmb(context_id);
         let outgoing_msg_json = UiTrafficConverter::new_marshal(outgoing_msg);
@@ -63,6 +71,11 @@ impl UiConnection {
         self.client.send_message(message).unwrap();
     }
 
+    // Writes the frame as is, so binary or malformed traffic can be aimed at the server.
+    pub fn send_owned_message(&mut self, message: OwnedMessage) {
+        self.send_message(&message)
+    }
+
     pub fn writer(&mut self) -> &mut dyn Write {
         self.client.writer_mut()
     }
@@ -73,6 +86,15 @@ impl UiConnection {
                 panic!("The queue is empty; all messages are gone.")
             }
             Ok(OwnedMessage::Text(json)) => json,
+            Ok(OwnedMessage::Close(close_data_opt)) => {
+                return TransactionError(UiConnectionError::Closed(close_data_opt))
+            }
+            Err(WebSocketError::IoError(e))
+                if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut =>
+            {
+                return TransactionError(UiConnectionError::Timeout)
+            }
+            Err(e) => return TransactionError(UiConnectionError::Transport(e)),
             x => panic!(
                 "We received an unexpected message from the MockWebSocketServer: {:?}",
                 x
@@ -97,20 +119,28 @@ impl UiConnection {
         match result {
             Ok((payload, _)) => ReceiveResult::Correct(payload),
             Err(UiMessageError::PayloadError(message_body)) => {
-                let payload_error = message_body
+                let (code, message) = message_body
                     .payload
                     .expect_err("PayloadError message body contained no payload error");
-                ReceiveResult::TransactionError(payload_error)
+                let context_id = match message_body.path {
+                    Conversation(id) => id,
+                    FireAndForget => 0,
+                };
+                ReceiveResult::TransactionError(UiConnectionError::Server {
+                    context_id,
+                    code,
+                    message,
+                })
             }
             Err(e) => ReceiveResult::MarshalError((incoming_msg, e)),
         }
     }
 
-    pub fn skip_until_received<T: FromMessageBody>(&mut self) -> Result<T, (u64, String)> {
+    pub fn skip_until_received<T: FromMessageBody>(&mut self) -> Result<T, UiConnectionError> {
         Self::await_message(self)
     }
 
-    fn await_message<T: FromMessageBody>(&mut self) -> Result<T, (u64, String)> {
+    fn await_message<T: FromMessageBody>(&mut self) -> Result<T, UiConnectionError> {
         loop {
             match self.receive_main::<T>(None) {
                 Correct(msg) => break Ok(msg),
@@ -123,7 +153,7 @@ impl UiConnection {
     pub fn transact<S: ToMessageBody, R: FromMessageBody>(
         &mut self,
         payload: S,
-    ) -> Result<R, (u64, String)> {
+    ) -> Result<R, UiConnectionError> {
         self.send(payload);
         Self::standard_result_resolution(self.receive_main::<R>(None))
     }
@@ -132,14 +162,35 @@ impl UiConnection {
         &mut self,
         payload: S,
         context_id: u64,
-    ) -> Result<R, (u64, String)> {
+    ) -> Result<R, UiConnectionError> {
         self.send_with_context_id(payload, context_id);
         Self::standard_result_resolution(self.receive_main::<R>(Some(context_id)))
     }
 
+    // A wedged server yields Err(UiConnectionError::Timeout) here instead of blocking forever.
+    pub fn transact_with_timeout<S: ToMessageBody, R: FromMessageBody>(
+        &mut self,
+        payload: S,
+        context_id: u64,
+        timeout: Duration,
+    ) -> Result<R, UiConnectionError> {
+        self.send_with_context_id(payload, context_id);
+        self.set_read_timeout(Some(timeout));
+        let result = self.receive_main::<R>(Some(context_id));
+        self.set_read_timeout(None);
+        Self::standard_result_resolution(result)
+    }
+
+    fn set_read_timeout(&self, timeout_opt: Option<Duration>) {
+        self.client
+            .stream_ref()
+            .set_read_timeout(timeout_opt)
+            .expect("Couldn't set read timeout")
+    }
+
     fn standard_result_resolution<T>(
         extended_result: ReceiveResult<T>,
-    ) -> Result<T, (u64, String)> {
+    ) -> Result<T, UiConnectionError> {
         match extended_result {
             Correct(msg) => Ok(msg),
             TransactionError(e) => Err(e),
@@ -154,8 +205,63 @@ impl UiConnection {
     }
 }
 
+#[derive(Debug)]
+pub enum UiConnectionError {
+    Transport(WebSocketError),
+    Timeout,
+    Closed(Option<CloseData>),
+    // code and message come from the error payload of the server's response
+    Server {
+        context_id: u64,
+        code: u64,
+        message: String,
+    },
+}
+
 pub enum ReceiveResult<T> {
     Correct(T),
-    TransactionError((u64, String)),
+    TransactionError(UiConnectionError),
     MarshalError((NodeToUiMessage, UiMessageError)),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::{UiChangePasswordRequest, UiChangePasswordResponse, NODE_UI_PROTOCOL};
+    use crate::utils::find_free_port;
+    use crossbeam_channel::unbounded;
+    use std::thread;
+    use std::time::Instant;
+    use websocket::sync::Server;
+
+    #[test]
+    fn transact_with_timeout_gives_up_on_a_server_that_never_answers() {
+        let port = find_free_port();
+        let mut server = Server::bind(SocketAddr::new(localhost(), port)).unwrap();
+        let (done_tx, done_rx) = unbounded::<()>();
+        let join_handle = thread::spawn(move || {
+            let upgrade = server.accept().unwrap();
+            let _client = upgrade.use_protocol(NODE_UI_PROTOCOL).accept().unwrap();
+            let _ = done_rx.recv();
+        });
+        let mut subject = UiConnection::new(port, NODE_UI_PROTOCOL);
+        let request = UiChangePasswordRequest {
+            old_password_opt: None,
+            new_password: "password".to_string(),
+        };
+        let before = Instant::now();
+
+        let result: Result<UiChangePasswordResponse, UiConnectionError> =
+            subject.transact_with_timeout(request, 1234, Duration::from_millis(200));
+
+        let elapsed = before.elapsed();
+        done_tx.send(()).unwrap();
+        join_handle.join().unwrap();
+        match result {
+            Err(UiConnectionError::Timeout) => (),
+            x => panic!("Expected Err(Timeout), got {:?}", x),
+        }
+        assert!(elapsed >= Duration::from_millis(200), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(2000), "{:?}", elapsed);
+    }
+}
@@ -1,18 +1,52 @@
 // Copyright (c) 2019, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
 
-use crate::blockchains::chains::Chain;
+use crate::blockchains::blockchain_records::CHAINS;
+use crate::blockchains::chains::{chain_from_chain_identifier_opt, Chain};
 use crate::test_utils::environment_guard::EnvironmentGuard;
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 
 pub const TEST_DEFAULT_CHAIN: Chain = Chain::EthRopsten;
 pub const TEST_DEFAULT_MULTINODE_CHAIN: Chain = Chain::Dev;
 pub const BASE_TEST_DIR: &str = "generated/test";
 const MASQ_SOURCE_CODE_UNAVAILABLE: &str = "MASQ_SOURCE_CODE_UNAVAILABLE";
+const MASQ_TEST_DIR: &str = "MASQ_TEST_DIR";
+
+static UNIQUE_HOME_DIRECTORY_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+pub fn chain_id_from_name(name: &str) -> Option<u64> {
+    chain_from_chain_identifier_opt(name).map(|chain| chain.rec().num_chain_id)
+}
+
+pub fn chain_name_from_id(id: u64) -> Option<&'static str> {
+    CHAINS
+        .iter()
+        .find(|record| record.num_chain_id == id)
+        .map(|record| record.literal_identifier)
+}
+
+// Set MASQ_TEST_DIR to put test artifacts somewhere other than under the crate, such as a tmpfs
+pub fn base_test_dir() -> String {
+    base_test_dir_from(std::env::var(MASQ_TEST_DIR).ok())
+}
+
+fn base_test_dir_from(env_value: Option<String>) -> String {
+    match env_value {
+        Some(dir) if !dir.trim().is_empty() => dir,
+        _ => BASE_TEST_DIR.to_string(),
+    }
+}
 
 pub fn node_home_directory(module: &str, name: &str) -> PathBuf {
-    let home_dir_string = format!("{}/{}/{}/home", BASE_TEST_DIR, module, name);
+    let home_dir_string = format!(
+        "{}/{}/{}/home",
+        base_test_dir(),
+        safe_path_component(module),
+        safe_path_component(name)
+    );
     PathBuf::from(home_dir_string.as_str())
 }
 
@@ -23,17 +57,158 @@ pub fn ensure_node_home_directory_does_not_exist(module: &str, name: &str) -> Pa
 }
 
 pub fn ensure_node_home_directory_exists(module: &str, name: &str) -> PathBuf {
+    try_ensure_node_home_directory_exists(module, name).unwrap_or_else(|e| {
+        panic!(
+            "Couldn't create node home directory {:?}: {}",
+            node_home_directory(module, name),
+            e
+        )
+    })
+}
+
+pub fn try_ensure_node_home_directory_exists(module: &str, name: &str) -> io::Result<PathBuf> {
     let home_dir = node_home_directory(module, name);
+    remove_dir_all_if_present(&home_dir)?;
+    fs::create_dir_all(&home_dir)?;
+    Ok(home_dir)
+}
+
+// Mirrors the Node's per-chain layout, so one test can keep several chains' homes side by side
+pub fn node_home_directory_for_chain(module: &str, name: &str, chain_name: &str) -> PathBuf {
+    let home_dir_string = format!(
+        "{}/{}/{}/{}/home",
+        base_test_dir(),
+        safe_path_component(module),
+        safe_path_component(name),
+        safe_path_component(chain_name)
+    );
+    PathBuf::from(home_dir_string.as_str())
+}
+
+pub fn ensure_node_home_directory_for_chain_does_not_exist(
+    module: &str,
+    name: &str,
+    chain_name: &str,
+) -> PathBuf {
+    let home_dir = node_home_directory_for_chain(module, name, chain_name);
     let _ = fs::remove_dir_all(&home_dir);
-    let _ = fs::create_dir_all(&home_dir);
     home_dir
 }
 
-pub fn is_running_under_github_actions() -> bool {
-    if let Ok(value) = std::env::var("GITHUB_ACTIONS") {
-        &value == "true"
+pub fn ensure_node_home_directory_for_chain_exists(
+    module: &str,
+    name: &str,
+    chain_name: &str,
+) -> PathBuf {
+    let home_dir = node_home_directory_for_chain(module, name, chain_name);
+    remove_dir_all_if_present(&home_dir)
+        .and_then(|_| fs::create_dir_all(&home_dir))
+        .unwrap_or_else(|e| panic!("Couldn't create node home directory {:?}: {}", home_dir, e));
+    home_dir
+}
+
+// A careless module or test name must not steer remove_dir_all outside the test directory
+fn safe_path_component(part: &str) -> String {
+    let sanitized = part
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+    match sanitized.as_str() {
+        "" | "." | ".." => panic!("'{}' can't be used as a test directory name", part),
+        _ => sanitized,
+    }
+}
+
+pub fn unique_node_home_directory(module: &str, name: &str) -> PathBuf {
+    let serial = UNIQUE_HOME_DIRECTORY_COUNTER.fetch_add(1, Ordering::SeqCst);
+    let unique_name = format!("{}_{}_{}", name, std::process::id(), serial);
+    ensure_node_home_directory_exists(module, &unique_name)
+}
+
+pub fn seed_node_home_from_fixture(module: &str, name: &str, fixture_dir: &Path) -> PathBuf {
+    let home_dir = ensure_node_home_directory_exists(module, name);
+    copy_dir_contents(fixture_dir, &home_dir).unwrap_or_else(|e| {
+        panic!(
+            "Couldn't seed node home directory {:?} from fixture {:?}: {}",
+            home_dir, fixture_dir, e
+        )
+    });
+    home_dir
+}
+
+fn copy_dir_contents(from: &Path, to: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            fs::create_dir_all(&target)?;
+            copy_dir_contents(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn clean_base_test_dir() -> io::Result<()> {
+    remove_dir_all_if_present(Path::new(&base_test_dir()))
+}
+
+pub fn clean_module_test_dir(module: &str) -> io::Result<()> {
+    remove_dir_all_if_present(&Path::new(&base_test_dir()).join(safe_path_component(module)))
+}
+
+fn remove_dir_all_if_present(dir: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(dir) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CiProvider {
+    GitHubActions,
+    GitLab,
+    CircleCi,
+    Jenkins,
+    Other,
+}
+
+pub fn running_ci_provider() -> Option<CiProvider> {
+    if env_var_is("GITHUB_ACTIONS", "true") {
+        Some(CiProvider::GitHubActions)
+    } else if env_var_is("GITLAB_CI", "true") {
+        Some(CiProvider::GitLab)
+    } else if env_var_is("CIRCLECI", "true") {
+        Some(CiProvider::CircleCi)
+    } else if env_var_is_nonblank("JENKINS_URL") || env_var_is_nonblank("HOST_NODE_PARENT_DIR") {
+        Some(CiProvider::Jenkins)
+    } else if env_var_is("CI", "true") {
+        Some(CiProvider::Other)
     } else {
-        false
+        None
+    }
+}
+
+pub fn is_running_under_github_actions() -> bool {
+    running_ci_provider() == Some(CiProvider::GitHubActions)
+}
+
+fn env_var_is(name: &str, expected: &str) -> bool {
+    match std::env::var(name) {
+        Ok(value) => value.eq_ignore_ascii_case(expected),
+        Err(_) => false,
+    }
+}
+
+fn env_var_is_nonblank(name: &str) -> bool {
+    match std::env::var(name) {
+        Ok(value) => !value.trim().is_empty(),
+        Err(_) => false,
     }
 }
 
@@ -81,4 +256,316 @@ mod tests {
         assert_eq!(TEST_DEFAULT_MULTINODE_CHAIN, Chain::Dev);
         assert_eq!(BASE_TEST_DIR, "generated/test");
     }
+
+    #[test]
+    fn chain_ids_and_names_map_both_ways() {
+        assert_eq!(chain_id_from_name("eth-ropsten"), Some(3));
+        assert_eq!(chain_id_from_name("dev"), Some(2));
+        assert_eq!(chain_id_from_name("eth-mainnet"), Some(1));
+        assert_eq!(chain_id_from_name("polygon-mumbai"), Some(80001));
+        assert_eq!(chain_name_from_id(3), Some("eth-ropsten"));
+        assert_eq!(chain_name_from_id(2), Some("dev"));
+        assert_eq!(chain_name_from_id(137), Some("polygon-mainnet"));
+        CHAINS.iter().for_each(|record| {
+            assert_eq!(
+                chain_name_from_id(record.num_chain_id).and_then(chain_id_from_name),
+                Some(record.num_chain_id)
+            )
+        });
+    }
+
+    #[test]
+    fn base_test_dir_honors_the_environment() {
+        let default_dir = base_test_dir_from(None);
+        let blank_dir = base_test_dir_from(Some("  ".to_string()));
+        let configured_dir = base_test_dir_from(Some("/tmp/masq_test_dir".to_string()));
+
+        assert_eq!(default_dir, BASE_TEST_DIR);
+        assert_eq!(blank_dir, BASE_TEST_DIR);
+        assert_eq!(configured_dir, "/tmp/masq_test_dir");
+    }
+
+    #[test]
+    fn chain_home_directories_sit_side_by_side() {
+        let module = "chain_home_directories_sit_side_by_side";
+        let ropsten_home =
+            ensure_node_home_directory_for_chain_exists(module, "name", "eth-ropsten");
+        fs::write(ropsten_home.join("node-data.db"), b"booga").unwrap();
+
+        let dev_home = ensure_node_home_directory_for_chain_exists(module, "name", "dev");
+
+        assert_eq!(
+            ropsten_home,
+            PathBuf::from(format!(
+                "{}/{}/name/eth-ropsten/home",
+                BASE_TEST_DIR, module
+            ))
+        );
+        assert_eq!(
+            dev_home,
+            node_home_directory_for_chain(module, "name", "dev")
+        );
+        assert!(dev_home.is_dir());
+        assert!(ropsten_home.join("node-data.db").exists());
+        let removed = ensure_node_home_directory_for_chain_does_not_exist(module, "name", "dev");
+        assert_eq!(removed, dev_home);
+        assert!(!dev_home.exists());
+        assert!(ropsten_home.is_dir());
+    }
+
+    #[test]
+    fn path_separators_and_reserved_characters_are_neutralized() {
+        let result = node_home_directory("module:one", "../../etc");
+
+        assert_eq!(
+            result,
+            PathBuf::from(format!("{}/module_one/.._.._etc/home", BASE_TEST_DIR))
+        );
+        assert_eq!(
+            safe_path_component("a\\b*c?d\"e<f>g|h\ti"),
+            "a_b_c_d_e_f_g_h_i"
+        );
+        assert_eq!(safe_path_component("..."), "...");
+    }
+
+    #[test]
+    #[should_panic(expected = "'..' can't be used as a test directory name")]
+    fn parent_directory_is_rejected_as_a_name() {
+        node_home_directory("module", "..");
+    }
+
+    #[test]
+    #[should_panic(expected = "'' can't be used as a test directory name")]
+    fn blank_chain_name_is_rejected() {
+        node_home_directory_for_chain("module", "name", "");
+    }
+
+    #[test]
+    fn unique_node_home_directory_never_repeats_itself() {
+        let module = "unique_node_home_directory_never_repeats_itself";
+        let plain = node_home_directory(module, "home");
+
+        let first = unique_node_home_directory(module, "home");
+        let second = unique_node_home_directory(module, "home");
+
+        assert_ne!(first, second);
+        assert_ne!(first, plain);
+        assert!(first.is_dir());
+        assert!(second.is_dir());
+        let parent_name = first
+            .parent()
+            .unwrap()
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(
+            parent_name.starts_with(&format!("home_{}_", std::process::id())),
+            "{}",
+            parent_name
+        );
+    }
+
+    #[test]
+    fn try_ensure_node_home_directory_exists_replaces_an_existing_directory() {
+        let module = "try_ensure_node_home_directory_exists_replaces_an_existing_directory";
+        let home_dir = ensure_node_home_directory_exists(module, "home");
+        let stale_file = home_dir.join("stale.txt");
+        fs::write(&stale_file, b"booga").unwrap();
+
+        let result = try_ensure_node_home_directory_exists(module, "home");
+
+        assert_eq!(result.unwrap(), home_dir);
+        assert!(home_dir.is_dir());
+        assert!(!stale_file.exists());
+    }
+
+    #[test]
+    fn try_ensure_node_home_directory_exists_reports_failure() {
+        let module = "try_ensure_node_home_directory_exists_reports_failure";
+        let _ = fs::remove_dir_all(Path::new(BASE_TEST_DIR).join(module));
+        fs::create_dir_all(Path::new(BASE_TEST_DIR).join(module)).unwrap();
+        fs::write(Path::new(BASE_TEST_DIR).join(module).join("name"), b"").unwrap();
+
+        let result = try_ensure_node_home_directory_exists(module, "name");
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Couldn't create node home directory \"generated/test/ensure_node_home_directory_exists_panics_with_the_path/name/home\""
+    )]
+    fn ensure_node_home_directory_exists_panics_with_the_path() {
+        let module = "ensure_node_home_directory_exists_panics_with_the_path";
+        let _ = fs::remove_dir_all(Path::new(BASE_TEST_DIR).join(module));
+        fs::create_dir_all(Path::new(BASE_TEST_DIR).join(module)).unwrap();
+        fs::write(Path::new(BASE_TEST_DIR).join(module).join("name"), b"").unwrap();
+
+        ensure_node_home_directory_exists(module, "name");
+    }
+
+    #[test]
+    fn seed_node_home_from_fixture_copies_the_whole_fixture_tree() {
+        let module = "seed_node_home_from_fixture_copies_the_whole_fixture_tree";
+        let fixture_dir = ensure_node_home_directory_exists(module, "fixture");
+        fs::write(fixture_dir.join("config.toml"), b"chain = \"dev\"\n").unwrap();
+        fs::create_dir_all(fixture_dir.join("dev")).unwrap();
+        fs::write(fixture_dir.join("dev").join("node-data.db"), b"booga").unwrap();
+        let stale_file = ensure_node_home_directory_exists(module, "node").join("stale.txt");
+        fs::write(&stale_file, b"agoob").unwrap();
+
+        let result = seed_node_home_from_fixture(module, "node", &fixture_dir);
+
+        assert_eq!(result, node_home_directory(module, "node"));
+        assert_eq!(
+            fs::read_to_string(result.join("config.toml")).unwrap(),
+            "chain = \"dev\"\n"
+        );
+        assert_eq!(
+            fs::read(result.join("dev").join("node-data.db")).unwrap(),
+            b"booga".to_vec()
+        );
+        assert!(!stale_file.exists());
+    }
+
+    #[test]
+    #[should_panic(expected = "Couldn't seed node home directory")]
+    fn seed_node_home_from_fixture_panics_on_a_missing_fixture() {
+        let module = "seed_node_home_from_fixture_panics_on_a_missing_fixture";
+        let fixture_dir = ensure_node_home_directory_does_not_exist(module, "fixture");
+
+        seed_node_home_from_fixture(module, "node", &fixture_dir);
+    }
+
+    #[test]
+    fn clean_module_test_dir_removes_the_whole_module_tree() {
+        let module = "clean_module_test_dir_removes_the_whole_module_tree";
+        let first = ensure_node_home_directory_exists(module, "first");
+        let second = ensure_node_home_directory_exists(module, "second");
+        fs::write(first.join("file.txt"), b"booga").unwrap();
+
+        let result = clean_module_test_dir(module);
+
+        assert!(result.is_ok());
+        assert!(!first.exists());
+        assert!(!second.exists());
+        assert!(!Path::new(BASE_TEST_DIR).join(module).exists());
+    }
+
+    #[test]
+    fn clean_module_test_dir_stays_inside_the_base_test_dir() {
+        let canary = Path::new(&base_test_dir())
+            .join("..")
+            .join("clean_module_test_dir_canary");
+        fs::create_dir_all(&canary).unwrap();
+        let absolute_canary = canary.canonicalize().unwrap();
+
+        let relative_result = clean_module_test_dir("../clean_module_test_dir_canary");
+        let absolute_result = clean_module_test_dir(absolute_canary.to_str().unwrap());
+
+        assert!(relative_result.is_ok());
+        assert!(absolute_result.is_ok());
+        assert!(canary.exists());
+        fs::remove_dir_all(&canary).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "'..' can't be used as a test directory name")]
+    fn clean_module_test_dir_refuses_the_parent_directory() {
+        let _ = clean_module_test_dir("..");
+    }
+
+    #[test]
+    fn clean_module_test_dir_tolerates_a_missing_module() {
+        let module = "clean_module_test_dir_tolerates_a_missing_module";
+        ensure_node_home_directory_does_not_exist(module, "home");
+        let _ = fs::remove_dir_all(Path::new(BASE_TEST_DIR).join(module));
+
+        let result = clean_module_test_dir(module);
+
+        assert!(result.is_ok());
+    }
+
+    const CI_VARIABLES: [&str; 6] = [
+        "GITHUB_ACTIONS",
+        "GITLAB_CI",
+        "CIRCLECI",
+        "JENKINS_URL",
+        "HOST_NODE_PARENT_DIR",
+        "CI",
+    ];
+
+    fn provider_with_only(name: &str, value: &str) -> Option<CiProvider> {
+        CI_VARIABLES
+            .iter()
+            .for_each(|variable| std::env::remove_var(variable));
+        std::env::set_var(name, value);
+        running_ci_provider()
+    }
+
+    #[test]
+    fn running_ci_provider_recognizes_each_provider() {
+        let _guard = EnvironmentGuard::new();
+
+        assert_eq!(
+            provider_with_only("GITHUB_ACTIONS", "true"),
+            Some(CiProvider::GitHubActions)
+        );
+        assert_eq!(
+            provider_with_only("GITLAB_CI", "true"),
+            Some(CiProvider::GitLab)
+        );
+        assert_eq!(
+            provider_with_only("CIRCLECI", "true"),
+            Some(CiProvider::CircleCi)
+        );
+        assert_eq!(
+            provider_with_only("JENKINS_URL", "http://jenkins:8080/"),
+            Some(CiProvider::Jenkins)
+        );
+        assert_eq!(
+            provider_with_only("HOST_NODE_PARENT_DIR", "/var/jenkins"),
+            Some(CiProvider::Jenkins)
+        );
+        assert_eq!(provider_with_only("CI", "true"), Some(CiProvider::Other));
+    }
+
+    #[test]
+    fn running_ci_provider_ignores_false_and_blank_values() {
+        let _guard = EnvironmentGuard::new();
+
+        assert_eq!(provider_with_only("GITHUB_ACTIONS", "false"), None);
+        assert_eq!(provider_with_only("HOST_NODE_PARENT_DIR", ""), None);
+        assert_eq!(provider_with_only("CI", "false"), None);
+    }
+
+    #[test]
+    fn is_running_under_github_actions_only_recognizes_github() {
+        let _guard = EnvironmentGuard::new();
+
+        provider_with_only("GITHUB_ACTIONS", "true");
+        assert!(is_running_under_github_actions());
+        provider_with_only("GITLAB_CI", "true");
+        assert!(!is_running_under_github_actions());
+    }
+
+    #[test]
+    fn unknown_chains_are_not_found() {
+        assert_eq!(chain_id_from_name("booga"), None);
+        assert_eq!(chain_name_from_id(4), None);
+    }
+
+    #[test]
+    fn test_default_chains_are_in_the_table() {
+        assert_eq!(
+            chain_id_from_name(TEST_DEFAULT_CHAIN.rec().literal_identifier),
+            Some(TEST_DEFAULT_CHAIN.rec().num_chain_id)
+        );
+        assert_eq!(
+            chain_name_from_id(TEST_DEFAULT_MULTINODE_CHAIN.rec().num_chain_id),
+            Some(TEST_DEFAULT_MULTINODE_CHAIN.rec().literal_identifier)
+        );
+    }
 }
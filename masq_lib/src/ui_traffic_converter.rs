@@ -102,6 +102,15 @@ impl UiTrafficConverter {
         format!("{{{}{}{}}}", opcode_section, path_section, payload_section)
     }
 
+    // Same content as new_marshal, but indented for human eyes; not for use on the wire.
+    pub fn new_marshal_pretty(body: MessageBody) -> String {
+        let compact = Self::new_marshal(body);
+        match serde_json::from_str::<Value>(&compact) {
+            Ok(value) => serde_json::to_string_pretty(&value).expect("Reserialization problem"),
+            Err(_) => compact,
+        }
+    }
+
     pub fn new_unmarshal(json: &str) -> Result<MessageBody, UnmarshalError> {
         match serde_json::from_str(json) {
             Ok(Value::Object(map)) => {
@@ -304,6 +313,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn new_pretty_marshaling_is_readable_and_still_unmarshals() {
+        let body = MessageBody {
+            opcode: "opcode".to_string(),
+            path: Conversation(1234),
+            payload: Ok(r#"{"string": "Booga", "number": 42}"#.to_string()),
+        };
+
+        let json = UiTrafficConverter::new_marshal_pretty(body.clone());
+
+        assert_eq!(
+            json,
+            "{\n  \"contextId\": 1234,\n  \"opcode\": \"opcode\",\n  \"payload\": {\n    \"number\": 42,\n    \"string\": \"Booga\"\n  }\n}"
+        );
+        let ui_msg = UiTrafficConverter::new_unmarshal_from_ui(&json, 4321).unwrap();
+        assert_eq!(ui_msg.client_id, 4321);
+        assert_eq!(ui_msg.body.opcode, body.opcode);
+        assert_eq!(ui_msg.body.path, body.path);
+        assert_eq!(
+            serde_json::from_str::<Value>(&ui_msg.body.payload.unwrap()).unwrap(),
+            serde_json::from_str::<Value>(&body.payload.unwrap()).unwrap()
+        );
+    }
+
     #[test]
     fn new_marshaling_and_unmarshaling_works_from_ui_one_way_for_failure() {
         let ui_msg = NodeFromUiMessage {
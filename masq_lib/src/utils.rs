@@ -93,6 +93,42 @@ pub fn find_free_port_0000() -> u16 {
     find_free_port_for_ip_addr(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)))
 }
 
+// Every port is held until all of them have been found, so the whole set is free at once; the
+// ports are ascending but not necessarily adjacent. Panics if a full lap of the search range
+// doesn't turn up enough of them.
+pub fn find_free_port_range(count: usize) -> Vec<u16> {
+    let range_size = (FIND_FREE_PORT_HIGHEST - FIND_FREE_PORT_LOWEST) as usize + 1;
+    if count > range_size {
+        no_room_for_free_ports(count)
+    }
+    let mut current_port = FIND_FREE_PORT_NEXT.lock().unwrap();
+    let mut held = vec![];
+    let mut candidates_left = range_size;
+    while held.len() < count {
+        if candidates_left == 0 {
+            // Let go of the lock first so that later searches don't find it poisoned
+            drop(current_port);
+            no_room_for_free_ports(count)
+        }
+        candidates_left -= 1;
+        let candidate = *current_port;
+        *current_port = next_port(*current_port);
+        if let Some(sockets) = hold_port_for_ip_addr(localhost(), candidate) {
+            held.push((candidate, sockets));
+        }
+    }
+    let mut ports = held.into_iter().map(|(port, _)| port).collect::<Vec<u16>>();
+    ports.sort_unstable();
+    ports
+}
+
+fn no_room_for_free_ports(count: usize) -> ! {
+    panic!(
+        "Can't find {} free ports between {} and {}",
+        count, FIND_FREE_PORT_LOWEST, FIND_FREE_PORT_HIGHEST
+    )
+}
+
 fn find_free_port_for_ip_addr(ip_addr: IpAddr) -> u16 {
     let mut current_port = FIND_FREE_PORT_NEXT.lock().unwrap();
     loop {
@@ -105,28 +141,27 @@ fn find_free_port_for_ip_addr(ip_addr: IpAddr) -> u16 {
 }
 
 fn port_is_free_for_ip_addr(ip_addr: IpAddr, port: u16) -> bool {
+    hold_port_for_ip_addr(ip_addr, port).is_some()
+}
+
+// The port stays taken, for TCP and UDP alike, until the returned sockets are dropped
+fn hold_port_for_ip_addr(ip_addr: IpAddr, port: u16) -> Option<(TcpListener, UdpSocket)> {
     let test_address = SocketAddr::new(ip_addr, port);
-    fn result_checker<T>(result: io::Result<T>) -> bool {
+    fn result_checker<T>(result: io::Result<T>) -> Option<T> {
         match result {
             Err(ref e)
                 if (e.kind() == ErrorKind::AddrInUse)
                     || (e.kind() == ErrorKind::AddrNotAvailable) =>
             {
-                false
+                None
             }
             Err(e) => panic!("Couldn't find free port: {:?}", e),
-            Ok(_) => true,
+            Ok(socket) => Some(socket),
         }
     }
-    let result = TcpListener::bind(test_address);
-    if !result_checker(result) {
-        return false;
-    }
-    let result = UdpSocket::bind(test_address);
-    if !result_checker(result) {
-        return false;
-    }
-    true
+    let tcp_listener = result_checker(TcpListener::bind(test_address))?;
+    let udp_socket = result_checker(UdpSocket::bind(test_address))?;
+    Some((tcp_listener, udp_socket))
 }
 
 pub fn localhost() -> IpAddr {
@@ -386,6 +421,27 @@ mod tests {
     use std::fs::{create_dir_all, File, OpenOptions};
     use std::io::Write as FmtWrite;
 
+    #[test]
+    fn find_free_port_range_finds_distinct_ports_that_are_free_together() {
+        let result = find_free_port_range(5);
+
+        assert_eq!(result.len(), 5);
+        let mut distinct = result.clone();
+        distinct.dedup();
+        assert_eq!(distinct, result);
+        let held = result
+            .iter()
+            .map(|port| hold_port_for_ip_addr(localhost(), *port))
+            .collect::<Vec<Option<(TcpListener, UdpSocket)>>>();
+        assert!(held.iter().all(|sockets| sockets.is_some()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't find 32769 free ports between 32768 and 65535")]
+    fn find_free_port_range_complains_about_more_ports_than_the_range_holds() {
+        find_free_port_range(32769);
+    }
+
     #[test]
     fn constants_have_correct_values() {
         assert_eq!(
@@ -0,0 +1,75 @@
+// Copyright (c) 2019, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use masq_lib::messages::{FromMessageBody, ToMessageBody, UiMessage, UiMessageError};
+use masq_lib::ui_gateway::MessagePath::{Conversation, FireAndForget};
+use masq_lib::ui_gateway::MessageTarget;
+use masq_lib::ui_traffic_converter::UiTrafficConverter;
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, UiMessage)]
+#[opcode = "sampleConversation"]
+#[message_path = "conversation"]
+struct UiSampleRequest {
+    #[serde(rename = "nameOpt")]
+    name_opt: Option<String>,
+    count: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, UiMessage)]
+#[opcode = "sampleBroadcast"]
+#[message_path = "fireAndForget"]
+struct UiSampleBroadcast {}
+
+#[test]
+fn derived_conversation_message_round_trips_through_traffic_converter() {
+    let subject = UiSampleRequest {
+        name_opt: Some("booga".to_string()),
+        count: 42,
+    };
+
+    let json = UiTrafficConverter::new_marshal(subject.clone().tmb(1234));
+    let body = UiTrafficConverter::new_unmarshal_to_ui(&json, MessageTarget::AllClients)
+        .unwrap()
+        .body;
+    let result = UiSampleRequest::fmb(body.clone());
+
+    assert_eq!(body.opcode, "sampleConversation");
+    assert_eq!(body.path, Conversation(1234));
+    assert_eq!(result, Ok((subject.clone(), 1234)));
+    assert_eq!(subject.opcode(), "sampleConversation");
+    assert!(subject.is_conversational());
+}
+
+#[test]
+fn derived_fire_and_forget_message_round_trips_through_traffic_converter() {
+    let json = UiTrafficConverter::new_marshal(UiSampleBroadcast {}.tmb(1234));
+    let body = UiTrafficConverter::new_unmarshal_to_ui(&json, MessageTarget::AllClients)
+        .unwrap()
+        .body;
+    let result = UiSampleBroadcast::fmb(body.clone());
+
+    assert_eq!(body.opcode, "sampleBroadcast");
+    assert_eq!(body.path, FireAndForget);
+    assert_eq!(result, Ok((UiSampleBroadcast {}, 0)));
+    assert_eq!(UiSampleBroadcast::type_opcode(), "sampleBroadcast");
+    assert!(!UiSampleBroadcast::type_is_conversational());
+}
+
+#[test]
+fn derived_messages_reject_each_other() {
+    let conversation_body = UiSampleRequest {
+        name_opt: None,
+        count: 0,
+    }
+    .tmb(1);
+    let broadcast_body = UiSampleBroadcast {}.tmb(0);
+
+    assert_eq!(
+        UiSampleBroadcast::fmb(conversation_body.clone()),
+        Err(UiMessageError::UnexpectedMessage(conversation_body))
+    );
+    assert_eq!(
+        UiSampleRequest::fmb(broadcast_body.clone()),
+        Err(UiMessageError::UnexpectedMessage(broadcast_body))
+    );
+}
@@ -1,11 +1,20 @@
 // Copyright (c) 2019, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
 
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
 use std::process;
-use std::process::Output;
+use std::process::{Child, Output, Stdio};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
 
 pub struct Command {
     text: String,
     command: process::Command,
+    stdin_opt: Option<Vec<u8>>,
     output: Option<Output>,
 }
 
@@ -20,17 +29,42 @@ impl Command {
         Command {
             text: format!("{} {}", program, args.join(" ")),
             command,
+            stdin_opt: None,
             output: None,
         }
     }
 
+    // Added on top of the environment the test runner inherited.
+    pub fn env(&mut self, key: &str, value: &str) {
+        self.command.env(key, value);
+    }
+
+    pub fn envs(&mut self, vars: HashMap<String, String>) {
+        self.command.envs(vars);
+    }
+
+    pub fn current_dir(&mut self, dir: PathBuf) {
+        self.command.current_dir(dir);
+    }
+
+    // Fed to the child's stdin once it's spawned; the pipe is closed after the last byte.
+    pub fn stdin(&mut self, input: Vec<u8>) {
+        self.stdin_opt = Some(input);
+    }
+
     pub fn strings(slices: Vec<&str>) -> Vec<String> {
         slices.into_iter().map(String::from).collect()
     }
 
     pub fn wait_for_exit(&mut self) -> i32 {
         println!("{}", self.text);
-        self.output = Some(self.command.output().unwrap());
+        self.output = Some(match self.stdin_opt {
+            None => self.command.output().unwrap(),
+            Some(_) => {
+                self.command.stdout(Stdio::piped()).stderr(Stdio::piped());
+                self.spawn().wait_with_output().unwrap()
+            }
+        });
         match self.output.as_ref().unwrap().status.code() {
             None => panic!("Command terminated by signal"),
             Some(exit_code) => exit_code,
@@ -49,6 +83,91 @@ impl Command {
         self.combine_exit_code_stdout_and_stderr(exit_code)
     }
 
+    pub fn combined_output(&mut self) -> String {
+        self.wait_for_exit_with_combined_output().1
+    }
+
+    // stdout and stderr are read concurrently and merged line by line in the order the lines
+    // arrive, so that interleaved error and info lines keep their relative positions
+    pub fn wait_for_exit_with_combined_output(&mut self) -> (i32, String) {
+        println!("{}", self.text);
+        self.command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        let mut child = self.spawn();
+        let lines_arc = Arc::new(Mutex::new(vec![]));
+        let stdout_handle =
+            Self::start_line_collector(child.stdout.take().unwrap(), lines_arc.clone());
+        let stderr_handle =
+            Self::start_line_collector(child.stderr.take().unwrap(), lines_arc.clone());
+        stdout_handle.join().unwrap();
+        stderr_handle.join().unwrap();
+        let exit_code = match child.wait().unwrap().code() {
+            None => panic!("Command terminated by signal"),
+            Some(exit_code) => exit_code,
+        };
+        let text = lines_arc.lock().unwrap().join("\n");
+        println!("{}", Self::truncate_long_string(text.clone()));
+        (exit_code, text)
+    }
+
+    pub fn for_each_line(&mut self, mut f: impl FnMut(&str)) -> i32 {
+        println!("{}", self.text);
+        self.command.stdout(Stdio::piped());
+        let mut child = self.spawn();
+        BufReader::new(child.stdout.take().unwrap())
+            .lines()
+            .map_while(Result::ok)
+            .for_each(|line| f(&line));
+        match child.wait().unwrap().code() {
+            None => panic!("Command terminated by signal"),
+            Some(exit_code) => exit_code,
+        }
+    }
+
+    // The child keeps running after this returns; it is killed when the LineStream is dropped
+    pub fn stream_lines(&mut self) -> LineStream {
+        println!("{}", self.text);
+        self.command.stdout(Stdio::piped());
+        let mut child = self.spawn();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        LineStream { child, receiver }
+    }
+
+    // The input is written from its own thread, so a child that fills its output pipes before
+    // reading all of stdin can't deadlock against us.
+    fn spawn(&mut self) -> Child {
+        if self.stdin_opt.is_some() {
+            self.command.stdin(Stdio::piped());
+        }
+        let mut child = self.command.spawn().unwrap();
+        if let Some(input) = self.stdin_opt.clone() {
+            let mut stdin = child.stdin.take().unwrap();
+            thread::spawn(move || {
+                let _ = stdin.write_all(&input);
+            });
+        }
+        child
+    }
+
+    fn start_line_collector<R: Read + Send + 'static>(
+        stream: R,
+        lines_arc: Arc<Mutex<Vec<String>>>,
+    ) -> JoinHandle<()> {
+        thread::spawn(move || {
+            BufReader::new(stream)
+                .lines()
+                .map_while(Result::ok)
+                .for_each(|line| lines_arc.lock().unwrap().push(line))
+        })
+    }
+
     fn combine_exit_code_stdout_and_stderr(&self, exit_code: i32) -> String {
         format!(
             "EXIT CODE: {}\nSTDOUT:\n{}\n\nSTDERR:\n{}\n\n",
@@ -88,3 +207,132 @@ impl Command {
         string
     }
 }
+
+pub struct LineStream {
+    child: Child,
+    receiver: Receiver<String>,
+}
+
+impl LineStream {
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<String, RecvTimeoutError> {
+        self.receiver.recv_timeout(timeout)
+    }
+}
+
+impl Drop for LineStream {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// Every test here runs a Unix tool (sh, cat, pwd) as the child
+#[cfg(all(test, not(target_os = "windows")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn child_sees_injected_environment_variables() {
+        let mut subject = Command::new(
+            "sh",
+            Command::strings(vec![
+                "-c",
+                "echo $DOCKER_HOST $CONTAINER_HOST $DOCKER_TLS_VERIFY",
+            ]),
+        );
+        subject.env("DOCKER_HOST", "tcp://1.2.3.4:2375");
+        subject.envs(HashMap::from([
+            ("CONTAINER_HOST".to_string(), "unix:///booga".to_string()),
+            ("DOCKER_TLS_VERIFY".to_string(), "1".to_string()),
+        ]));
+
+        let result = subject.stdout_or_stderr();
+
+        assert_eq!(
+            result,
+            Ok("tcp://1.2.3.4:2375 unix:///booga 1\n".to_string())
+        );
+    }
+
+    #[test]
+    fn child_runs_in_the_configured_directory() {
+        let dir = std::env::temp_dir().canonicalize().unwrap();
+        let mut subject = Command::new("pwd", vec![]);
+        subject.current_dir(dir.clone());
+
+        let result = subject.stdout_or_stderr();
+
+        assert_eq!(result, Ok(format!("{}\n", dir.to_string_lossy())));
+    }
+
+    #[test]
+    fn child_reads_the_provided_stdin() {
+        let mut subject = Command::new("cat", vec![]);
+        subject.stdin(b"SELECT * FROM config;\n".to_vec());
+
+        let result = subject.stdout_or_stderr();
+
+        assert_eq!(result, Ok("SELECT * FROM config;\n".to_string()));
+    }
+
+    #[test]
+    fn streamed_children_read_the_provided_stdin_too() {
+        let mut subject = Command::new("cat", vec![]);
+        subject.stdin(b"one\ntwo\n".to_vec());
+        let mut lines = vec![];
+
+        let exit_code = subject.for_each_line(|line| lines.push(line.to_string()));
+
+        assert_eq!(exit_code, 0);
+        assert_eq!(lines, vec!["one".to_string(), "two".to_string()]);
+    }
+
+    #[test]
+    fn combined_output_merges_stdout_and_stderr_in_arrival_order() {
+        // The pauses keep the two pipes from racing each other to the collectors
+        let mut subject = Command::new(
+            "sh",
+            Command::strings(vec![
+                "-c",
+                "echo a; sleep 0.2; echo b >&2; sleep 0.2; echo c; exit 3",
+            ]),
+        );
+
+        let (exit_code, text) = subject.wait_for_exit_with_combined_output();
+
+        assert_eq!(exit_code, 3);
+        assert_eq!(text, "a\nb\nc".to_string());
+    }
+
+    #[test]
+    fn combined_output_is_the_merged_text_alone() {
+        let mut subject = Command::new(
+            "sh",
+            Command::strings(vec![
+                "-c",
+                "echo a; sleep 0.2; echo b >&2; sleep 0.2; echo c",
+            ]),
+        );
+
+        let result = subject.combined_output();
+
+        assert_eq!(result, "a\nb\nc".to_string());
+    }
+
+    #[test]
+    fn for_each_line_sees_every_line_in_order_and_returns_the_exit_code() {
+        let mut subject = Command::new(
+            "sh",
+            Command::strings(vec!["-c", "echo one; echo two; echo three; exit 5"]),
+        );
+        let mut lines = vec![];
+
+        let exit_code = subject.for_each_line(|line| lines.push(line.to_string()));
+
+        assert_eq!(exit_code, 5);
+        assert_eq!(
+            lines,
+            vec!["one".to_string(), "two".to_string(), "three".to_string()]
+        );
+    }
+}
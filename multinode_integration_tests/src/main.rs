@@ -435,6 +435,52 @@ mod tests {
         assert_eq!(data_hunk.data, vec!(1, 2, 3, 4));
     }
 
+    #[test]
+    fn receives_on_every_mentioned_port() {
+        let control_stream_port = find_free_port();
+        let first_port = find_free_port();
+        let second_port = find_free_port();
+        thread::spawn(move || {
+            let mut subject = MockNode::new();
+            subject.control_stream_port = control_stream_port;
+            let mut streams: StdStreams<'_> = StdStreams {
+                stdin: &mut io::stdin(),
+                stdout: &mut io::stdout(),
+                stderr: &mut io::stderr(),
+            };
+            subject.go(
+                &mut streams,
+                &[
+                    String::from("binary"),
+                    format!("127.0.0.1:{}/{}", first_port, second_port),
+                ],
+            );
+        });
+        thread::sleep(Duration::from_millis(100));
+        let mut control_stream = TcpStream::connect(
+            SocketAddr::from_str(format!("127.0.0.1:{}", control_stream_port).as_str()).unwrap(),
+        )
+        .unwrap();
+        thread::sleep(Duration::from_millis(100));
+
+        vec![(first_port, 1u8), (second_port, 2u8)]
+            .into_iter()
+            .for_each(|(port, payload)| {
+                let mut write_stream = TcpStream::connect(
+                    SocketAddr::from_str(format!("127.0.0.1:{}", port).as_str()).unwrap(),
+                )
+                .unwrap();
+                write_stream.write(&[payload]).unwrap();
+
+                let mut buf = [0u8; 100];
+                let size = control_stream.read(&mut buf).unwrap();
+                let data_hunk: DataHunk = Vec::from(&buf[..size]).into();
+                assert_eq!(data_hunk.from, write_stream.local_addr().unwrap());
+                assert_eq!(data_hunk.to.port(), port);
+                assert_eq!(data_hunk.data, vec![payload]);
+            });
+    }
+
     #[test]
     fn can_instruct_transmission_of_data() {
         let control_stream_port = find_free_port();
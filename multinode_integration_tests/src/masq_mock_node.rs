@@ -1,11 +1,14 @@
 // Copyright (c) 2019, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
 use crate::command::Command;
 use crate::main::CONTROL_STREAM_PORT;
+use crate::masq_node::DockerNetwork;
 use crate::masq_node::MASQNode;
 use crate::masq_node::MASQNodeUtils;
 use crate::masq_node::NodeReference;
 use crate::masq_node::PortSelector;
-use crate::multinode_gossip::{Introduction, MultinodeGossip, SingleNode};
+use crate::multinode_gossip::{
+    parse_gossip, GossipType, Introduction, MultinodeGossip, SingleNode,
+};
 use masq_lib::blockchains::chains::Chain;
 use masq_lib::test_utils::utils::TEST_DEFAULT_MULTINODE_CHAIN;
 use node_lib::hopper::live_cores_package::LiveCoresPackage;
@@ -13,6 +16,7 @@ use node_lib::json_masquerader::JsonMasquerader;
 use node_lib::masquerader::{MasqueradeError, Masquerader};
 use node_lib::neighborhood::gossip::Gossip_0v1;
 use node_lib::neighborhood::node_record::NodeRecord;
+use node_lib::neighborhood::AccessibleGossipRecord;
 use node_lib::sub_lib::cryptde::CryptData;
 use node_lib::sub_lib::cryptde::PublicKey;
 use node_lib::sub_lib::cryptde::{encodex, CryptDE};
@@ -28,17 +32,19 @@ use node_lib::sub_lib::wallet::Wallet;
 use node_lib::test_utils::data_hunk::DataHunk;
 use node_lib::test_utils::data_hunk_framer::DataHunkFramer;
 use node_lib::test_utils::{make_paying_wallet, make_wallet};
-use std::cell::RefCell;
-use std::convert::TryFrom;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::convert::{TryFrom, TryInto};
 use std::io::{Error, ErrorKind, Read, Write};
-use std::net::Ipv4Addr;
 use std::net::SocketAddr;
 use std::net::TcpStream;
 use std::net::{IpAddr, Shutdown};
 use std::ops::Add;
 use std::rc::Rc;
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
 pub struct MASQMockNode {
     control_stream: RefCell<TcpStream>,
@@ -49,6 +55,40 @@ pub struct MASQMockNode {
     guts: Rc<MASQMockNodeGuts>,
 }
 
+const RECEIVED_PACKETS_CAPACITY: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReceivedPacketType {
+    ClientRequest,
+    ClientResponse,
+    Gossip,
+    GossipFailure,
+    DnsResolveFailed,
+    Undecryptable,
+}
+
+impl From<&MessageType> for ReceivedPacketType {
+    fn from(message_type: &MessageType) -> Self {
+        match message_type {
+            MessageType::ClientRequest(_) => ReceivedPacketType::ClientRequest,
+            MessageType::ClientResponse(_) => ReceivedPacketType::ClientResponse,
+            MessageType::Gossip(_) => ReceivedPacketType::Gossip,
+            MessageType::GossipFailure(_) => ReceivedPacketType::GossipFailure,
+            MessageType::DnsResolveFailed(_) => ReceivedPacketType::DnsResolveFailed,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReceivedPacket {
+    pub timestamp: SystemTime,
+    pub from: SocketAddr,
+    pub to: SocketAddr,
+    // Only known when the sender can be identified from the payload, as with Gossip
+    pub source_key_opt: Option<PublicKey>,
+    pub packet_type: ReceivedPacketType,
+}
+
 enum CryptDEEnum {
     Real(CryptDEReal),
     Fake((CryptDENull, CryptDENull)),
@@ -177,6 +217,7 @@ pub trait MASQMockNodeStarter<T> {
         host_node_parent_dir: Option<String>,
         public_key_opt: Option<&PublicKey>,
         chain: Chain,
+        network: &DockerNetwork,
     ) -> T;
 }
 
@@ -190,6 +231,7 @@ impl MASQMockNodeStarter<MASQMockNode> for ImmutableMASQMockNodeStarter {
         host_node_parent_dir: Option<String>,
         public_key_opt: Option<&PublicKey>,
         chain: Chain,
+        network: &DockerNetwork,
     ) -> MASQMockNode {
         let (control_stream, mock_node_guts) = MASQMockNode::start_masq_mock_node_with_bare_guts(
             ports,
@@ -197,6 +239,7 @@ impl MASQMockNodeStarter<MASQMockNode> for ImmutableMASQMockNodeStarter {
             host_node_parent_dir,
             public_key_opt,
             chain,
+            network,
         );
         MASQMockNode {
             control_stream,
@@ -215,6 +258,7 @@ impl MASQMockNodeStarter<MutableMASQMockNode> for MutableMASQMockNodeStarter {
         host_node_parent_dir: Option<String>,
         public_key_opt: Option<&PublicKey>,
         chain: Chain,
+        network: &DockerNetwork,
     ) -> MutableMASQMockNode {
         let (control_stream, mock_node_guts) = MASQMockNode::start_masq_mock_node_with_bare_guts(
             ports,
@@ -222,6 +266,7 @@ impl MASQMockNodeStarter<MutableMASQMockNode> for MutableMASQMockNodeStarter {
             host_node_parent_dir,
             public_key_opt,
             chain,
+            network,
         );
         MutableMASQMockNode {
             control_stream,
@@ -239,6 +284,10 @@ impl MASQMockNode {
     }
 
     pub fn transmit_data(&self, data_hunk: DataHunk) -> Result<(), Error> {
+        if self.should_drop() {
+            self.guts.dropped_transmissions.borrow_mut().push(data_hunk);
+            return Ok(());
+        }
         let to_transmit: Vec<u8> = data_hunk.into();
         match self.control_stream.borrow_mut().write(&to_transmit[..]) {
             Ok(_) => Ok(()),
@@ -246,6 +295,30 @@ impl MASQMockNode {
         }
     }
 
+    // Every transmit_* call is then skipped with this probability, as if lost on the wire.
+    pub fn set_drop_rate(&self, probability: f64) {
+        if !(0.0..=1.0).contains(&probability) {
+            panic!("Drop rate must be between 0.0 and 1.0, not {}", probability)
+        }
+        self.guts.drop_rate.set(probability);
+    }
+
+    // Makes the sequence of drops repeatable from one test run to the next.
+    pub fn seed_drop_rate(&self, seed: u64) {
+        *self.guts.drop_rng.borrow_mut() = SmallRng::seed_from_u64(seed);
+    }
+
+    pub fn dropped_transmissions(&self) -> Vec<DataHunk> {
+        self.guts.dropped_transmissions.borrow().clone()
+    }
+
+    fn should_drop(&self) -> bool {
+        match self.guts.drop_rate.get() {
+            rate if rate <= 0.0 => false,
+            rate => self.guts.drop_rng.borrow_mut().gen_bool(rate),
+        }
+    }
+
     pub fn transmit_package(
         &self,
         transmit_port: u16,
@@ -266,6 +339,22 @@ impl MASQMockNode {
         self.transmit_data(data_hunk)
     }
 
+    // The bytes are masked but otherwise sent as they are, so they needn't decode as a package.
+    pub fn transmit_cores_package(
+        &self,
+        target: &dyn MASQNode,
+        payload: Vec<u8>,
+    ) -> Result<(), Error> {
+        let masquerader = JsonMasquerader::new();
+        let masked_data = masquerader.mask(payload.as_slice()).unwrap();
+        let data_hunk = DataHunk::new(
+            SocketAddr::new(self.ip_address(), self.port_list()[0]),
+            target.socket_addr(PortSelector::First),
+            masked_data,
+        );
+        self.transmit_data(data_hunk)
+    }
+
     pub fn transmit_gossip(
         &self,
         transmit_port: u16,
@@ -296,6 +385,19 @@ impl MASQMockNode {
         self.transmit_multinode_gossip(receiver, &gossip)
     }
 
+    // Times a Debut out to the target and back to the first Gossip the target sends in answer.
+    // Gossip that got here before the Debut left can't be the answer, so it's set aside unread.
+    pub fn ping_round_trip(&self, target: &dyn MASQNode, timeout: Duration) -> Option<Duration> {
+        let target_ip_addr = target.ip_address();
+        while let Some(early_gossip) = self.receive_gossip(Duration::from_millis(0)) {
+            self.guts.gossip_buffer.borrow_mut().push_back(early_gossip);
+        }
+        let started_at = Instant::now();
+        self.transmit_debut(target).ok()?;
+        self.receive_gossip_where(timeout, |_, sender, _| sender == target_ip_addr)
+            .map(|_| started_at.elapsed())
+    }
+
     pub fn transmit_pass(
         &self,
         receiver: &dyn MASQNode,
@@ -360,25 +462,31 @@ impl MASQMockNode {
         timeout: Duration,
     ) -> Result<(SocketAddr, SocketAddr, LiveCoresPackage), Error> {
         let stop_at = Instant::now().add(timeout);
-        let mut accumulated_data: Vec<u8> = vec![];
         let (unmasked_chunk, socket_from, socket_to) = loop {
-            match self.wait_for_data(Duration::from_millis(100)) {
+            // Never read for longer than the caller is willing to wait
+            let slice = stop_at
+                .saturating_duration_since(Instant::now())
+                .min(Duration::from_millis(100))
+                .max(Duration::from_millis(1));
+            match self.wait_for_data(slice) {
                 Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
-                    if Instant::now() > stop_at {
+                    if Instant::now() >= stop_at {
                         return Err(Error::from(ErrorKind::WouldBlock));
                     }
                     continue;
                 }
                 Err(e) => return Err(e),
                 Ok(data_hunk) => {
-                    accumulated_data.extend(data_hunk.data);
-                    match masquerader.try_unmask(&accumulated_data) {
+                    let mut partial_data = self.guts.partial_data.borrow_mut();
+                    partial_data.extend(data_hunk.data);
+                    match masquerader.try_unmask(&partial_data) {
                         Err(MasqueradeError::NotThisMasquerader) => {
                             panic!("Wrong Masquerader supplied to wait_for_package")
                         }
                         Err(_) => continue,
                         Ok(unmasked_chunk) => {
-                            break (unmasked_chunk.chunk, data_hunk.from, data_hunk.to)
+                            partial_data.clear();
+                            break (unmasked_chunk.chunk, data_hunk.from, data_hunk.to);
                         }
                     }
                 }
@@ -391,13 +499,126 @@ impl MASQMockNode {
             .unwrap();
         let live_cores_package =
             serde_cbor::de::from_slice::<LiveCoresPackage>(decrypted_data.as_slice()).unwrap();
+        self.record_received_packet(socket_from, socket_to, &live_cores_package);
         Ok((socket_from, socket_to, live_cores_package))
     }
 
+    pub fn received_packets(&self) -> Vec<ReceivedPacket> {
+        self.guts
+            .received_packets
+            .borrow()
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    fn record_received_packet(&self, from: SocketAddr, to: SocketAddr, package: &LiveCoresPackage) {
+        let cryptde = self.signing_cryptde().unwrap();
+        let (packet_type, source_key_opt) = match package.to_expired(from, cryptde, cryptde) {
+            Ok(expired) => (
+                ReceivedPacketType::from(&expired.payload),
+                Self::gossip_source_key(&expired.payload, from.ip()),
+            ),
+            Err(_) => (ReceivedPacketType::Undecryptable, None),
+        };
+        let mut received_packets = self.guts.received_packets.borrow_mut();
+        if received_packets.len() >= RECEIVED_PACKETS_CAPACITY {
+            received_packets.pop_front();
+        }
+        received_packets.push_back(ReceivedPacket {
+            timestamp: SystemTime::now(),
+            from,
+            to,
+            source_key_opt,
+            packet_type,
+        });
+    }
+
+    fn gossip_source_key(payload: &MessageType, sender: IpAddr) -> Option<PublicKey> {
+        let gossip = match payload {
+            MessageType::Gossip(vd) => Gossip_0v1::try_from(vd.clone()).ok()?,
+            _ => return None,
+        };
+        let agrs: Vec<AccessibleGossipRecord> = gossip.try_into().ok()?;
+        agrs.into_iter()
+            .find(|agr| {
+                agr.node_addr_opt
+                    .as_ref()
+                    .map(|node_addr| node_addr.ip_addr() == sender)
+                    .unwrap_or(false)
+            })
+            .map(|agr| agr.inner.public_key)
+    }
+
     pub fn wait_for_gossip(&self, timeout: Duration) -> Option<(Gossip_0v1, IpAddr)> {
+        if let Some((gossip, sender, _)) = self.guts.gossip_buffer.borrow_mut().pop_front() {
+            return Some((gossip, sender));
+        }
+        self.receive_gossip(timeout)
+            .map(|(gossip, sender, _)| (gossip, sender))
+    }
+
+    pub fn wait_for_gossip_matching(
+        &self,
+        timeout: Duration,
+        pred: impl Fn(&Gossip_0v1, IpAddr) -> bool,
+    ) -> Option<(Gossip_0v1, IpAddr)> {
+        self.wait_for_gossip_where(timeout, |gossip, sender, _| pred(gossip, sender))
+            .map(|(gossip, sender, _)| (gossip, sender))
+    }
+
+    // The mock Node listens on every port it was started with; this waits for Gossip that
+    // arrived on one particular port, leaving Gossip that arrived on the others for later.
+    pub fn wait_for_gossip_on(&self, port: u16, timeout: Duration) -> Option<(Gossip_0v1, IpAddr)> {
+        self.wait_for_gossip_where(timeout, |_, _, receiving_port| receiving_port == port)
+            .map(|(gossip, sender, _)| (gossip, sender))
+    }
+
+    fn wait_for_gossip_where(
+        &self,
+        timeout: Duration,
+        pred: impl Fn(&Gossip_0v1, IpAddr, u16) -> bool,
+    ) -> Option<(Gossip_0v1, IpAddr, u16)> {
+        let buffered_match_opt = {
+            let mut buffer = self.guts.gossip_buffer.borrow_mut();
+            buffer
+                .iter()
+                .position(|(gossip, sender, port)| pred(gossip, *sender, *port))
+                .and_then(|index| buffer.remove(index))
+        };
+        if buffered_match_opt.is_some() {
+            return buffered_match_opt;
+        }
+        self.receive_gossip_where(timeout, pred)
+    }
+
+    // Like wait_for_gossip_where(), but only Gossip that hasn't been read yet will do.
+    fn receive_gossip_where(
+        &self,
+        timeout: Duration,
+        pred: impl Fn(&Gossip_0v1, IpAddr, u16) -> bool,
+    ) -> Option<(Gossip_0v1, IpAddr, u16)> {
+        let stop_at = Instant::now().add(timeout);
+        loop {
+            let remaining = stop_at.saturating_duration_since(Instant::now());
+            if remaining == Duration::from_millis(0) {
+                return None;
+            }
+            let (gossip, sender, port) = self.receive_gossip(remaining)?;
+            if pred(&gossip, sender, port) {
+                return Some((gossip, sender, port));
+            }
+            self.guts
+                .gossip_buffer
+                .borrow_mut()
+                .push_back((gossip, sender, port));
+        }
+    }
+
+    fn receive_gossip(&self, timeout: Duration) -> Option<(Gossip_0v1, IpAddr, u16)> {
         let masquerader = JsonMasquerader::new();
         match self.wait_for_package(&masquerader, timeout) {
-            Ok((from, _, package)) => {
+            Ok((from, to, package)) => {
                 let incoming_cores_package = match package.to_expired(
                     from,
                     self.main_cryptde_null().unwrap(),
@@ -407,10 +628,11 @@ impl MASQMockNode {
                     Err(e) => panic!("Couldn't expire LiveCoresPackage: {:?}", e),
                 };
                 match incoming_cores_package.payload {
-                    MessageType::Gossip(vd) => Some((
-                        Gossip_0v1::try_from(vd).expect("Couldn't deserialize Gossip"),
-                        from.ip(),
-                    )),
+                    MessageType::Gossip(vd) => {
+                        let gossip = Gossip_0v1::try_from(vd).expect("Couldn't deserialize Gossip");
+                        self.respond_to_debut_if_bootstrapping(&gossip, from.ip());
+                        Some((gossip, from.ip(), to.port()))
+                    }
                     _ => panic!("Expected Gossip, got something else"),
                 }
             }
@@ -418,6 +640,52 @@ impl MASQMockNode {
         }
     }
 
+    // From now on, every Debut this mock Node reads is answered with one Introduction per
+    // introducee. The mock only reacts while it's reading, as in wait_for_gossip().
+    pub fn auto_bootstrap(&self, introducees: Vec<&dyn MASQNode>) {
+        *self.guts.auto_bootstrap_introducees.borrow_mut() = introducees
+            .into_iter()
+            .map(AccessibleGossipRecord::from)
+            .collect();
+    }
+
+    fn respond_to_debut_if_bootstrapping(&self, gossip: &Gossip_0v1, sender: IpAddr) {
+        let introducees = self.guts.auto_bootstrap_introducees.borrow();
+        if introducees.is_empty() {
+            return;
+        }
+        let debuter = match parse_gossip(gossip, sender) {
+            Ok(GossipType::DebutGossip(debuter)) => debuter,
+            _ => return,
+        };
+        // A Debut this mock can't answer is left alone, as a real Node would leave it
+        let debuter_key = match debuter.key_set().into_iter().next() {
+            Some(key) => key,
+            None => return,
+        };
+        let debuter_addr = match debuter.agr(&debuter_key).and_then(|agr| agr.node_addr_opt) {
+            Some(node_addr) if !node_addr.ports().is_empty() => node_addr,
+            _ => return,
+        };
+        let introducer = AccessibleGossipRecord::from(self as &dyn MASQNode);
+        introducees.iter().for_each(|introducee| {
+            let introduction = Introduction::from((&introducer, introducee));
+            if let Err(e) = self.transmit_gossip(
+                self.port_list()[0],
+                introduction.render(),
+                &debuter_key,
+                SocketAddr::new(debuter_addr.ip_addr(), debuter_addr.ports()[0]),
+            ) {
+                println!(
+                    "{} couldn't answer Debut from {} with Introduction: {}",
+                    self.name(),
+                    sender,
+                    e
+                )
+            }
+        });
+    }
+
     pub fn wait_for_gossip_failure(
         &self,
         timeout: Duration,
@@ -457,9 +725,10 @@ impl MASQMockNode {
         host_node_parent_dir: Option<String>,
         public_key_opt: Option<&PublicKey>,
         chain: Chain,
+        network: &DockerNetwork,
     ) -> (RefCell<TcpStream>, MASQMockNodeGuts) {
         let cryptde_enum = Self::initiate_cryptde_enum(public_key_opt, chain);
-        Self::start_with_cryptde_enum(ports, index, host_node_parent_dir, cryptde_enum)
+        Self::start_with_cryptde_enum(ports, index, host_node_parent_dir, cryptde_enum, network)
     }
 
     fn initiate_cryptde_enum(public_key_opt: Option<&PublicKey>, chain: Chain) -> CryptDEEnum {
@@ -480,13 +749,14 @@ impl MASQMockNode {
         index: usize,
         host_node_parent_dir: Option<String>,
         cryptde_enum: CryptDEEnum,
+        network: &DockerNetwork,
     ) -> (RefCell<TcpStream>, MASQMockNodeGuts) {
         let name = format!("mock_node_{}", index);
-        let node_addr = NodeAddr::new(&IpAddr::V4(Ipv4Addr::new(172, 18, 1, index as u8)), &ports);
+        let node_addr = NodeAddr::new(&network.node_ip_addr(index), &ports);
         let earning_wallet = make_wallet(format!("{}_earning", name).as_str());
         let consuming_wallet = Some(make_paying_wallet(format!("{}_consuming", name).as_bytes()));
         MASQNodeUtils::clean_up_existing_container(&name[..]);
-        MASQMockNode::do_docker_run(&node_addr, host_node_parent_dir, &name);
+        MASQMockNode::do_docker_run(&node_addr, host_node_parent_dir, &name, network);
         let wait_addr = SocketAddr::new(node_addr.ip_addr(), CONTROL_STREAM_PORT);
         let control_stream = RefCell::new(MASQMockNode::wait_for_startup(wait_addr, &name));
         let framer = RefCell::new(DataHunkFramer::new());
@@ -498,12 +768,24 @@ impl MASQMockNode {
             rate_pack: DEFAULT_RATE_PACK,
             cryptde_enum,
             framer,
+            partial_data: RefCell::new(vec![]),
+            gossip_buffer: RefCell::new(VecDeque::new()),
+            received_packets: RefCell::new(VecDeque::new()),
+            auto_bootstrap_introducees: RefCell::new(vec![]),
+            drop_rate: Cell::new(0.0),
+            drop_rng: RefCell::new(SmallRng::from_entropy()),
+            dropped_transmissions: RefCell::new(vec![]),
             chain: TEST_DEFAULT_MULTINODE_CHAIN,
         };
         (control_stream, guts)
     }
 
-    fn do_docker_run(node_addr: &NodeAddr, host_node_parent_dir: Option<String>, name: &str) {
+    fn do_docker_run(
+        node_addr: &NodeAddr,
+        host_node_parent_dir: Option<String>,
+        name: &str,
+        network: &DockerNetwork,
+    ) {
         let root = match host_node_parent_dir {
             Some(dir) => dir,
             None => MASQNodeUtils::find_project_root(),
@@ -513,6 +795,7 @@ impl MASQMockNode {
         let docker_command = "docker";
         let ip_addr_string = format!("{}", node_addr.ip_addr());
         let v_param = format!("{}:/node_root/node", command_dir);
+        let run_label = network.run_label();
         let mut docker_args = Command::strings(vec![
             "run",
             "--detach",
@@ -521,7 +804,9 @@ impl MASQMockNode {
             "--name",
             name,
             "--net",
-            "integration_net",
+            &network.name,
+            "--label",
+            &run_label,
             "-v",
             v_param.as_str(),
             "test_node_image",
@@ -571,6 +856,14 @@ struct MASQMockNodeGuts {
     rate_pack: RatePack,
     cryptde_enum: CryptDEEnum,
     framer: RefCell<DataHunkFramer>,
+    // A package can straddle DataHunks, and a wait can time out between them
+    partial_data: RefCell<Vec<u8>>,
+    gossip_buffer: RefCell<VecDeque<(Gossip_0v1, IpAddr, u16)>>,
+    received_packets: RefCell<VecDeque<ReceivedPacket>>,
+    auto_bootstrap_introducees: RefCell<Vec<AccessibleGossipRecord>>,
+    drop_rate: Cell<f64>,
+    drop_rng: RefCell<SmallRng>,
+    dropped_transmissions: RefCell<Vec<DataHunk>>,
     chain: Chain,
 }
 
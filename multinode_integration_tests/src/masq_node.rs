@@ -18,6 +18,7 @@ use std::ffi::OsStr;
 use std::fmt;
 use std::fs;
 use std::net::IpAddr;
+use std::net::Ipv4Addr;
 use std::net::SocketAddr;
 use std::path::Path;
 use std::path::PathBuf;
@@ -178,6 +179,56 @@ pub enum PortSelector {
     Index(usize),
 }
 
+pub const INTEGRATION_NETWORK_NAME: &str = "integration_net";
+pub const RUN_LABEL_KEY: &str = "masq-run";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DockerNetwork {
+    pub name: String,
+    pub subnet: String,
+    pub run_id: String,
+    prefix: (u8, u8),
+}
+
+impl DockerNetwork {
+    pub fn integration_net(run_id: &str) -> Self {
+        Self::new(INTEGRATION_NETWORK_NAME, "172.18.0.0/16", run_id)
+            .expect("Bad integration_net subnet")
+    }
+
+    // Nodes are addressed as <a>.<b>.1.<index>, so only /16 subnets ending in .0.0 will do.
+    pub fn new(name: &str, subnet: &str, run_id: &str) -> Result<Self, String> {
+        let malformed = || {
+            format!(
+                "Subnet for network {} should look like 172.19.0.0/16, not '{}'",
+                name, subnet
+            )
+        };
+        let address = subnet.strip_suffix("/16").ok_or_else(malformed)?;
+        let octets = Ipv4Addr::from_str(address)
+            .map_err(|_| malformed())?
+            .octets();
+        if octets[2] != 0 || octets[3] != 0 {
+            return Err(malformed());
+        }
+        Ok(Self {
+            name: name.to_string(),
+            subnet: subnet.to_string(),
+            run_id: run_id.to_string(),
+            prefix: (octets[0], octets[1]),
+        })
+    }
+
+    // Everything the owning cluster creates carries this label, so that it cleans up only its own.
+    pub fn run_label(&self) -> String {
+        format!("{}={}", RUN_LABEL_KEY, self.run_id)
+    }
+
+    pub fn node_ip_addr(&self, index: usize) -> IpAddr {
+        IpAddr::V4(Ipv4Addr::new(self.prefix.0, self.prefix.1, 1, index as u8))
+    }
+}
+
 pub trait MASQNode: Any {
     // This is the name of the Docker container on which this MASQNode will run.
     fn name(&self) -> &str;
@@ -307,6 +358,52 @@ mod tests {
     use base64::{decode_config, STANDARD_NO_PAD};
     use masq_lib::test_utils::utils::TEST_DEFAULT_MULTINODE_CHAIN;
 
+    #[test]
+    fn integration_net_addresses_nodes_as_before() {
+        let subject = DockerNetwork::integration_net("run_id");
+
+        assert_eq!(subject.name, "integration_net".to_string());
+        assert_eq!(subject.subnet, "172.18.0.0/16".to_string());
+        assert_eq!(
+            subject.node_ip_addr(7),
+            IpAddr::V4(Ipv4Addr::new(172, 18, 1, 7))
+        );
+    }
+
+    #[test]
+    fn additional_network_addresses_nodes_in_its_own_subnet() {
+        let subject = DockerNetwork::new("other_net", "172.19.0.0/16", "run_id").unwrap();
+
+        assert_eq!(
+            subject.node_ip_addr(3),
+            IpAddr::V4(Ipv4Addr::new(172, 19, 1, 3))
+        );
+    }
+
+    #[test]
+    fn network_is_labeled_with_its_run_id() {
+        let subject = DockerNetwork::new("other_net", "172.19.0.0/16", "1234-5678").unwrap();
+
+        assert_eq!(subject.run_label(), "masq-run=1234-5678".to_string());
+    }
+
+    #[test]
+    fn network_with_unusable_subnet_is_rejected() {
+        vec!["172.19.0.0/24", "172.19.0.0", "172.19.5.0/16", "booga/16"]
+            .into_iter()
+            .for_each(|subnet| {
+                let result = DockerNetwork::new("other_net", subnet, "run_id");
+
+                assert_eq!(
+                    result,
+                    Err(format!(
+                        "Subnet for network other_net should look like 172.19.0.0/16, not '{}'",
+                        subnet
+                    ))
+                );
+            })
+    }
+
     #[test]
     fn strip_ports_works_single_port() {
         let tail = "1.2.3.4:4444";
@@ -4,16 +4,56 @@ use crate::masq_mock_node::{
     ImmutableMASQMockNodeStarter, MASQMockNode, MASQMockNodeStarter, MutableMASQMockNode,
     MutableMASQMockNodeStarter,
 };
-use crate::masq_node::{MASQNode, MASQNodeUtils};
+use crate::masq_node::{DockerNetwork, MASQNode, MASQNodeUtils, RUN_LABEL_KEY};
 use crate::masq_real_node::MASQRealNode;
 use crate::masq_real_node::NodeStartupConfig;
 use masq_lib::blockchains::chains::Chain;
-use masq_lib::test_utils::utils::TEST_DEFAULT_MULTINODE_CHAIN;
+use masq_lib::test_utils::utils::{running_ci_provider, CiProvider, TEST_DEFAULT_MULTINODE_CHAIN};
+use node_lib::neighborhood::gossip::Gossip_0v1;
 use node_lib::sub_lib::cryptde::PublicKey;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::env;
-use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, ToSocketAddrs};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, ToSocketAddrs};
+use std::path::Path;
+use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    Real,
+    Mock,
+}
+
+// Matching on this recovers the concrete Node type from a single lookup by name.
+#[derive(Clone)]
+pub enum NodeHandle {
+    Real(MASQRealNode),
+    Mock(MASQMockNode),
+}
+
+impl NodeHandle {
+    pub fn kind(&self) -> NodeKind {
+        match self {
+            NodeHandle::Real(_) => NodeKind::Real,
+            NodeHandle::Mock(_) => NodeKind::Mock,
+        }
+    }
+
+    pub fn as_masq_node(&self) -> &dyn MASQNode {
+        match self {
+            NodeHandle::Real(node) => node,
+            NodeHandle::Mock(node) => node,
+        }
+    }
+}
+
+const GOSSIP_LISTENING_TURN: Duration = Duration::from_millis(20);
 
 pub struct MASQNodeCluster {
     startup_configs: HashMap<(String, usize), NodeStartupConfig>,
@@ -21,20 +61,25 @@ pub struct MASQNodeCluster {
     mock_nodes: HashMap<String, MASQMockNode>,
     host_node_parent_dir: Option<String>,
     next_index: usize,
+    networks: Vec<DockerNetwork>,
+    run_id: String,
+    crash_watcher: NodeCrashWatcher,
     pub chain: Chain,
 }
 
 impl MASQNodeCluster {
     pub fn start() -> Result<MASQNodeCluster, String> {
+        let run_id = MASQNodeCluster::make_run_id();
+        let network = DockerNetwork::integration_net(&run_id);
         MASQNodeCluster::docker_version()?;
-        MASQNodeCluster::cleanup()?;
-        MASQNodeCluster::create_network()?;
+        MASQNodeCluster::remove_stale_network(&network.name)?;
+        MASQNodeCluster::create_network(&network)?;
         let host_node_parent_dir = match env::var("HOST_NODE_PARENT_DIR") {
             Ok(ref hnpd) if !hnpd.is_empty() => Some(hnpd.clone()),
             _ => None,
         };
         if Self::is_in_jenkins() {
-            MASQNodeCluster::interconnect_network()?;
+            MASQNodeCluster::interconnect_network(&network.name)?;
         }
         Ok(MASQNodeCluster {
             startup_configs: HashMap::new(),
@@ -42,18 +87,43 @@ impl MASQNodeCluster {
             mock_nodes: HashMap::new(),
             host_node_parent_dir,
             next_index: 1,
+            networks: vec![network],
+            run_id,
+            crash_watcher: NodeCrashWatcher::start(),
             chain: TEST_DEFAULT_MULTINODE_CHAIN,
         })
     }
 
+    // The callback is called on a background thread with the name of any Node that goes away
+    // without having been stopped through the cluster: a mock Node whose container disappears, or
+    // a real Node whose MASQNode process dies, even though its container keeps running.
+    pub fn on_node_crash(&mut self, callback: impl Fn(&str) + Send + 'static) {
+        self.crash_watcher.set_callback(Box::new(callback));
+    }
+
+    // The subnet must look like "172.19.0.0/16"; Nodes on it are addressed as 172.19.1.<index>.
+    pub fn create_additional_network(&mut self, name: &str, subnet: &str) -> Result<(), String> {
+        if self.networks.iter().any(|network| network.name == name) {
+            return Err(format!("Network {} already exists", name));
+        }
+        let network = DockerNetwork::new(name, subnet, &self.run_id)?;
+        MASQNodeCluster::remove_stale_network(&network.name)?;
+        MASQNodeCluster::create_network(&network)?;
+        if Self::is_in_jenkins() {
+            MASQNodeCluster::interconnect_network(&network.name)?;
+        }
+        self.networks.push(network);
+        Ok(())
+    }
+
     pub fn next_index(&self) -> usize {
         self.next_index
     }
 
     pub fn prepare_real_node(&mut self, config: &NodeStartupConfig) -> (String, usize) {
-        let index = self.startup_configs.len() + 1;
+        let index = self.next_index;
         let name = MASQRealNode::make_name(index);
-        self.next_index = index + 1;
+        self.next_index += 1;
         self.startup_configs
             .insert((name.clone(), index), config.clone());
         MASQRealNode::prepare(&name);
@@ -62,21 +132,60 @@ impl MASQNodeCluster {
     }
 
     pub fn start_real_node(&mut self, config: NodeStartupConfig) -> MASQRealNode {
+        self.start_real_node_on_network(config, None)
+    }
+
+    pub fn start_real_node_on_network(
+        &mut self,
+        config: NodeStartupConfig,
+        network_name_opt: Option<&str>,
+    ) -> MASQRealNode {
+        let network = self.find_network(network_name_opt);
         let index = self.next_index;
         self.next_index += 1;
-        let node = MASQRealNode::start(config, index, self.host_node_parent_dir.clone());
+        let node = MASQRealNode::start(config, index, self.host_node_parent_dir.clone(), &network);
         let name = node.name().to_string();
+        self.crash_watcher.watch(&name, NodeKind::Real);
         self.real_nodes.insert(name.clone(), node);
         self.real_nodes.get(&name).unwrap().clone()
     }
 
+    // The name replaces the usual test_node_<index>; the index still sets the Node's IP address.
+    pub fn start_real_node_named(&mut self, name: &str, config: NodeStartupConfig) -> MASQRealNode {
+        if let Err(e) = MASQNodeCluster::check_node_name(name) {
+            panic!("{}", e)
+        }
+        if self.real_nodes.contains_key(name) || self.mock_nodes.contains_key(name) {
+            panic!("Node {} is already running in cluster", name)
+        }
+        let network = self.find_network(None);
+        let index = self.next_index;
+        self.next_index += 1;
+        let node = MASQRealNode::start_named(
+            name,
+            config,
+            index,
+            self.host_node_parent_dir.clone(),
+            &network,
+        );
+        self.crash_watcher.watch(name, NodeKind::Real);
+        self.real_nodes.insert(name.to_string(), node);
+        self.real_nodes.get(name).unwrap().clone()
+    }
+
     pub fn start_named_real_node(
         &mut self,
         name: &str,
         index: usize,
         config: NodeStartupConfig,
     ) -> MASQRealNode {
-        MASQRealNode::start_prepared(name, config, index, self.host_node_parent_dir.clone())
+        MASQRealNode::start_prepared(
+            name,
+            config,
+            index,
+            self.host_node_parent_dir.clone(),
+            &self.networks[0],
+        )
     }
 
     pub fn start_mock_node_with_real_cryptde(&mut self, ports: Vec<u16>) -> MASQMockNode {
@@ -96,27 +205,48 @@ impl MASQNodeCluster {
         ports: Vec<u16>,
         public_key: &PublicKey,
     ) -> MutableMASQMockNode {
-        self.start_mock_node(&MutableMASQMockNodeStarter {}, ports, Some(public_key))
+        self.start_mock_node(
+            &MutableMASQMockNodeStarter {},
+            ports,
+            Some(public_key),
+            None,
+        )
     }
 
-    fn start_mock_node_added_to_cluster(
+    pub fn start_mock_node_on_network(
         &mut self,
         ports: Vec<u16>,
         public_key_opt: Option<&PublicKey>,
+        network_name_opt: Option<&str>,
     ) -> MASQMockNode {
-        let mock_node =
-            self.start_mock_node(&ImmutableMASQMockNodeStarter {}, ports, public_key_opt);
+        let mock_node = self.start_mock_node(
+            &ImmutableMASQMockNodeStarter {},
+            ports,
+            public_key_opt,
+            network_name_opt,
+        );
         let name = mock_node.name().to_string();
+        self.crash_watcher.watch(&name, NodeKind::Mock);
         self.mock_nodes.insert(name.clone(), mock_node);
         self.mock_nodes.get(&name).unwrap().clone()
     }
 
+    fn start_mock_node_added_to_cluster(
+        &mut self,
+        ports: Vec<u16>,
+        public_key_opt: Option<&PublicKey>,
+    ) -> MASQMockNode {
+        self.start_mock_node_on_network(ports, public_key_opt, None)
+    }
+
     fn start_mock_node<T>(
         &mut self,
         mock_node_starter: &dyn MASQMockNodeStarter<T>,
         ports: Vec<u16>,
         public_key_opt: Option<&PublicKey>,
+        network_name_opt: Option<&str>,
     ) -> T {
+        let network = self.find_network(network_name_opt);
         let index = self.next_index;
         self.next_index += 1;
         mock_node_starter.start(
@@ -125,21 +255,35 @@ impl MASQNodeCluster {
             self.host_node_parent_dir.clone(),
             public_key_opt,
             self.chain,
+            &network,
         )
     }
 
+    fn find_network(&self, network_name_opt: Option<&str>) -> DockerNetwork {
+        match network_name_opt {
+            None => self.networks[0].clone(),
+            Some(name) => match self.networks.iter().find(|network| network.name == name) {
+                Some(network) => network.clone(),
+                None => panic!("Network {} was not created by this cluster", name),
+            },
+        }
+    }
+
     pub fn finalize_and_add(&mut self, mutable_mock_node: MutableMASQMockNode) -> MASQMockNode {
         let mock_node = MASQMockNode::from(mutable_mock_node);
         let name = mock_node.name().to_string();
+        self.crash_watcher.watch(&name, NodeKind::Mock);
         self.mock_nodes.insert(name.clone(), mock_node);
         self.mock_nodes.get(&name).unwrap().clone()
     }
 
-    pub fn stop(self) {
-        MASQNodeCluster::cleanup().unwrap()
+    pub fn stop(mut self) {
+        self.crash_watcher.stop();
+        MASQNodeCluster::cleanup(&self.run_id, &self.networks).unwrap()
     }
 
     pub fn stop_node(&mut self, name: &str) {
+        self.crash_watcher.unwatch(name);
         match self.real_nodes.remove(name) {
             Some(node) => drop(node),
             None => match self.mock_nodes.remove(name) {
@@ -149,6 +293,48 @@ impl MASQNodeCluster {
         };
     }
 
+    // Stops the real Node's MASQNode process but leaves its container up, so that the Node can be
+    // brought back with restart_node. Going through the cluster keeps the crash watcher quiet.
+    pub fn kill_node(&mut self, name: &str) {
+        let node = self.real_node_to_stop(name);
+        node.kill_node();
+    }
+
+    pub fn graceful_shutdown_node(&mut self, name: &str, timeout: Duration) -> Result<(), String> {
+        let node = self.real_node_to_stop(name);
+        node.graceful_shutdown(timeout)
+    }
+
+    pub fn restart_node(&mut self, name: &str, startup_config: NodeStartupConfig) {
+        let node = match self.real_nodes.get(name) {
+            Some(node) => node,
+            None => panic!("Real Node {} was not found in cluster", name),
+        };
+        node.restart_node(startup_config);
+        self.crash_watcher.watch(name, NodeKind::Real);
+    }
+
+    fn real_node_to_stop(&self, name: &str) -> MASQRealNode {
+        let node = match self.real_nodes.get(name) {
+            Some(node) => node.clone(),
+            None => panic!("Real Node {} was not found in cluster", name),
+        };
+        // Unwatched first, so that the watcher can't catch the Node on its way down
+        self.crash_watcher.unwatch(name);
+        node
+    }
+
+    // Leaves integration_net up so that a fresh batch of Nodes can be started on it.
+    pub fn stop_all_nodes(&mut self) {
+        self.crash_watcher.unwatch_all();
+        self.real_nodes
+            .drain()
+            .map(|(name, _)| name)
+            .chain(self.mock_nodes.drain().map(|(name, _)| name))
+            .for_each(|name| MASQNodeUtils::stop(&name));
+        self.startup_configs.clear();
+    }
+
     pub fn running_node_names(&self) -> HashSet<String> {
         let mut node_name_refs = vec![];
         node_name_refs.extend(self.real_nodes.keys());
@@ -156,6 +342,36 @@ impl MASQNodeCluster {
         node_name_refs.into_iter().cloned().collect()
     }
 
+    pub fn real_node_names(&self) -> HashSet<String> {
+        self.real_nodes.keys().cloned().collect()
+    }
+
+    pub fn mock_node_names(&self) -> HashSet<String> {
+        self.mock_nodes.keys().cloned().collect()
+    }
+
+    pub fn node_kinds(&self) -> HashMap<String, NodeKind> {
+        let real_kinds = self
+            .real_nodes
+            .keys()
+            .map(|name| (name.clone(), NodeKind::Real));
+        let mock_kinds = self
+            .mock_nodes
+            .keys()
+            .map(|name| (name.clone(), NodeKind::Mock));
+        real_kinds.chain(mock_kinds).collect()
+    }
+
+    pub fn get_node_kind(&self, name: &str) -> Option<NodeKind> {
+        if self.real_nodes.contains_key(name) {
+            Some(NodeKind::Real)
+        } else if self.mock_nodes.contains_key(name) {
+            Some(NodeKind::Mock)
+        } else {
+            None
+        }
+    }
+
     pub fn get_real_node_by_name(&self, name: &str) -> Option<MASQRealNode> {
         self.real_nodes.get(name).cloned()
     }
@@ -181,6 +397,39 @@ impl MASQNodeCluster {
         }
     }
 
+    pub fn get_node_handle_by_name(&self, name: &str) -> Option<NodeHandle> {
+        match self.real_nodes.get(name) {
+            Some(node_ref) => Some(NodeHandle::Real(node_ref.clone())),
+            None => self
+                .mock_nodes
+                .get(name)
+                .map(|node_ref| NodeHandle::Mock(node_ref.clone())),
+        }
+    }
+
+    // Mock Nodes can't leave this thread, so each one is given a short turn at listening until
+    // one of them hears Gossip or the time runs out.
+    pub fn wait_for_any_gossip(&self, timeout: Duration) -> Option<(String, Gossip_0v1, IpAddr)> {
+        let mut mock_nodes = self.mock_nodes.values().collect::<Vec<&MASQMockNode>>();
+        mock_nodes.sort_by(|a, b| a.name().cmp(b.name()));
+        if mock_nodes.is_empty() {
+            return None;
+        }
+        let stop_at = Instant::now() + timeout;
+        loop {
+            for mock_node in &mock_nodes {
+                let remaining = stop_at.saturating_duration_since(Instant::now());
+                if remaining == Duration::from_millis(0) {
+                    return None;
+                }
+                let turn = remaining.min(GOSSIP_LISTENING_TURN);
+                if let Some((gossip, sender)) = mock_node.wait_for_gossip(turn) {
+                    return Some((mock_node.name().to_string(), gossip, sender));
+                }
+            }
+        }
+    }
+
     pub fn get_real_node_home_dir_path_by_name(&self, name: String) -> String {
         MASQRealNode::node_home_dir(
             &self
@@ -191,26 +440,117 @@ impl MASQNodeCluster {
         )
     }
 
-    pub fn is_in_jenkins() -> bool {
-        match env::var("HOST_NODE_PARENT_DIR") {
-            Ok(ref value) if value.is_empty() => false,
-            Ok(_) => true,
-            Err(_) => false,
+    pub fn copy_into(&self, name: &str, local: &Path, container_path: &str) -> Result<(), String> {
+        self.require_node(name)?;
+        let local_path = Self::path_string(local)?;
+        let container_spec = format!("{}:{}", name, container_path);
+        Self::docker_cp(&local_path, &container_spec)
+    }
+
+    pub fn copy_out(&self, name: &str, container_path: &str, local: &Path) -> Result<(), String> {
+        self.require_node(name)?;
+        let local_path = Self::path_string(local)?;
+        let container_spec = format!("{}:{}", name, container_path);
+        Self::docker_cp(&container_spec, &local_path)
+    }
+
+    pub fn resource_usage(&self, name: &str) -> Result<ResourceUsage, String> {
+        self.require_node(name)?;
+        let mut command = Command::new(
+            "docker",
+            Command::strings(vec![
+                "stats",
+                "--no-stream",
+                "--format",
+                "{{.CPUPerc}}|{{.MemUsage}}|{{.NetIO}}",
+                name,
+            ]),
+        );
+        if command.wait_for_exit() != 0 {
+            return Err(format!(
+                "Could not get resource usage for {}: {}",
+                name,
+                command.stderr_as_string()
+            ));
         }
+        ResourceUsage::from_docker_stats(command.stdout_as_string().trim())
     }
 
-    fn cleanup() -> Result<(), String> {
-        MASQNodeCluster::stop_running_containers()?;
-        if Self::is_in_jenkins() {
-            Self::disconnect_network()
+    fn require_node(&self, name: &str) -> Result<(), String> {
+        if self.running_node_names().contains(name) {
+            Ok(())
+        } else {
+            Err(format!("Node {} was not found in cluster", name))
+        }
+    }
+
+    fn path_string(path: &Path) -> Result<String, String> {
+        match path.to_str() {
+            Some(path_str) => Ok(path_str.to_string()),
+            None => Err(format!("Path {:?} is not valid UTF-8", path)),
+        }
+    }
+
+    fn docker_cp(from: &str, to: &str) -> Result<(), String> {
+        let mut command = Command::new("docker", Command::strings(vec!["cp", from, to]));
+        match command.wait_for_exit_with_combined_output() {
+            (0, _) => Ok(()),
+            (_, output) => Err(format!("Could not copy {} to {}: {}", from, to, output)),
         }
-        MASQNodeCluster::remove_network_if_running()
     }
 
-    fn stop_running_containers() -> Result<(), String> {
+    pub fn is_in_jenkins() -> bool {
+        running_ci_provider() == Some(CiProvider::Jenkins)
+    }
+
+    // Unique enough that two clusters started on the same host never share one; the pid and host
+    // let a later run tell whether this one is still alive.
+    fn make_run_id() -> String {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("System clock is before 1970")
+            .as_nanos();
+        format!("{}-{}-{}", process::id(), nanos, Self::host_name())
+    }
+
+    // The name becomes the container's hostname on the Docker network, so it has to be DNS-safe.
+    fn check_node_name(name: &str) -> Result<(), String> {
+        let first_char_ok = match name.chars().next() {
+            Some(c) => c.is_ascii_lowercase() || c.is_ascii_digit(),
+            None => false,
+        };
+        let all_chars_ok = name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if first_char_ok && all_chars_ok && name.len() <= 63 {
+            Ok(())
+        } else {
+            Err(format!(
+                "Node name '{}' should be 1 to 63 lowercase letters, digits or '-', starting with a letter or digit",
+                name
+            ))
+        }
+    }
+
+    fn run_label_filter(run_id: &str) -> String {
+        format!("label={}={}", RUN_LABEL_KEY, run_id)
+    }
+
+    fn cleanup(run_id: &str, networks: &[DockerNetwork]) -> Result<(), String> {
+        MASQNodeCluster::stop_running_containers(run_id)?;
+        networks.iter().try_for_each(|network| {
+            if Self::is_in_jenkins() {
+                Self::disconnect_network(&network.name)
+            }
+            MASQNodeCluster::remove_network_if_running(network)
+        })
+    }
+
+    fn stop_running_containers(run_id: &str) -> Result<(), String> {
+        let label_filter = Self::run_label_filter(run_id);
         let mut command = Command::new(
             "docker",
-            Command::strings(vec!["ps", "-q", "--filter", "ancestor=test_node_image"]),
+            Command::strings(vec!["ps", "-q", "--filter", &label_filter]),
         );
         if command.wait_for_exit() != 0 {
             return Err(format!(
@@ -227,12 +567,11 @@ impl MASQNodeCluster {
                     "docker",
                     Command::strings(vec!["stop", "-t", "0", container_id]),
                 );
-                match command.wait_for_exit() {
-                    0 => Ok(()),
-                    _ => Err(format!(
+                match command.wait_for_exit_with_combined_output() {
+                    (0, _) => Ok(()),
+                    (_, output) => Err(format!(
                         "Could not stop node '{}': {}",
-                        container_id,
-                        command.stderr_as_string()
+                        container_id, output
                     )),
                 }
             })
@@ -246,38 +585,203 @@ impl MASQNodeCluster {
         }
     }
 
-    fn disconnect_network() {
+    fn disconnect_network(network_name: &str) {
+        let mut command = Command::new(
+            "docker",
+            Command::strings(vec!["network", "disconnect", network_name, "subjenkins"]),
+        );
+        command.wait_for_exit();
+    }
+
+    fn remove_network_if_running(network: &DockerNetwork) -> Result<(), String> {
+        let label_filter = Self::run_label_filter(&network.run_id);
         let mut command = Command::new(
             "docker",
             Command::strings(vec![
                 "network",
-                "disconnect",
-                "integration_net",
-                "subjenkins",
+                "ls",
+                "--filter",
+                &label_filter,
+                "--format",
+                "{{.Name}}",
             ]),
         );
-        command.wait_for_exit();
+        if command.wait_for_exit() != 0 {
+            return Err(format!(
+                "Could not list networks: {}",
+                command.stderr_as_string()
+            ));
+        }
+        if !Self::names_include(&command.stdout_as_string(), &network.name) {
+            return Ok(());
+        }
+        Self::remove_network(&network.name)
     }
 
-    fn remove_network_if_running() -> Result<(), String> {
-        let output = Self::list_network()?;
-        if !output.contains("integration_net") {
+    // A network left behind by an earlier run that died without cleaning up. Network names are
+    // fixed, so it has to go before this run can create its own, but only if every run it or its
+    // containers are labeled for has died; a live run's network is reported instead.
+    fn remove_stale_network(network_name: &str) -> Result<(), String> {
+        if !Self::names_include(&Self::list_network_names()?, network_name) {
             return Ok(());
         }
+        let labeled_containers = Self::labeled_containers(network_name)?;
+        let this_host = Self::host_name();
+        let live_run_opt = Self::network_run_id(network_name)?
+            .into_iter()
+            .chain(labeled_containers.iter().map(|(_, run_id)| run_id.clone()))
+            .find(|run_id| Self::run_is_alive(run_id, &this_host, Self::process_is_alive));
+        if let Some(run_id) = live_run_opt {
+            return Err(format!(
+                "Network {} is still in use by run {}, which hasn't finished",
+                network_name, run_id
+            ));
+        }
+        Self::remove_stale_containers(&labeled_containers)?;
+        if Self::is_in_jenkins() {
+            Self::disconnect_network(network_name)
+        }
+        Self::remove_network(network_name)
+    }
+
+    fn network_run_id(network_name: &str) -> Result<Option<String>, String> {
+        let mut command = Command::new(
+            "docker",
+            Command::strings(vec![
+                "network",
+                "inspect",
+                "-f",
+                &format!("{{{{index .Labels \"{}\"}}}}", RUN_LABEL_KEY),
+                network_name,
+            ]),
+        );
+        if command.wait_for_exit() != 0 {
+            return Err(format!(
+                "Could not inspect network {}: {}",
+                network_name,
+                command.stderr_as_string()
+            ));
+        }
+        match command.stdout_as_string().trim() {
+            "" | "<no value>" => Ok(None),
+            run_id => Ok(Some(run_id.to_string())),
+        }
+    }
+
+    // Container IDs paired with the run each one is labeled for
+    fn labeled_containers(network_name: &str) -> Result<Vec<(String, String)>, String> {
+        let network_filter = format!("network={}", network_name);
+        let mut command = Command::new(
+            "docker",
+            Command::strings(vec![
+                "ps",
+                "-a",
+                "--filter",
+                &network_filter,
+                "--filter",
+                &format!("label={}", RUN_LABEL_KEY),
+                "--format",
+                &format!("{{{{.ID}}}} {{{{.Label \"{}\"}}}}", RUN_LABEL_KEY),
+            ]),
+        );
+        if command.wait_for_exit() != 0 {
+            return Err(format!(
+                "Could not list containers on network {}: {}",
+                network_name,
+                command.stderr_as_string()
+            ));
+        }
+        Ok(command
+            .stdout_as_string()
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.trim().splitn(2, ' ');
+                match (fields.next(), fields.next()) {
+                    (Some(container_id), Some(run_id)) if !container_id.is_empty() => {
+                        Some((container_id.to_string(), run_id.trim().to_string()))
+                    }
+                    _ => None,
+                }
+            })
+            .collect())
+    }
+
+    fn remove_stale_containers(labeled_containers: &[(String, String)]) -> Result<(), String> {
+        let errors = labeled_containers
+            .iter()
+            .filter_map(|(container_id, _)| {
+                let mut command = Command::new(
+                    "docker",
+                    Command::strings(vec!["rm", "-f", container_id.as_str()]),
+                );
+                match command.wait_for_exit_with_combined_output() {
+                    (0, _) => None,
+                    (_, output) => Some(format!(
+                        "Could not remove stale container '{}': {}",
+                        container_id, output
+                    )),
+                }
+            })
+            .collect::<Vec<String>>();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
+    // Run IDs look like <pid>-<nanos>-<host>. A run started on another host can't be checked from
+    // here, so it's taken to be alive; a run ID that doesn't parse can't belong to a live run.
+    fn run_is_alive(run_id: &str, this_host: &str, process_is_alive: fn(u32) -> bool) -> bool {
+        let mut fields = run_id.splitn(3, '-');
+        let pid = match fields.next().map(u32::from_str) {
+            Some(Ok(pid)) => pid,
+            _ => return false,
+        };
+        match (fields.next(), fields.next()) {
+            (Some(_), Some(host)) if host != this_host => true,
+            _ => process_is_alive(pid),
+        }
+    }
+
+    fn process_is_alive(pid: u32) -> bool {
+        std::process::Command::new("ps")
+            .args(["-p", &pid.to_string()])
+            .output()
+            .map(|output| output.status.success())
+            // If ps can't tell us, don't risk removing a live run's containers
+            .unwrap_or(true)
+    }
+
+    fn host_name() -> String {
+        match std::process::Command::new("hostname").output() {
+            Ok(output) if output.status.success() => {
+                String::from_utf8_lossy(&output.stdout).trim().to_string()
+            }
+            _ => "unknown-host".to_string(),
+        }
+    }
+
+    fn names_include(names: &str, name: &str) -> bool {
+        names.lines().any(|line| line.trim() == name)
+    }
+
+    fn remove_network(network_name: &str) -> Result<(), String> {
         let mut command = Command::new(
             "docker",
-            Command::strings(vec!["network", "rm", "integration_net"]),
+            Command::strings(vec!["network", "rm", network_name]),
         );
         match command.wait_for_exit() {
             0 => Ok(()),
             _ if command
                 .stderr_as_string()
-                .starts_with("Error: No such network: integration_net") =>
+                .starts_with(&format!("Error: No such network: {}", network_name)) =>
             {
                 Ok(())
             }
             _ => Err(format!(
-                "Could not remove network integration_net: {}",
+                "Could not remove network {}: {}",
+                network_name,
                 command.stderr_as_string()
             )),
         }
@@ -294,8 +798,11 @@ impl MASQNodeCluster {
         Ok(command.stdout_as_string())
     }
 
-    fn list_network() -> Result<String, String> {
-        let mut command = Command::new("docker", Command::strings(vec!["network", "ls"]));
+    fn list_network_names() -> Result<String, String> {
+        let mut command = Command::new(
+            "docker",
+            Command::strings(vec!["network", "ls", "--format", "{{.Name}}"]),
+        );
         if command.wait_for_exit() != 0 {
             return Err(format!(
                 "Could not list networks: {}",
@@ -305,40 +812,312 @@ impl MASQNodeCluster {
         Ok(command.stdout_as_string())
     }
 
-    fn create_network() -> Result<(), String> {
+    pub fn subnet_available(subnet: &str) -> Result<bool, String> {
+        Ok(Self::network_using_subnet(subnet)?.is_none())
+    }
+
+    fn network_using_subnet(subnet: &str) -> Result<Option<(String, String)>, String> {
+        Ok(Self::find_overlapping_subnet(
+            subnet,
+            &Self::existing_subnets()?,
+        ))
+    }
+
+    fn existing_subnets() -> Result<Vec<(String, String)>, String> {
+        let mut command = Command::new(
+            "docker",
+            Command::strings(vec!["network", "ls", "--format", "{{.Name}}"]),
+        );
+        let names = command
+            .stdout_or_stderr()
+            .map_err(|e| format!("Could not list networks: {}", e))?;
+        let mut args = vec![
+            "network",
+            "inspect",
+            "--format",
+            "{{.Name}}|{{range .IPAM.Config}}{{.Subnet}} {{end}}",
+        ];
+        args.extend(names.split_whitespace());
+        let mut command = Command::new("docker", Command::strings(args));
+        let output = command
+            .stdout_or_stderr()
+            .map_err(|e| format!("Could not inspect networks: {}", e))?;
+        Ok(output
+            .lines()
+            .filter_map(|line| line.split_once('|'))
+            .flat_map(|(name, subnets)| {
+                subnets
+                    .split_whitespace()
+                    .map(move |subnet| (name.to_string(), subnet.to_string()))
+            })
+            .collect())
+    }
+
+    fn find_overlapping_subnet(
+        subnet: &str,
+        existing: &[(String, String)],
+    ) -> Option<(String, String)> {
+        existing
+            .iter()
+            .find(|(_, existing_subnet)| Self::subnets_overlap(subnet, existing_subnet))
+            .cloned()
+    }
+
+    // Only IPv4 subnets are compared; anything else is taken not to overlap.
+    fn subnets_overlap(first: &str, second: &str) -> bool {
+        match (Self::parse_subnet(first), Self::parse_subnet(second)) {
+            (Some((first_base, first_bits)), Some((second_base, second_bits))) => {
+                let bits = first_bits.min(second_bits);
+                let mask = if bits == 0 {
+                    0
+                } else {
+                    u32::MAX << (32 - bits)
+                };
+                (first_base & mask) == (second_base & mask)
+            }
+            _ => false,
+        }
+    }
+
+    fn parse_subnet(subnet: &str) -> Option<(u32, u32)> {
+        let (address, bits) = subnet.split_once('/')?;
+        let address = Ipv4Addr::from_str(address).ok()?;
+        let bits = bits.parse::<u32>().ok().filter(|bits| *bits <= 32)?;
+        Some((u32::from(address), bits))
+    }
+
+    fn create_network(network: &DockerNetwork) -> Result<(), String> {
+        if let Some((existing_name, existing_subnet)) = Self::network_using_subnet(&network.subnet)?
+        {
+            return Err(format!(
+                "Could not create network {}: its subnet {} overlaps subnet {} of existing network {}. Remove that network (docker network rm {}) or choose another subnet.",
+                network.name, network.subnet, existing_subnet, existing_name, existing_name
+            ));
+        }
+        let subnet_arg = format!("--subnet={}", network.subnet);
+        let run_label = network.run_label();
         let mut command = Command::new(
             "docker",
             Command::strings(vec![
                 "network",
                 "create",
-                "--subnet=172.18.0.0/16",
-                "integration_net",
+                &subnet_arg,
+                "--label",
+                &run_label,
+                &network.name,
             ]),
         );
-        match command.wait_for_exit() {
-            0 => Ok(()),
-            _ => Err(format!(
-                "Could not create network integration_net: {}",
-                command.stderr_as_string()
+        match command.wait_for_exit_with_combined_output() {
+            (0, _) => Ok(()),
+            (_, output) => Err(format!(
+                "Could not create network {}: {}",
+                network.name, output
             )),
         }
     }
 
-    fn interconnect_network() -> Result<(), String> {
+    fn interconnect_network(network_name: &str) -> Result<(), String> {
         let mut command = Command::new(
             "docker",
-            Command::strings(vec!["network", "connect", "integration_net", "subjenkins"]),
+            Command::strings(vec!["network", "connect", network_name, "subjenkins"]),
         );
         match command.wait_for_exit() {
             0 => Ok(()),
             _ => Err(format!(
-                "Could not connect subjenkins to integration_net: {}",
+                "Could not connect subjenkins to {}: {}",
+                network_name,
                 command.stderr_as_string()
             )),
         }
     }
 }
 
+type NodeCrashCallback = Box<dyn Fn(&str) + Send>;
+
+const CRASH_WATCHER_INTERVAL: Duration = Duration::from_millis(500);
+
+struct NodeCrashWatcher {
+    watched_names: Arc<Mutex<HashMap<String, NodeKind>>>,
+    callback_opt: Arc<Mutex<Option<NodeCrashCallback>>>,
+    stopping: Arc<AtomicBool>,
+    join_handle_opt: Option<JoinHandle<()>>,
+}
+
+impl Drop for NodeCrashWatcher {
+    fn drop(&mut self) {
+        self.stop()
+    }
+}
+
+impl NodeCrashWatcher {
+    fn start() -> Self {
+        let watched_names: Arc<Mutex<HashMap<String, NodeKind>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+        let callback_opt: Arc<Mutex<Option<NodeCrashCallback>>> = Arc::new(Mutex::new(None));
+        let stopping = Arc::new(AtomicBool::new(false));
+        let inner_watched_names = watched_names.clone();
+        let inner_callback_opt = callback_opt.clone();
+        let inner_stopping = stopping.clone();
+        let join_handle = thread::spawn(move || {
+            while !inner_stopping.load(Ordering::Relaxed) {
+                thread::sleep(CRASH_WATCHER_INTERVAL);
+                // Docker is slow, so it's asked about a snapshot rather than under the lock
+                let watched_names = inner_watched_names.lock().unwrap().clone();
+                if watched_names.is_empty() {
+                    continue;
+                }
+                let running_names = match Self::running_container_names() {
+                    Some(names) => names,
+                    None => continue,
+                };
+                let missing_names = watched_names
+                    .into_iter()
+                    .filter(|(name, kind)| {
+                        !running_names.contains(name)
+                            || (*kind == NodeKind::Real && !Self::masq_node_is_running(name))
+                    })
+                    .map(|(name, _)| name)
+                    .collect::<Vec<String>>();
+                // Anything unwatched in the meantime was stopped on purpose
+                let crashed_names = {
+                    let mut watched_names = inner_watched_names.lock().unwrap();
+                    missing_names
+                        .into_iter()
+                        .filter(|name| watched_names.remove(name).is_some())
+                        .collect::<Vec<String>>()
+                };
+                if let Some(callback) = inner_callback_opt.lock().unwrap().as_ref() {
+                    crashed_names.iter().for_each(|name| callback(name));
+                }
+            }
+        });
+        Self {
+            watched_names,
+            callback_opt,
+            stopping,
+            join_handle_opt: Some(join_handle),
+        }
+    }
+
+    fn set_callback(&self, callback: NodeCrashCallback) {
+        *self.callback_opt.lock().unwrap() = Some(callback);
+    }
+
+    fn watch(&self, name: &str, kind: NodeKind) {
+        self.watched_names
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), kind);
+    }
+
+    fn unwatch(&self, name: &str) {
+        self.watched_names.lock().unwrap().remove(name);
+    }
+
+    fn unwatch_all(&self) {
+        self.watched_names.lock().unwrap().clear();
+    }
+
+    fn stop(&mut self) {
+        self.stopping.store(true, Ordering::Relaxed);
+        if let Some(join_handle) = self.join_handle_opt.take() {
+            let _ = join_handle.join();
+        }
+    }
+
+    // Polls too often to go through crate::command::Command, which echoes every invocation.
+    fn running_container_names() -> Option<HashSet<String>> {
+        let output = std::process::Command::new("docker")
+            .args(["ps", "--format", "{{.Names}}"])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(|line| line.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect(),
+        )
+    }
+
+    // A real Node's container idles on after its MASQNode process dies, so the process is checked.
+    fn masq_node_is_running(name: &str) -> bool {
+        std::process::Command::new("docker")
+            .args(["exec", name, "pgrep", "MASQNode"])
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceUsage {
+    pub cpu_percent: f64,
+    pub memory_used_bytes: u64,
+    pub memory_limit_bytes: u64,
+    pub network_received_bytes: u64,
+    pub network_sent_bytes: u64,
+}
+
+impl ResourceUsage {
+    // Expects a line like "0.15%|12.5MiB / 7.77GiB|1.2kB / 648B", as produced by
+    // docker stats --format "{{.CPUPerc}}|{{.MemUsage}}|{{.NetIO}}"
+    pub fn from_docker_stats(line: &str) -> Result<Self, String> {
+        let fields = line.split('|').collect::<Vec<&str>>();
+        if fields.len() != 3 {
+            return Err(format!("Unrecognized docker stats output: '{}'", line));
+        }
+        let cpu_percent = fields[0]
+            .trim()
+            .trim_end_matches('%')
+            .parse::<f64>()
+            .map_err(|_| format!("Unrecognized CPU percentage: '{}'", fields[0]))?;
+        let (memory_used_bytes, memory_limit_bytes) = Self::parse_pair(fields[1])?;
+        let (network_received_bytes, network_sent_bytes) = Self::parse_pair(fields[2])?;
+        Ok(ResourceUsage {
+            cpu_percent,
+            memory_used_bytes,
+            memory_limit_bytes,
+            network_received_bytes,
+            network_sent_bytes,
+        })
+    }
+
+    fn parse_pair(pair: &str) -> Result<(u64, u64), String> {
+        match pair.split(" / ").collect::<Vec<&str>>().as_slice() {
+            [left, right] => Ok((Self::parse_size(left)?, Self::parse_size(right)?)),
+            _ => Err(format!("Unrecognized docker stats pair: '{}'", pair)),
+        }
+    }
+
+    fn parse_size(size: &str) -> Result<u64, String> {
+        let size = size.trim();
+        let split_at = size
+            .find(|c: char| c.is_ascii_alphabetic())
+            .unwrap_or(size.len());
+        let (number, unit) = size.split_at(split_at);
+        let multiplier: f64 = match unit {
+            "B" | "" => 1.0,
+            "kB" | "KB" => 1e3,
+            "MB" => 1e6,
+            "GB" => 1e9,
+            "TB" => 1e12,
+            "KiB" => 1024.0,
+            "MiB" => 1024.0 * 1024.0,
+            "GiB" => 1024.0 * 1024.0 * 1024.0,
+            "TiB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+            _ => return Err(format!("Unrecognized size unit: '{}'", size)),
+        };
+        let number = number
+            .parse::<f64>()
+            .map_err(|_| format!("Unrecognized size: '{}'", size))?;
+        Ok((number * multiplier).round() as u64)
+    }
+}
+
 pub struct DockerHostSocketAddr {
     socket_addrs: Vec<SocketAddr>,
 }
@@ -361,3 +1140,203 @@ impl DockerHostSocketAddr {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_ids_differ_between_clusters() {
+        let first = MASQNodeCluster::make_run_id();
+        thread::sleep(Duration::from_millis(1));
+        let second = MASQNodeCluster::make_run_id();
+
+        assert_ne!(first, second);
+        assert!(first.starts_with(&format!("{}-", process::id())));
+    }
+
+    #[test]
+    fn run_ids_name_the_process_and_host_that_made_them() {
+        let result = MASQNodeCluster::make_run_id();
+
+        assert!(result.ends_with(&format!("-{}", MASQNodeCluster::host_name())));
+        assert!(MASQNodeCluster::run_is_alive(
+            &result,
+            &MASQNodeCluster::host_name(),
+            MASQNodeCluster::process_is_alive
+        ));
+    }
+
+    #[test]
+    fn run_on_this_host_is_alive_only_while_its_process_is() {
+        let alive = |_: u32| true;
+        let dead = |_: u32| false;
+
+        assert!(MASQNodeCluster::run_is_alive(
+            "1234-5678-here",
+            "here",
+            alive
+        ));
+        assert!(!MASQNodeCluster::run_is_alive(
+            "1234-5678-here",
+            "here",
+            dead
+        ));
+        assert!(!MASQNodeCluster::run_is_alive("1234-5678", "here", dead));
+    }
+
+    #[test]
+    fn run_on_another_host_is_taken_to_be_alive() {
+        let dead = |_: u32| false;
+
+        assert!(MASQNodeCluster::run_is_alive(
+            "1234-5678-elsewhere-host",
+            "here",
+            dead
+        ));
+    }
+
+    #[test]
+    fn run_id_that_does_not_parse_belongs_to_no_live_run() {
+        let alive = |_: u32| true;
+
+        assert!(!MASQNodeCluster::run_is_alive("", "here", alive));
+        assert!(!MASQNodeCluster::run_is_alive(
+            "booga-5678-here",
+            "here",
+            alive
+        ));
+    }
+
+    #[test]
+    fn memorable_node_names_are_accepted() {
+        vec!["gateway", "exit-node", "relay-2", "3rd"]
+            .into_iter()
+            .for_each(|name| assert_eq!(MASQNodeCluster::check_node_name(name), Ok(())));
+    }
+
+    #[test]
+    fn node_names_that_are_not_dns_safe_are_rejected() {
+        let too_long = "a".repeat(64);
+        vec![
+            "",
+            "Gateway",
+            "-gateway",
+            "_gateway",
+            "relay_2",
+            "gate way",
+            "gate.way",
+            &too_long,
+        ]
+        .into_iter()
+        .for_each(|name| {
+            let result = MASQNodeCluster::check_node_name(name);
+
+            assert_eq!(
+                result,
+                Err(format!(
+                    "Node name '{}' should be 1 to 63 lowercase letters, digits or '-', starting with a letter or digit",
+                    name
+                ))
+            );
+        })
+    }
+
+    #[test]
+    fn run_label_filter_selects_only_this_run() {
+        let result = MASQNodeCluster::run_label_filter("1234-5678");
+
+        assert_eq!(result, "label=masq-run=1234-5678".to_string());
+    }
+
+    #[test]
+    fn names_include_requires_an_exact_match() {
+        let names = "integration_net_2\nother_net\n";
+
+        assert!(MASQNodeCluster::names_include(names, "other_net"));
+        assert!(!MASQNodeCluster::names_include(names, "integration_net"));
+    }
+
+    #[test]
+    fn subnets_overlap_when_one_contains_the_other() {
+        assert!(MASQNodeCluster::subnets_overlap(
+            "172.18.0.0/16",
+            "172.18.0.0/16"
+        ));
+        assert!(MASQNodeCluster::subnets_overlap(
+            "172.18.0.0/16",
+            "172.18.5.0/24"
+        ));
+        assert!(MASQNodeCluster::subnets_overlap(
+            "172.16.0.0/12",
+            "172.18.0.0/16"
+        ));
+        assert!(!MASQNodeCluster::subnets_overlap(
+            "172.18.0.0/16",
+            "172.19.0.0/16"
+        ));
+        assert!(!MASQNodeCluster::subnets_overlap(
+            "172.18.0.0/16",
+            "fd00::/64"
+        ));
+    }
+
+    #[test]
+    fn find_overlapping_subnet_names_the_conflicting_network() {
+        let existing = vec![
+            ("bridge".to_string(), "172.17.0.0/16".to_string()),
+            (
+                "somebody_elses_net".to_string(),
+                "172.18.0.0/20".to_string(),
+            ),
+        ];
+
+        let conflict = MASQNodeCluster::find_overlapping_subnet("172.18.0.0/16", &existing);
+        let no_conflict = MASQNodeCluster::find_overlapping_subnet("172.19.0.0/16", &existing);
+
+        assert_eq!(
+            conflict,
+            Some((
+                "somebody_elses_net".to_string(),
+                "172.18.0.0/20".to_string()
+            ))
+        );
+        assert_eq!(no_conflict, None);
+    }
+
+    #[test]
+    fn resource_usage_parses_docker_stats_line() {
+        let result = ResourceUsage::from_docker_stats("0.15%|12.5MiB / 7.77GiB|1.2kB / 648B");
+
+        assert_eq!(
+            result,
+            Ok(ResourceUsage {
+                cpu_percent: 0.15,
+                memory_used_bytes: 13_107_200,
+                memory_limit_bytes: 8_342_973_972,
+                network_received_bytes: 1_200,
+                network_sent_bytes: 648,
+            })
+        );
+    }
+
+    #[test]
+    fn resource_usage_complains_about_unrecognizable_output() {
+        assert_eq!(
+            ResourceUsage::from_docker_stats("booga"),
+            Err("Unrecognized docker stats output: 'booga'".to_string())
+        );
+        assert_eq!(
+            ResourceUsage::from_docker_stats("lots%|1MiB / 2MiB|1B / 2B"),
+            Err("Unrecognized CPU percentage: 'lots%'".to_string())
+        );
+        assert_eq!(
+            ResourceUsage::from_docker_stats("1.0%|1MiB|1B / 2B"),
+            Err("Unrecognized docker stats pair: '1MiB'".to_string())
+        );
+        assert_eq!(
+            ResourceUsage::from_docker_stats("1.0%|1MiB / 2MiB|1B / 2XB"),
+            Err("Unrecognized size unit: '2XB'".to_string())
+        );
+    }
+}
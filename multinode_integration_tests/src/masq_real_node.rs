@@ -1,5 +1,6 @@
 // Copyright (c) 2019, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
 use crate::command::Command;
+use crate::masq_node::DockerNetwork;
 use crate::masq_node::MASQNode;
 use crate::masq_node::MASQNodeUtils;
 use crate::masq_node::NodeReference;
@@ -9,10 +10,13 @@ use crate::masq_node_server::MASQNodeServer;
 use crate::masq_node_ui_client::MASQNodeUIClient;
 use bip39::{Language, Mnemonic, Seed};
 use log::Level;
+use masq_lib::blockchains::blockchain_records::CHAINS;
 use masq_lib::blockchains::chains::Chain;
 use masq_lib::constants::{CURRENT_LOGFILE_NAME, DEFAULT_UI_PORT};
+use masq_lib::messages::{ToMessageBody, UiShutdownRequest};
 use masq_lib::test_utils::utils::TEST_DEFAULT_MULTINODE_CHAIN;
 use masq_lib::utils::localhost;
+use masq_lib::utils::NeighborhoodModeLight;
 use masq_lib::utils::{DEFAULT_CONSUMING_DERIVATION_PATH, DEFAULT_EARNING_DERIVATION_PATH};
 use node_lib::blockchain::bip32::Bip32ECKeyProvider;
 use node_lib::neighborhood::DEFAULT_MIN_HOPS;
@@ -30,11 +34,13 @@ use std::fmt::Display;
 use std::net::IpAddr;
 use std::net::Ipv4Addr;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::str::FromStr;
 use std::string::ToString;
+use std::sync::mpsc::RecvTimeoutError;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 pub const DATA_DIRECTORY: &str = "/node_root/home";
 pub const STANDARD_CLIENT_TIMEOUT_MILLIS: u64 = 1000;
@@ -129,11 +135,15 @@ pub struct NodeStartupConfig {
     pub memory_opt: Option<String>,
     pub fake_public_key_opt: Option<PublicKey>,
     pub blockchain_service_url_opt: Option<String>,
+    pub gas_price_opt: Option<u64>,
     pub chain: Chain,
     pub db_password_opt: Option<String>,
     pub scans_opt: Option<bool>,
     pub log_level_opt: Option<Level>,
     pub ui_port_opt: Option<u16>,
+    pub data_directory_opt: Option<PathBuf>,
+    pub extra_env: Vec<(String, String)>,
+    pub extra_args: Vec<String>,
 }
 
 impl Default for NodeStartupConfig {
@@ -161,11 +171,15 @@ impl NodeStartupConfig {
             memory_opt: None,
             fake_public_key_opt: None,
             blockchain_service_url_opt: None,
+            gas_price_opt: None,
             chain: TEST_DEFAULT_MULTINODE_CHAIN,
             db_password_opt: Some("password".to_string()),
             scans_opt: None,
             log_level_opt: None,
             ui_port_opt: None,
+            data_directory_opt: None,
+            extra_env: vec![],
+            extra_args: vec![],
         }
     }
 
@@ -173,6 +187,14 @@ impl NodeStartupConfig {
         self.firewall_opt.clone()
     }
 
+    // The data directory inside the container; the host's node home is mounted at DATA_DIRECTORY.
+    pub fn data_directory(&self) -> String {
+        match &self.data_directory_opt {
+            Some(path) => path.to_string_lossy().to_string(),
+            None => DATA_DIRECTORY.to_string(),
+        }
+    }
+
     #[allow(clippy::vec_init_then_push)]
     fn make_args(&self) -> Vec<String> {
         let mut args = vec![];
@@ -197,9 +219,18 @@ impl NodeStartupConfig {
             args.push(clandestine_port.to_string());
         }
         args.push("--log-level".to_string());
-        args.push("trace".to_string());
+        args.push(
+            match self.log_level_opt.unwrap_or(Level::Trace) {
+                Level::Error => "error",
+                Level::Warn => "warn",
+                Level::Info => "info",
+                Level::Debug => "debug",
+                Level::Trace => "trace",
+            }
+            .to_string(),
+        );
         args.push("--data-directory".to_string());
-        args.push(DATA_DIRECTORY.to_string());
+        args.push(self.data_directory());
         args.push("--rate-pack".to_string());
         args.push(format!("\"{}\"", self.rate_pack));
         args.push("--payment-thresholds".to_string());
@@ -220,6 +251,10 @@ impl NodeStartupConfig {
             args.push("--blockchain-service-url".to_string());
             args.push(blockchain_service_url.to_string());
         }
+        if let Some(gas_price) = self.gas_price_opt {
+            args.push("--gas-price".to_string());
+            args.push(gas_price.to_string());
+        }
         args.push("--chain".to_string());
         args.push(self.chain.rec().literal_identifier.to_string());
 
@@ -237,24 +272,11 @@ impl NodeStartupConfig {
             });
         }
 
-        if let Some(ref level) = self.log_level_opt {
-            args.push("--log-level".to_string());
-            args.push(
-                match level {
-                    Level::Error => "error",
-                    Level::Warn => "warn",
-                    Level::Info => "info",
-                    Level::Debug => "debug",
-                    Level::Trace => "trace",
-                }
-                .to_string(),
-            );
-        }
-
         if let Some(ref ui_port) = self.ui_port_opt {
             args.push("--ui-port".to_string());
             args.push(ui_port.to_string());
         }
+        args.extend(self.extra_args.clone());
         args
     }
 
@@ -263,6 +285,7 @@ impl NodeStartupConfig {
     }
 
     fn make_establish_wallet_args(&self) -> Option<Vec<String>> {
+        let data_directory = self.data_directory();
         let args = match (&self.earning_wallet_info, &self.consuming_wallet_info) {
             (EarningWalletInfo::None, ConsumingWalletInfo::None) => return None,
             (EarningWalletInfo::None, ConsumingWalletInfo::PrivateKey(_)) => return None,
@@ -270,7 +293,7 @@ impl NodeStartupConfig {
                 Self::slices_to_strings(vec![
                     "--recover-wallet",
                     "--data-directory",
-                    DATA_DIRECTORY,
+                    &data_directory,
                     "--mnemonic",
                     &format!("\"{}\"", &phrase),
                     "--mnemonic-passphrase",
@@ -289,7 +312,7 @@ impl NodeStartupConfig {
             ) => Self::slices_to_strings(vec![
                 "--recover-wallet",
                 "--data-directory",
-                DATA_DIRECTORY,
+                &data_directory,
                 "--mnemonic",
                 &format!("\"{}\"", &phrase),
                 "--mnemonic-passphrase",
@@ -305,7 +328,7 @@ impl NodeStartupConfig {
                 Self::slices_to_strings(vec![
                     "--recover-wallet",
                     "--data-directory",
-                    DATA_DIRECTORY,
+                    &data_directory,
                     "--mnemonic",
                     &format!("\"{}\"", &phrase),
                     "--mnemonic-passphrase",
@@ -322,7 +345,7 @@ impl NodeStartupConfig {
             ) => Self::slices_to_strings(vec![
                 "--recover-wallet",
                 "--data-directory",
-                DATA_DIRECTORY,
+                &data_directory,
                 "--mnemonic",
                 &format!("\"{}\"", &phrase),
                 "--mnemonic-passphrase",
@@ -345,7 +368,7 @@ impl NodeStartupConfig {
                 Self::slices_to_strings(vec![
                     "--recover-wallet",
                     "--data-directory",
-                    DATA_DIRECTORY,
+                    &data_directory,
                     "--mnemonic",
                     &format!("\"{}\"", &ephrase),
                     "--mnemonic-passphrase",
@@ -424,10 +447,14 @@ pub struct NodeStartupConfigBuilder {
     memory: Option<String>,
     fake_public_key: Option<PublicKey>,
     blockchain_service_url: Option<String>,
+    gas_price_opt: Option<u64>,
     chain: Chain,
     scans_opt: Option<bool>,
     log_level_opt: Option<Level>,
     ui_port_opt: Option<u16>,
+    data_directory_opt: Option<PathBuf>,
+    extra_env: Vec<(String, String)>,
+    extra_args: Vec<String>,
     db_password: Option<String>,
 }
 
@@ -480,10 +507,14 @@ impl NodeStartupConfigBuilder {
             memory: None,
             fake_public_key: None,
             blockchain_service_url: None,
+            gas_price_opt: None,
             chain: TEST_DEFAULT_MULTINODE_CHAIN,
             scans_opt: None,
             log_level_opt: None,
             ui_port_opt: None,
+            data_directory_opt: None,
+            extra_env: vec![],
+            extra_args: vec![],
             db_password: Some("password".to_string()),
         }
     }
@@ -506,30 +537,30 @@ impl NodeStartupConfigBuilder {
             memory: config.memory_opt.clone(),
             fake_public_key: config.fake_public_key_opt.clone(),
             blockchain_service_url: config.blockchain_service_url_opt.clone(),
+            gas_price_opt: config.gas_price_opt,
             chain: config.chain,
             scans_opt: config.scans_opt,
             log_level_opt: config.log_level_opt,
             ui_port_opt: config.ui_port_opt,
+            data_directory_opt: config.data_directory_opt.clone(),
+            extra_env: config.extra_env.clone(),
+            extra_args: config.extra_args.clone(),
             db_password: config.db_password_opt.clone(),
         }
     }
 
-    pub fn neighborhood_mode(mut self, value: &str) -> Self {
-        if vec![
-            "zero-hop".to_string(),
-            "consume-only".to_string(),
-            "originate-only".to_string(),
-            "standard".to_string(),
-        ]
-        .contains(&value.to_string())
-        {
-            self.neighborhood_mode = value.to_string();
-            self
-        } else {
-            panic!("Unrecognized --neighborhood-mode: '{}'", value)
+    pub fn neighborhood_mode(self, value: &str) -> Self {
+        match NeighborhoodModeLight::from_str(value) {
+            Ok(mode) => self.neighborhood_mode_light(mode),
+            Err(_) => panic!("Unrecognized --neighborhood-mode: '{}'", value),
         }
     }
 
+    pub fn neighborhood_mode_light(mut self, value: NeighborhoodModeLight) -> Self {
+        self.neighborhood_mode = value.to_string();
+        self
+    }
+
     pub fn min_hops(mut self, value: Hops) -> Self {
         self.min_hops = value;
         self
@@ -560,6 +591,11 @@ impl NodeStartupConfigBuilder {
         self
     }
 
+    pub fn extend_neighbors(mut self, value: Vec<NodeReference>) -> Self {
+        self.neighbors.extend(value);
+        self
+    }
+
     pub fn clandestine_port(mut self, value: u16) -> Self {
         self.clandestine_port_opt = Some(value);
         self
@@ -585,6 +621,14 @@ impl NodeStartupConfigBuilder {
         self
     }
 
+    pub fn earning_wallet(self, address: &str) -> Self {
+        self.earning_wallet_info(EarningWalletInfo::Address(address.to_string()))
+    }
+
+    pub fn consuming_wallet(self, private_key_hex: &str) -> Self {
+        self.consuming_wallet_info(ConsumingWalletInfo::PrivateKey(private_key_hex.to_string()))
+    }
+
     pub fn rate_pack(mut self, value: RatePack) -> Self {
         self.rate_pack = value;
         self
@@ -620,11 +664,44 @@ impl NodeStartupConfigBuilder {
         self
     }
 
+    pub fn gas_price(mut self, gas_price: u64) -> Self {
+        self.gas_price_opt = Some(gas_price);
+        self
+    }
+
     pub fn chain(mut self, chain: Chain) -> Self {
         self.chain = chain;
         self
     }
 
+    pub fn chain_name(self, value: &str) -> Self {
+        match CHAINS
+            .iter()
+            .find(|record| record.literal_identifier == value)
+        {
+            Some(record) => self.chain(record.self_id),
+            None => panic!("Unrecognized --chain: '{}'", value),
+        }
+    }
+
+    pub fn data_directory(mut self, value: PathBuf) -> Self {
+        self.data_directory_opt = Some(value);
+        self
+    }
+
+    // Escape hatches for Node options the builder doesn't model yet. Environment values are quoted
+    // for the shell, but arguments go through it as given, after the structured arguments; a flag
+    // that takes a value needs two arg() calls.
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.extra_env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn arg(mut self, flag: &str) -> Self {
+        self.extra_args.push(flag.to_string());
+        self
+    }
+
     pub fn scans(mut self, scans: bool) -> Self {
         self.scans_opt = Some(scans);
         self
@@ -646,6 +723,31 @@ impl NodeStartupConfigBuilder {
     }
 
     pub fn build(self) -> NodeStartupConfig {
+        if let Some(ref url) = self.blockchain_service_url {
+            if !Self::is_well_formed_url(url) {
+                panic!("Malformed blockchain service URL: '{}'", url)
+            }
+        }
+        if let EarningWalletInfo::Address(ref address) = self.earning_wallet_info {
+            if !Self::is_well_formed_address(address) {
+                panic!("Malformed earning wallet address: '{}'", address)
+            }
+        }
+        if let ConsumingWalletInfo::PrivateKey(ref key) = self.consuming_wallet_info {
+            if !Self::is_well_formed_private_key(key) {
+                panic!(
+                    "Malformed consuming wallet private key: expected 64 hex digits, found {} characters",
+                    key.len()
+                )
+            }
+        }
+        if let Some((key, _)) = self
+            .extra_env
+            .iter()
+            .find(|(key, _)| !Self::is_well_formed_env_key(key))
+        {
+            panic!("Malformed environment variable name: '{}'", key)
+        }
         NodeStartupConfig {
             neighborhood_mode: self.neighborhood_mode,
             min_hops: self.min_hops,
@@ -663,13 +765,41 @@ impl NodeStartupConfigBuilder {
             memory_opt: self.memory,
             fake_public_key_opt: self.fake_public_key,
             blockchain_service_url_opt: self.blockchain_service_url,
+            gas_price_opt: self.gas_price_opt,
             chain: self.chain,
             db_password_opt: self.db_password,
             scans_opt: self.scans_opt,
             log_level_opt: self.log_level_opt,
             ui_port_opt: self.ui_port_opt,
+            data_directory_opt: self.data_directory_opt,
+            extra_env: self.extra_env,
+            extra_args: self.extra_args,
         }
     }
+
+    fn is_well_formed_url(url: &str) -> bool {
+        Regex::new(r"^https?://[^\s/:?#]+(:\d{1,5})?([/?#]\S*)?$")
+            .expect("Bad regex")
+            .is_match(url)
+    }
+
+    fn is_well_formed_address(address: &str) -> bool {
+        Regex::new(r"^0x[0-9a-fA-F]{40}$")
+            .expect("Bad regex")
+            .is_match(address)
+    }
+
+    fn is_well_formed_private_key(key: &str) -> bool {
+        Regex::new(r"^[0-9a-fA-F]{64}$")
+            .expect("Bad regex")
+            .is_match(key)
+    }
+
+    fn is_well_formed_env_key(key: &str) -> bool {
+        Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$")
+            .expect("Bad regex")
+            .is_match(key)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -774,6 +904,7 @@ impl MASQRealNode {
         startup_config: NodeStartupConfig,
         index: usize,
         host_node_parent_dir: Option<String>,
+        network: &DockerNetwork,
     ) -> Self {
         let name = Self::make_name(index);
         Self::start_with(
@@ -781,6 +912,24 @@ impl MASQRealNode {
             startup_config,
             index,
             host_node_parent_dir,
+            network,
+            Box::new(Self::do_docker_run),
+        )
+    }
+
+    pub fn start_named(
+        name: &str,
+        startup_config: NodeStartupConfig,
+        index: usize,
+        host_node_parent_dir: Option<String>,
+        network: &DockerNetwork,
+    ) -> Self {
+        Self::start_with(
+            name,
+            startup_config,
+            index,
+            host_node_parent_dir,
+            network,
             Box::new(Self::do_docker_run),
         )
     }
@@ -790,12 +939,14 @@ impl MASQRealNode {
         startup_config: NodeStartupConfig,
         index: usize,
         host_node_parent_dir: Option<String>,
+        network: &DockerNetwork,
     ) -> Self {
         Self::start_with(
             name,
             startup_config,
             index,
             host_node_parent_dir,
+            network,
             Box::new(Self::do_preprepared_docker_run),
         )
     }
@@ -805,9 +956,10 @@ impl MASQRealNode {
         startup_config: NodeStartupConfig,
         index: usize,
         host_node_parent_dir: Option<String>,
+        network: &DockerNetwork,
         docker_run_fn: RunDockerFn,
     ) -> Self {
-        let ip_addr = IpAddr::V4(Ipv4Addr::new(172, 18, 1, index as u8));
+        let ip_addr = network.node_ip_addr(index);
         MASQNodeUtils::clean_up_existing_container(name);
         let real_startup_config = match startup_config.ip_info {
             LocalIpInfo::ZeroHop => startup_config,
@@ -824,7 +976,7 @@ impl MASQRealNode {
             None => MASQNodeUtils::find_project_root(),
         };
 
-        docker_run_fn(&root_dir, ip_addr, name).expect("docker run");
+        docker_run_fn(&root_dir, ip_addr, name, network).expect("docker run");
 
         let ui_port = real_startup_config.ui_port_opt.unwrap_or(DEFAULT_UI_PORT);
         let ui_port_pair = format!("{}:{}", ui_port, ui_port);
@@ -889,7 +1041,9 @@ impl MASQRealNode {
         });
         let mut result = Self { guts };
         result.restart_node(restart_startup_config);
-        let node_reference = Self::extract_node_reference(name).expect("extracting node reference");
+        let node_reference =
+            Self::extract_node_reference(name, &real_startup_config.data_directory())
+                .expect("extracting node reference");
         Rc::get_mut(&mut result.guts).unwrap().node_reference = node_reference;
         result
     }
@@ -898,11 +1052,95 @@ impl MASQRealNode {
         self.guts.startup_config.clone()
     }
 
+    // The port the Node's UI gateway listens on, exposed on the same port of the container.
+    pub fn ui_port(&self) -> u16 {
+        self.guts
+            .startup_config
+            .ui_port_opt
+            .unwrap_or(DEFAULT_UI_PORT)
+    }
+
+    // To the cluster's crash watcher this looks like a crash; stop a Node on purpose with
+    // MASQNodeCluster::kill_node or MASQNodeCluster::graceful_shutdown_node instead.
     pub fn kill_node(&self) {
         let _ =
             Self::exec_command_on_container_and_wait(&self.guts.name, vec!["pkill", "MASQNode"]);
     }
 
+    pub fn graceful_shutdown(&self, timeout: Duration) -> Result<(), String> {
+        let ui_client = self.make_ui(self.ui_port());
+        ui_client.send_request(UiShutdownRequest {}.tmb(1));
+        let time_limit = Instant::now() + timeout;
+        while Instant::now() < time_limit {
+            // Polls too often to go through crate::command::Command, which echoes every invocation.
+            let output = std::process::Command::new("docker")
+                .args(["exec", self.name(), "pgrep", "MASQNode"])
+                .output()
+                .map_err(|e| format!("Couldn't run docker exec on {}: {}", self.name(), e))?;
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            match Self::pgrep_outcome(output.status.code(), &stderr) {
+                PgrepOutcome::Found => thread::sleep(Duration::from_millis(250)),
+                PgrepOutcome::NotFound => return Ok(()),
+                PgrepOutcome::ExecFailed(exec_error) => {
+                    return Self::check_container_exit(self.name(), &exec_error)
+                }
+            }
+        }
+        Err(format!(
+            "{} was still running {:?} after being asked to shut down",
+            self.name(),
+            timeout
+        ))
+    }
+
+    // pgrep exits with 1 and says nothing when it finds no match; docker exec also exits with 1
+    // when it can't get into the container, but then it explains why on stderr.
+    fn pgrep_outcome(exit_code: Option<i32>, stderr: &str) -> PgrepOutcome {
+        match exit_code {
+            Some(0) => PgrepOutcome::Found,
+            Some(1) if stderr.trim().is_empty() => PgrepOutcome::NotFound,
+            _ => PgrepOutcome::ExecFailed(stderr.trim().to_string()),
+        }
+    }
+
+    fn check_container_exit(name: &str, exec_error: &str) -> Result<(), String> {
+        let output = std::process::Command::new("docker")
+            .args([
+                "inspect",
+                "-f",
+                "{{.State.Running}} {{.State.ExitCode}}",
+                name,
+            ])
+            .output()
+            .map_err(|e| format!("Couldn't run docker inspect on {}: {}", name, e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "{} has no container left to shut down: {}",
+                name, exec_error
+            ));
+        }
+        Self::container_exit_verdict(name, exec_error, &String::from_utf8_lossy(&output.stdout))
+    }
+
+    // Expects docker inspect -f "{{.State.Running}} {{.State.ExitCode}}" output, like "false 0"
+    fn container_exit_verdict(
+        name: &str,
+        exec_error: &str,
+        inspect_output: &str,
+    ) -> Result<(), String> {
+        match inspect_output.trim().split_once(' ') {
+            Some(("false", "0")) => Ok(()),
+            Some(("false", exit_code)) => Err(format!(
+                "{}'s container exited with code {} during shutdown",
+                name, exit_code
+            )),
+            _ => Err(format!(
+                "Couldn't look for MASQNode in {}: {}",
+                name, exec_error
+            )),
+        }
+    }
+
     pub fn restart_node(&self, startup_config: NodeStartupConfig) {
         let node_args = startup_config.make_args();
         let node_command = Self::create_node_command(node_args, startup_config);
@@ -941,6 +1179,56 @@ impl MASQRealNode {
         }
     }
 
+    pub fn wait_for_log(&self, pattern: &str, timeout: Duration) -> Result<String, String> {
+        let regex =
+            Regex::new(pattern).map_err(|e| format!("Bad log pattern '{}': {}", pattern, e))?;
+        // The remote timeout makes sure tail doesn't outlive us inside the container
+        let remote_timeout_secs = timeout.as_secs() + 1;
+        let log_path = format!(
+            "{}/{}",
+            self.guts.startup_config.data_directory(),
+            CURRENT_LOGFILE_NAME
+        );
+        let mut command = Command::new(
+            "docker",
+            Command::strings(vec![
+                "exec",
+                self.name(),
+                "timeout",
+                &remote_timeout_secs.to_string(),
+                "tail",
+                "-n",
+                "+1",
+                "-F",
+                &log_path,
+            ]),
+        );
+        let lines = command.stream_lines();
+        let time_limit = Instant::now() + timeout;
+        loop {
+            let remaining = time_limit.saturating_duration_since(Instant::now());
+            match lines.recv_timeout(remaining) {
+                Ok(line) if regex.is_match(&line) => return Ok(line),
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(format!(
+                        "After {:?}, no line in the log of {} matched '{}'",
+                        timeout,
+                        self.name(),
+                        pattern
+                    ))
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(format!(
+                        "Log of {} stopped streaming before any line matched '{}'",
+                        self.name(),
+                        pattern
+                    ))
+                }
+            }
+        }
+    }
+
     pub fn make_client(&self, port: u16, timeout_millis: u64) -> MASQNodeClient {
         let socket_addr = SocketAddr::new(self.ip_address(), port);
         MASQNodeClient::new(socket_addr, timeout_millis)
@@ -968,20 +1256,30 @@ impl MASQRealNode {
 
     fn create_node_command(node_args: Vec<String>, startup_config: NodeStartupConfig) -> String {
         let mut node_command_parts: Vec<String> = match startup_config.memory_opt {
-            Some(kbytes) => vec![format!(
-                "ulimit -v {} -m {} && /node_root/node/MASQNode",
-                kbytes, kbytes
-            )],
-            None => vec!["/node_root/node/MASQNode".to_string()],
+            Some(kbytes) => vec![format!("ulimit -v {} -m {} &&", kbytes, kbytes)],
+            None => vec![],
         };
+        node_command_parts.extend(
+            startup_config
+                .extra_env
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, Self::shell_quote(value))),
+        );
+        node_command_parts.push("/node_root/node/MASQNode".to_string());
         node_command_parts.extend(node_args);
         node_command_parts.join(" ")
     }
 
+    // Inside single quotes the shell takes everything literally except another single quote
+    fn shell_quote(value: &str) -> String {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+
     fn do_docker_run(
         root_dir: &str,
         ip_addr: IpAddr,
         container_name_ref: &str,
+        network: &DockerNetwork,
     ) -> Result<(), String> {
         let container_name = container_name_ref.to_string();
         let node_command_dir = format!("{}/node/target/release", root_dir);
@@ -994,6 +1292,7 @@ impl MASQRealNode {
         let ip_addr_string = format!("{}", ip_addr);
         let node_binary_v_param = format!("{}:/node_root/node", node_command_dir);
         let home_v_param = format!("{}:{}", host_node_home_dir, DATA_DIRECTORY);
+        let run_label = network.run_label();
 
         let mut args = vec![
             "run",
@@ -1005,7 +1304,9 @@ impl MASQRealNode {
             "--name",
             container_name.as_str(),
             "--net",
-            "integration_net",
+            network.name.as_str(),
+            "--label",
+            run_label.as_str(),
             "-v",
             node_binary_v_param.as_str(),
             "-v",
@@ -1035,6 +1336,7 @@ impl MASQRealNode {
         root_dir: &str,
         ip_addr: IpAddr,
         container_name_ref: &str,
+        network: &DockerNetwork,
     ) -> Result<(), String> {
         let container_name = container_name_ref.to_string();
         let node_command_dir = format!("{}/node/target/release", root_dir);
@@ -1042,6 +1344,7 @@ impl MASQRealNode {
         let ip_addr_string = format!("{}", ip_addr);
         let node_binary_v_param = format!("{}:/node_root/node", node_command_dir);
         let home_v_param = format!("{}:{}", host_node_home_dir, DATA_DIRECTORY);
+        let run_label = network.run_label();
 
         let mut args = vec![
             "run",
@@ -1053,7 +1356,9 @@ impl MASQRealNode {
             "--name",
             container_name.as_str(),
             "--net",
-            "integration_net",
+            network.name.as_str(),
+            "--label",
+            run_label.as_str(),
             "-v",
             node_binary_v_param.as_str(),
             "-v",
@@ -1164,7 +1469,7 @@ impl MASQRealNode {
         Regex::new(r"MASQ Node local descriptor: (masq://.+:.+@[\d.]*:[\d,]*)").unwrap()
     }
 
-    fn extract_node_reference(name: &str) -> Result<NodeReference, String> {
+    fn extract_node_reference(name: &str, data_directory: &str) -> Result<NodeReference, String> {
         let descriptor_regex = Self::descriptor_regex();
         let mut retries_left = 25;
         loop {
@@ -1178,7 +1483,7 @@ impl MASQRealNode {
                 name,
                 vec![
                     "cat",
-                    &format!("{}/{}", DATA_DIRECTORY, CURRENT_LOGFILE_NAME),
+                    &format!("{}/{}", data_directory, CURRENT_LOGFILE_NAME),
                 ],
             ) {
                 Ok(output) => {
@@ -1197,7 +1502,7 @@ impl MASQRealNode {
                 Err(e) => {
                     println!(
                         "Failed to cat logfile for {} at {}/{}: {}",
-                        name, DATA_DIRECTORY, CURRENT_LOGFILE_NAME, e
+                        name, data_directory, CURRENT_LOGFILE_NAME, e
                     );
                 }
             };
@@ -1205,6 +1510,13 @@ impl MASQRealNode {
     }
 }
 
+#[derive(Debug, PartialEq)]
+enum PgrepOutcome {
+    Found,
+    NotFound,
+    ExecFailed(String),
+}
+
 #[derive(Debug, Clone)]
 struct CryptDENullPair {
     main: CryptDENull,
@@ -1227,7 +1539,7 @@ struct MASQRealNodeGuts {
     routes_data: bool,
 }
 
-type RunDockerFn = Box<dyn Fn(&str, IpAddr, &str) -> Result<(), String>>;
+type RunDockerFn = Box<dyn Fn(&str, IpAddr, &str, &DockerNetwork) -> Result<(), String>>;
 
 impl Drop for MASQRealNodeGuts {
     fn drop(&mut self) {
@@ -1255,6 +1567,44 @@ mod tests {
         assert_eq!(result.neighborhood_mode, "zero-hop".to_string());
     }
 
+    #[test]
+    fn pgrep_outcome_tells_a_missing_process_from_a_failed_exec() {
+        assert_eq!(
+            MASQRealNode::pgrep_outcome(Some(0), ""),
+            PgrepOutcome::Found
+        );
+        assert_eq!(
+            MASQRealNode::pgrep_outcome(Some(1), ""),
+            PgrepOutcome::NotFound
+        );
+        assert_eq!(
+            MASQRealNode::pgrep_outcome(Some(1), "Error: No such container: test_node_1\n"),
+            PgrepOutcome::ExecFailed("Error: No such container: test_node_1".to_string())
+        );
+        assert_eq!(
+            MASQRealNode::pgrep_outcome(None, ""),
+            PgrepOutcome::ExecFailed(String::new())
+        );
+    }
+
+    #[test]
+    fn container_exit_verdict_accepts_only_a_clean_exit() {
+        let exec_error = "container is not running";
+
+        assert_eq!(
+            MASQRealNode::container_exit_verdict("test_node_1", exec_error, "false 0\n"),
+            Ok(())
+        );
+        assert_eq!(
+            MASQRealNode::container_exit_verdict("test_node_1", exec_error, "false 137\n"),
+            Err("test_node_1's container exited with code 137 during shutdown".to_string())
+        );
+        assert_eq!(
+            MASQRealNode::container_exit_verdict("test_node_1", exec_error, "true 0\n"),
+            Err("Couldn't look for MASQNode in test_node_1: container is not running".to_string())
+        );
+    }
+
     #[test]
     fn node_max_memory_can_be_configured() {
         let memory = "50mb";
@@ -1289,6 +1639,34 @@ mod tests {
         assert_eq!(result.neighborhood_mode, "originate-only".to_string());
     }
 
+    #[test]
+    fn neighborhood_mode_light_selects_each_mode() {
+        vec![
+            (NeighborhoodModeLight::Standard, "standard"),
+            (NeighborhoodModeLight::ConsumeOnly, "consume-only"),
+            (NeighborhoodModeLight::OriginateOnly, "originate-only"),
+            (NeighborhoodModeLight::ZeroHop, "zero-hop"),
+        ]
+        .into_iter()
+        .for_each(|(mode, expected)| {
+            let result = NodeStartupConfigBuilder::standard()
+                .neighborhood_mode_light(mode)
+                .build();
+
+            assert_eq!(result.neighborhood_mode, expected.to_string());
+            assert_eq!(
+                result.make_args()[0..2],
+                Command::strings(vec!["--neighborhood-mode", expected])[..]
+            );
+        })
+    }
+
+    #[test]
+    #[should_panic(expected = "Unrecognized --neighborhood-mode: 'booga'")]
+    fn neighborhood_mode_rejects_unknown_modes() {
+        let _ = NodeStartupConfigBuilder::standard().neighborhood_mode("booga");
+    }
+
     #[test]
     fn node_startup_config_builder_consume_only() {
         let result = NodeStartupConfigBuilder::consume_only().build();
@@ -1351,6 +1729,42 @@ mod tests {
         assert_eq!(result.dns_port, 35);
     }
 
+    #[test]
+    fn extend_neighbors_appends_to_existing_neighbors() {
+        let make_neighbor = |n: u8| {
+            NodeReference::new(
+                PublicKey::new(&[n]),
+                Some(IpAddr::V4(Ipv4Addr::new(1, 2, 3, n))),
+                vec![1000 + n as u16],
+                TEST_DEFAULT_MULTINODE_CHAIN,
+            )
+        };
+
+        let result = NodeStartupConfigBuilder::standard()
+            .neighbor(make_neighbor(1))
+            .extend_neighbors(vec![make_neighbor(2), make_neighbor(3)])
+            .extend_neighbors(vec![make_neighbor(4)])
+            .build();
+
+        let expected_neighbors = vec![
+            make_neighbor(1),
+            make_neighbor(2),
+            make_neighbor(3),
+            make_neighbor(4),
+        ];
+        assert_eq!(result.neighbors, expected_neighbors);
+        let args = result.make_args();
+        let neighbors_index = args.iter().position(|arg| arg == "--neighbors").unwrap();
+        assert_eq!(
+            args[neighbors_index + 1],
+            expected_neighbors
+                .iter()
+                .map(|neighbor| neighbor.to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        );
+    }
+
     #[test]
     fn node_startup_config_builder_copy() {
         let original = NodeStartupConfig {
@@ -1389,11 +1803,15 @@ mod tests {
             memory_opt: Some("32m".to_string()),
             fake_public_key_opt: Some(PublicKey::new(&[1, 2, 3, 4])),
             blockchain_service_url_opt: None,
+            gas_price_opt: None,
             chain: TEST_DEFAULT_MULTINODE_CHAIN,
             db_password_opt: Some("booga".to_string()),
             scans_opt: Some(false),
             log_level_opt: Some(Level::Info),
             ui_port_opt: Some(4321),
+            data_directory_opt: Some(PathBuf::from("/node_root/booga")),
+            extra_env: vec![("RUST_BACKTRACE".to_string(), "1".to_string())],
+            extra_args: vec!["--new-mode".to_string()],
         };
         let neighborhood_mode = "standard".to_string();
         let ip_addr = IpAddr::from_str("1.2.3.4").unwrap();
@@ -1457,6 +1875,15 @@ mod tests {
         assert_eq!(result.scans_opt, Some(false));
         assert_eq!(result.log_level_opt, Some(Level::Info));
         assert_eq!(result.ui_port_opt, Some(4321));
+        assert_eq!(
+            result.data_directory_opt,
+            Some(PathBuf::from("/node_root/booga"))
+        );
+        assert_eq!(
+            result.extra_env,
+            vec![("RUST_BACKTRACE".to_string(), "1".to_string())]
+        );
+        assert_eq!(result.extra_args, vec!["--new-mode".to_string()]);
         assert_eq!(
             result.payment_thresholds,
             PaymentThresholds {
@@ -1541,6 +1968,215 @@ mod tests {
         );
     }
 
+    #[test]
+    fn extra_args_follow_the_structured_ones() {
+        let subject = NodeStartupConfigBuilder::standard()
+            .ui_port(4321)
+            .arg("--new-option")
+            .arg("value")
+            .build();
+
+        let result = subject.make_args();
+
+        assert_eq!(
+            result[result.len() - 4..].to_vec(),
+            Command::strings(vec!["--ui-port", "4321", "--new-option", "value"])
+        );
+    }
+
+    #[test]
+    fn node_command_sets_extra_environment_variables() {
+        let plain_config = NodeStartupConfigBuilder::standard()
+            .env("RUST_BACKTRACE", "1")
+            .env("FEATURE", "on")
+            .build();
+        let limited_config = NodeStartupConfigBuilder::standard()
+            .memory("32m")
+            .env("RUST_BACKTRACE", "1")
+            .build();
+        let args = Command::strings(vec!["--chain", "dev"]);
+
+        let plain_command = MASQRealNode::create_node_command(args.clone(), plain_config);
+        let limited_command = MASQRealNode::create_node_command(args, limited_config);
+
+        assert_eq!(
+            plain_command,
+            "RUST_BACKTRACE='1' FEATURE='on' /node_root/node/MASQNode --chain dev"
+        );
+        assert_eq!(
+            limited_command,
+            "ulimit -v 32m -m 32m && RUST_BACKTRACE='1' /node_root/node/MASQNode --chain dev"
+        );
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn node_command_keeps_environment_values_away_from_the_shell() {
+        let tricky_value = "a b; echo 'gotcha'";
+        let config = NodeStartupConfigBuilder::standard()
+            .env("TRICKY", tricky_value)
+            .build();
+        let node_command = MASQRealNode::create_node_command(vec![], config);
+        let shell_command = node_command.replace("/node_root/node/MASQNode", "printenv TRICKY");
+
+        let output = std::process::Command::new("sh")
+            .args(["-c", &shell_command])
+            .output()
+            .unwrap();
+
+        assert_eq!(
+            node_command,
+            r"TRICKY='a b; echo '\''gotcha'\''' /node_root/node/MASQNode"
+        );
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            format!("{}\n", tricky_value)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Malformed environment variable name: 'BAD KEY;'")]
+    fn build_rejects_malformed_environment_variable_name() {
+        NodeStartupConfigBuilder::standard()
+            .env("BAD KEY;", "value")
+            .build();
+    }
+
+    #[test]
+    fn make_args_passes_a_single_log_level() {
+        let default_subject = NodeStartupConfigBuilder::standard().build();
+        let debug_subject = NodeStartupConfigBuilder::standard()
+            .log_level(Level::Debug)
+            .build();
+
+        let default_args = default_subject.make_args();
+        let debug_args = debug_subject.make_args();
+
+        let log_levels = |args: &[String]| {
+            args.iter()
+                .enumerate()
+                .filter(|(_, arg)| arg.as_str() == "--log-level")
+                .map(|(index, _)| args[index + 1].clone())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(log_levels(&default_args), vec!["trace".to_string()]);
+        assert_eq!(log_levels(&debug_args), vec!["debug".to_string()]);
+    }
+
+    #[test]
+    fn gas_price_and_blockchain_service_url_become_node_arguments() {
+        let subject = NodeStartupConfigBuilder::standard()
+            .blockchain_service_url("http://172.18.0.20:18545".to_string())
+            .gas_price(123)
+            .build();
+
+        let result = subject.make_args();
+
+        let blockchain_index = result
+            .iter()
+            .position(|arg| arg == "--blockchain-service-url")
+            .unwrap();
+        assert_eq!(result[blockchain_index + 1], "http://172.18.0.20:18545");
+        let gas_price_index = result.iter().position(|arg| arg == "--gas-price").unwrap();
+        assert_eq!(result[gas_price_index + 1], "123");
+    }
+
+    #[test]
+    fn gas_price_is_omitted_unless_specified() {
+        let subject = NodeStartupConfigBuilder::standard().build();
+
+        let result = subject.make_args();
+
+        assert!(!result.contains(&"--gas-price".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Malformed blockchain service URL: 'booga:8545'")]
+    fn build_rejects_malformed_blockchain_service_url() {
+        NodeStartupConfigBuilder::standard()
+            .blockchain_service_url("booga:8545".to_string())
+            .build();
+    }
+
+    #[test]
+    fn wallets_are_passed_as_their_cli_arguments() {
+        let subject = NodeStartupConfigBuilder::standard()
+            .earning_wallet("0x0123456789abcdef0123456789ABCDEF01234567")
+            .consuming_wallet("0123456789ABCDEF0123456789ABCDEF0123456789ABCDEF0123456789abcdef")
+            .build();
+
+        let result = subject.make_args();
+
+        let earning_index = result
+            .iter()
+            .position(|arg| arg == "--earning-wallet")
+            .unwrap();
+        assert_eq!(
+            result[earning_index + 1],
+            "0x0123456789abcdef0123456789ABCDEF01234567"
+        );
+        let consuming_index = result
+            .iter()
+            .position(|arg| arg == "--consuming-private-key")
+            .unwrap();
+        assert_eq!(
+            result[consuming_index + 1],
+            "0123456789ABCDEF0123456789ABCDEF0123456789ABCDEF0123456789abcdef"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Malformed earning wallet address: '0x0123456789abcdef'")]
+    fn build_rejects_malformed_earning_wallet_address() {
+        NodeStartupConfigBuilder::standard()
+            .earning_wallet("0x0123456789abcdef")
+            .build();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Malformed consuming wallet private key: expected 64 hex digits, found 63 characters"
+    )]
+    fn build_rejects_malformed_consuming_wallet_private_key() {
+        NodeStartupConfigBuilder::standard()
+            .consuming_wallet("0123456789ABCDEF0123456789ABCDEF0123456789ABCDEF0123456789abcde")
+            .build();
+    }
+
+    #[test]
+    fn data_directory_and_chain_name_are_passed_as_their_cli_arguments() {
+        let subject = NodeStartupConfigBuilder::standard()
+            .data_directory(PathBuf::from("/node_root/seeded"))
+            .chain_name("eth-ropsten")
+            .build();
+
+        let result = subject.make_args();
+
+        let data_directory_index = result
+            .iter()
+            .position(|arg| arg == "--data-directory")
+            .unwrap();
+        assert_eq!(result[data_directory_index + 1], "/node_root/seeded");
+        let chain_index = result.iter().position(|arg| arg == "--chain").unwrap();
+        assert_eq!(result[chain_index + 1], "eth-ropsten");
+        assert_eq!(subject.chain, Chain::EthRopsten);
+    }
+
+    #[test]
+    fn data_directory_defaults_to_the_mounted_home() {
+        let subject = NodeStartupConfigBuilder::standard().build();
+
+        let result = subject.data_directory();
+
+        assert_eq!(result, DATA_DIRECTORY);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unrecognized --chain: 'eth-ropstem'")]
+    fn chain_name_rejects_unknown_chains() {
+        NodeStartupConfigBuilder::standard().chain_name("eth-ropstem");
+    }
+
     #[test]
     fn regex_captures_descriptor() {
         let text = "scajcbakbcskjbcbackjbb MASQ Node local descriptor: masq://dev:BrrLUksswnE8GOQQMpwcAjk2hOX4HEmaTcBloBpPuE0@: jajca[cjscpajpojsc";
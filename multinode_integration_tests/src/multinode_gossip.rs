@@ -3,13 +3,18 @@ use crate::masq_node::MASQNode;
 use masq_lib::blockchains::chains::Chain;
 use masq_lib::test_utils::utils::TEST_DEFAULT_MULTINODE_CHAIN;
 use node_lib::neighborhood::gossip::{GossipNodeRecord, Gossip_0v1};
+use node_lib::neighborhood::node_record::{NodeRecord, NodeRecordInner_0v1};
 use node_lib::neighborhood::AccessibleGossipRecord;
-use node_lib::sub_lib::cryptde::PublicKey;
+use node_lib::sub_lib::cryptde::{CryptData, PlainData, PublicKey};
 use node_lib::sub_lib::cryptde_null::CryptDENull;
+use node_lib::sub_lib::neighborhood::DEFAULT_RATE_PACK;
+use node_lib::sub_lib::node_addr::NodeAddr;
 use node_lib::test_utils::vec_to_set;
-use std::collections::HashSet;
+use serde_json::json;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::convert::{TryFrom, TryInto};
 use std::net::IpAddr;
+use std::str::FromStr;
 
 #[derive(PartialEq, Eq, Clone, Debug)]
 #[allow(clippy::large_enum_variant)]
@@ -21,36 +26,52 @@ pub enum GossipType {
     Unrecognized,
 }
 
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum GossipParseError {
+    UndecodableRecord { index: usize, detail: String },
+    MissingNodeAddr { index: usize },
+}
+
 /// Note: this function has no access to the receiver database so as to determine whether the
 /// "introducee" of an Introduction pair is already there or not; therefore, it may misidentify
 /// two-Node Standard Gossip as an Introduction. If you know the Gossip you're getting is Standard
 /// Gossip, even though it has two Nodes, use Standard::from(gossip.try_into().unwrap()) to wrap it.
-pub fn parse_gossip(gossip: &Gossip_0v1, sender: IpAddr) -> GossipType {
+pub fn parse_gossip(gossip: &Gossip_0v1, sender: IpAddr) -> Result<GossipType, GossipParseError> {
     let agrs = gossip
         .node_records
         .iter()
-        .map(|gnr| AccessibleGossipRecord::try_from(gnr.clone()).unwrap())
-        .collect::<Vec<AccessibleGossipRecord>>();
-    match agrs.len() {
+        .enumerate()
+        .map(|(index, gnr)| {
+            AccessibleGossipRecord::try_from(gnr.clone())
+                .map_err(|detail| GossipParseError::UndecodableRecord { index, detail })
+        })
+        .collect::<Result<Vec<AccessibleGossipRecord>, GossipParseError>>()?;
+    let is_sender = |index: usize| -> Result<bool, GossipParseError> {
+        match agrs[index].node_addr_opt.as_ref() {
+            Some(node_addr) => Ok(node_addr.ip_addr() == sender),
+            None => Err(GossipParseError::MissingNodeAddr { index }),
+        }
+    };
+    Ok(match agrs.len() {
         0 => GossipType::Unrecognized,
         1 => {
-            if agrs[0].node_addr_opt.as_ref().unwrap().ip_addr() == sender {
+            if is_sender(0)? {
                 GossipType::DebutGossip(SingleNode::from(&agrs[0]))
             } else {
                 GossipType::PassGossip(SingleNode::from(&agrs[0]))
             }
         }
         2 => {
-            if agrs[0].node_addr_opt.as_ref().unwrap().ip_addr() == sender {
+            if is_sender(0)? {
                 GossipType::IntroductionGossip(Introduction::from((&agrs[0], &agrs[1])))
-            } else if agrs[1].node_addr_opt.as_ref().unwrap().ip_addr() == sender {
+            } else if is_sender(1)? {
                 GossipType::IntroductionGossip(Introduction::from((&agrs[1], &agrs[0])))
             } else {
                 GossipType::Unrecognized
             }
         }
         _ => GossipType::StandardGossip(Standard::from(&agrs)),
-    }
+    })
 }
 
 pub trait MultinodeGossip {
@@ -239,6 +260,14 @@ impl Introduction {
     pub fn introducee_key(&self) -> &PublicKey {
         &self.introducee_agr().inner.public_key
     }
+
+    pub fn introducer_addr(&self) -> Option<&NodeAddr> {
+        self.introducer_agr().node_addr_opt.as_ref()
+    }
+
+    pub fn introducee_addr(&self) -> Option<&NodeAddr> {
+        self.introducee_agr().node_addr_opt.as_ref()
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Debug)]
@@ -340,6 +369,31 @@ impl StandardBuilder {
         self.add_agr(&agr)
     }
 
+    // A Node without a NodeAddr can't be connected to directly, so it doesn't accept connections
+    pub fn add_node(
+        self,
+        key: PublicKey,
+        addr_opt: Option<NodeAddr>,
+        neighbors: Vec<PublicKey>,
+    ) -> StandardBuilder {
+        let mut agr = AccessibleGossipRecord {
+            inner: NodeRecordInner_0v1 {
+                earning_wallet: NodeRecord::earning_wallet_from_key(&key),
+                public_key: key,
+                rate_pack: DEFAULT_RATE_PACK,
+                neighbors: neighbors.into_iter().collect(),
+                accepts_connections: addr_opt.is_some(),
+                routes_data: true,
+                version: 0,
+            },
+            node_addr_opt: addr_opt,
+            signed_gossip: PlainData::new(b""),
+            signature: CryptData::new(b""),
+        };
+        agr.regenerate_signed_gossip(&CryptDENull::from(&agr.inner.public_key, self.chain));
+        self.add_agr(&agr)
+    }
+
     pub fn add_agr(mut self, agr: &AccessibleGossipRecord) -> StandardBuilder {
         self.agrs.push(agr.clone());
         self
@@ -383,6 +437,125 @@ impl StandardBuilder {
     }
 }
 
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct NeighborChange {
+    pub added: BTreeSet<PublicKey>,
+    pub removed: BTreeSet<PublicKey>,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct GossipDiff {
+    pub added_nodes: HashSet<PublicKey>,
+    pub removed_nodes: HashSet<PublicKey>,
+    // Only Nodes present in both Gossips appear here; see added_nodes for the others' neighbors
+    pub changed_edges: HashMap<PublicKey, NeighborChange>,
+}
+
+pub fn diff(before: &Gossip_0v1, after: &Gossip_0v1) -> GossipDiff {
+    let neighbors_by_key = |gossip: &Gossip_0v1| {
+        let agrs: Vec<AccessibleGossipRecord> = gossip.clone().try_into().unwrap();
+        agrs.into_iter()
+            .map(|agr| (agr.inner.public_key, agr.inner.neighbors))
+            .collect::<HashMap<PublicKey, BTreeSet<PublicKey>>>()
+    };
+    let before_nodes = neighbors_by_key(before);
+    let after_nodes = neighbors_by_key(after);
+    let keys_only_in = |these: &HashMap<PublicKey, BTreeSet<PublicKey>>,
+                        those: &HashMap<PublicKey, BTreeSet<PublicKey>>| {
+        these
+            .keys()
+            .filter(|key| !those.contains_key(key))
+            .cloned()
+            .collect::<HashSet<PublicKey>>()
+    };
+    let changed_edges = before_nodes
+        .iter()
+        .filter_map(|(key, before_neighbors)| {
+            let after_neighbors = after_nodes.get(key)?;
+            if after_neighbors == before_neighbors {
+                return None;
+            }
+            Some((
+                key.clone(),
+                NeighborChange {
+                    added: after_neighbors
+                        .difference(before_neighbors)
+                        .cloned()
+                        .collect(),
+                    removed: before_neighbors
+                        .difference(after_neighbors)
+                        .cloned()
+                        .collect(),
+                },
+            ))
+        })
+        .collect();
+    GossipDiff {
+        added_nodes: keys_only_in(&after_nodes, &before_nodes),
+        removed_nodes: keys_only_in(&before_nodes, &after_nodes),
+        changed_edges,
+    }
+}
+
+// Only the topology is captured: base64 keys, NodeAddrs and neighbor keys, with Nodes sorted by
+// key so that equal topologies always render as equal JSON.
+pub fn to_json(gossip: &Gossip_0v1) -> serde_json::Value {
+    let mut agrs: Vec<AccessibleGossipRecord> = gossip.clone().try_into().unwrap();
+    agrs.sort_by(|a, b| a.inner.public_key.cmp(&b.inner.public_key));
+    let nodes = agrs
+        .into_iter()
+        .map(|agr| {
+            json!({
+                "key": agr.inner.public_key.to_string(),
+                "node_addr": agr.node_addr_opt.map(|node_addr| node_addr.to_string()),
+                "neighbors": agr
+                    .inner
+                    .neighbors
+                    .iter()
+                    .map(|key| key.to_string())
+                    .collect::<Vec<String>>(),
+            })
+        })
+        .collect::<Vec<serde_json::Value>>();
+    json!({ "nodes": nodes })
+}
+
+// Whatever to_json() doesn't capture is filled in as StandardBuilder::add_node() would fill it.
+pub fn from_json(json: &serde_json::Value) -> Result<Standard, String> {
+    let nodes = json["nodes"]
+        .as_array()
+        .ok_or_else(|| format!("Gossip JSON should have a 'nodes' array: {}", json))?;
+    nodes
+        .iter()
+        .try_fold(StandardBuilder::new(), |builder, node| {
+            let key = key_from_json(&node["key"])?;
+            let addr_opt = match &node["node_addr"] {
+                serde_json::Value::Null => None,
+                serde_json::Value::String(node_addr) => Some(NodeAddr::from_str(node_addr)?),
+                other => return Err(format!("Malformed node_addr in Gossip JSON: {}", other)),
+            };
+            let neighbors = node["neighbors"]
+                .as_array()
+                .ok_or_else(|| {
+                    format!("Gossip JSON node should have a 'neighbors' array: {}", node)
+                })?
+                .iter()
+                .map(key_from_json)
+                .collect::<Result<Vec<PublicKey>, String>>()?;
+            Ok(builder.add_node(key, addr_opt, neighbors))
+        })
+        .map(StandardBuilder::build)
+}
+
+fn key_from_json(value: &serde_json::Value) -> Result<PublicKey, String> {
+    let encoded = value
+        .as_str()
+        .ok_or_else(|| format!("Malformed public key in Gossip JSON: {}", value))?;
+    base64::decode_config(encoded, base64::STANDARD_NO_PAD)
+        .map(|data| PublicKey::new(&data))
+        .map_err(|e| format!("Malformed public key '{}' in Gossip JSON: {:?}", encoded, e))
+}
+
 fn nodes_of_degree(nodes: &[AccessibleGossipRecord], degree: usize) -> Vec<PublicKey> {
     nodes
         .iter()
@@ -390,3 +563,259 @@ fn nodes_of_degree(nodes: &[AccessibleGossipRecord], degree: usize) -> Vec<Publi
         .map(|node| node.inner.public_key.clone())
         .collect::<Vec<PublicKey>>()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use node_lib::test_utils::neighborhood_test_utils::make_node_record;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn parse_gossip_recognizes_debut_and_pass() {
+        let node_record = make_node_record(1234, true);
+        let node_ip = node_record.node_addr_opt().unwrap().ip_addr();
+        let gossip = Gossip_0v1::new(vec![GossipNodeRecord::from(node_record)]);
+
+        let debut = parse_gossip(&gossip, node_ip);
+        let pass = parse_gossip(&gossip, IpAddr::V4(Ipv4Addr::new(4, 3, 2, 1)));
+
+        match debut {
+            Ok(GossipType::DebutGossip(_)) => (),
+            x => panic!("Expected DebutGossip, but found {:?}", x),
+        }
+        match pass {
+            Ok(GossipType::PassGossip(_)) => (),
+            x => panic!("Expected PassGossip, but found {:?}", x),
+        }
+    }
+
+    #[test]
+    fn introduction_exposes_introducer_and_introducee() {
+        let introducer = make_node_record(1234, true);
+        let introducee = make_node_record(2345, true);
+        let introducer_addr = introducer.node_addr_opt().unwrap();
+        let introducee_addr = introducee.node_addr_opt().unwrap();
+        let introducer_key = introducer.public_key().clone();
+        let introducee_key = introducee.public_key().clone();
+        let gossip = Gossip_0v1::new(vec![
+            GossipNodeRecord::from(introducee),
+            GossipNodeRecord::from(introducer),
+        ]);
+
+        let result = parse_gossip(&gossip, introducer_addr.ip_addr());
+
+        let introduction = match result {
+            Ok(GossipType::IntroductionGossip(introduction)) => introduction,
+            x => panic!("Expected IntroductionGossip, but found {:?}", x),
+        };
+        assert_eq!(introduction.introducer_key(), &introducer_key);
+        assert_eq!(introduction.introducee_key(), &introducee_key);
+        assert_eq!(introduction.introducer_addr(), Some(&introducer_addr));
+        assert_eq!(introduction.introducee_addr(), Some(&introducee_addr));
+    }
+
+    #[test]
+    fn parse_gossip_reports_undecodable_record() {
+        let gossip = Gossip_0v1::new(vec![
+            GossipNodeRecord::from(make_node_record(1234, true)),
+            GossipNodeRecord {
+                signed_data: PlainData::new(b"booga"),
+                signature: CryptData::new(b"signature"),
+                node_addr_opt: None,
+            },
+        ]);
+
+        let result = parse_gossip(&gossip, IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)));
+
+        match result {
+            Err(GossipParseError::UndecodableRecord { index: 1, .. }) => (),
+            x => panic!("Expected UndecodableRecord at index 1, but found {:?}", x),
+        }
+    }
+
+    #[test]
+    fn parse_gossip_reports_missing_node_addr() {
+        let gossip = Gossip_0v1::new(vec![GossipNodeRecord::from(make_node_record(1234, false))]);
+
+        let result = parse_gossip(&gossip, IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)));
+
+        assert_eq!(result, Err(GossipParseError::MissingNodeAddr { index: 0 }));
+    }
+
+    fn make_agr(n: u16) -> AccessibleGossipRecord {
+        AccessibleGossipRecord::try_from(GossipNodeRecord::from(make_node_record(n, true))).unwrap()
+    }
+
+    #[test]
+    fn diff_reports_added_and_removed_nodes_and_changed_edges() {
+        let a = make_agr(1234);
+        let b = make_agr(2345);
+        let c = make_agr(3456);
+        let (a_key, b_key, c_key) = (
+            a.inner.public_key.clone(),
+            b.inner.public_key.clone(),
+            c.inner.public_key.clone(),
+        );
+        let before = StandardBuilder::new()
+            .add_agr(&a)
+            .add_agr(&b)
+            .full_neighbors(&a_key, &b_key)
+            .build()
+            .render();
+        let after = StandardBuilder::new()
+            .add_agr(&a)
+            .add_agr(&c)
+            .full_neighbors(&a_key, &c_key)
+            .build()
+            .render();
+
+        let result = diff(&before, &after);
+
+        assert_eq!(result.added_nodes, vec_to_set(vec![c_key.clone()]));
+        assert_eq!(result.removed_nodes, vec_to_set(vec![b_key.clone()]));
+        let mut expected_changed_edges = HashMap::new();
+        expected_changed_edges.insert(
+            a_key,
+            NeighborChange {
+                added: vec![c_key].into_iter().collect(),
+                removed: vec![b_key].into_iter().collect(),
+            },
+        );
+        assert_eq!(result.changed_edges, expected_changed_edges);
+    }
+
+    #[test]
+    fn to_json_captures_keys_addrs_and_edges() {
+        let reachable_key = PublicKey::new(&[1, 2, 3, 4]);
+        let natted_key = PublicKey::new(&[5, 6, 7, 8]);
+        let gossip = StandardBuilder::new()
+            .add_node(natted_key.clone(), None, vec![reachable_key.clone()])
+            .add_node(
+                reachable_key.clone(),
+                Some(NodeAddr::new(
+                    &IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)),
+                    &[1234, 2345],
+                )),
+                vec![natted_key.clone()],
+            )
+            .build()
+            .render();
+
+        let result = to_json(&gossip);
+
+        assert_eq!(
+            result,
+            json!({
+                "nodes": [
+                    {
+                        "key": "AQIDBA",
+                        "node_addr": "1.2.3.4:1234/2345",
+                        "neighbors": ["BQYHCA"],
+                    },
+                    {
+                        "key": "BQYHCA",
+                        "node_addr": null,
+                        "neighbors": ["AQIDBA"],
+                    },
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn from_json_rebuilds_the_topology_to_json_captured() {
+        let a = make_agr(1234);
+        let b = make_agr(2345);
+        let c = make_agr(3456);
+        let (a_key, b_key, c_key) = (
+            a.inner.public_key.clone(),
+            b.inner.public_key.clone(),
+            c.inner.public_key.clone(),
+        );
+        let gossip = StandardBuilder::new()
+            .add_agr(&a)
+            .add_agr(&b)
+            .add_agr(&c)
+            .full_neighbors(&a_key, &b_key)
+            .half_neighbors(&b_key, &c_key)
+            .build()
+            .render();
+        let json = to_json(&gossip);
+
+        let result = from_json(&json).unwrap();
+
+        assert_eq!(to_json(&result.render()), json);
+        assert_eq!(diff(&gossip, &result.render()), GossipDiff::default());
+    }
+
+    #[test]
+    fn from_json_complains_about_a_malformed_key() {
+        let json = json!({
+            "nodes": [{"key": "!!!", "node_addr": null, "neighbors": []}]
+        });
+
+        let result = from_json(&json);
+
+        assert!(
+            result
+                .as_ref()
+                .err()
+                .map(|msg| msg.starts_with("Malformed public key '!!!' in Gossip JSON"))
+                .unwrap_or(false),
+            "{:?}",
+            result.err()
+        );
+    }
+
+    #[test]
+    fn diff_of_identical_gossip_is_empty() {
+        let gossip = StandardBuilder::new()
+            .add_agr(&make_agr(1234))
+            .add_agr(&make_agr(2345))
+            .build()
+            .render();
+
+        let result = diff(&gossip, &gossip);
+
+        assert_eq!(result, GossipDiff::default());
+    }
+
+    #[test]
+    fn add_node_builds_a_hand_crafted_topology_that_survives_serialization() {
+        let reachable_key = PublicKey::new(&[1, 2, 3, 4]);
+        let reachable_addr = NodeAddr::new(&IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), &[1234]);
+        let natted_key = PublicKey::new(&[5, 6, 7, 8]);
+        let standard = StandardBuilder::new()
+            .add_node(
+                reachable_key.clone(),
+                Some(reachable_addr.clone()),
+                vec![natted_key.clone()],
+            )
+            .add_node(natted_key.clone(), None, vec![reachable_key.clone()])
+            .build();
+        let serialized = serde_cbor::ser::to_vec(&standard.render()).unwrap();
+
+        let deserialized: Gossip_0v1 = serde_cbor::de::from_slice(&serialized).unwrap();
+
+        let agrs: Vec<AccessibleGossipRecord> = deserialized.try_into().unwrap();
+        let result = Standard::from(&agrs);
+        let reachable = result.agr(&reachable_key).unwrap();
+        assert_eq!(reachable.node_addr_opt, Some(reachable_addr));
+        assert!(reachable.inner.accepts_connections);
+        assert_eq!(
+            reachable.inner.neighbors,
+            vec![natted_key.clone()]
+                .into_iter()
+                .collect::<BTreeSet<PublicKey>>()
+        );
+        let natted = result.agr(&natted_key).unwrap();
+        assert_eq!(natted.node_addr_opt, None);
+        assert!(!natted.inner.accepts_connections);
+        assert_eq!(
+            natted.inner.neighbors,
+            vec![reachable_key]
+                .into_iter()
+                .collect::<BTreeSet<PublicKey>>()
+        );
+    }
+}
@@ -126,7 +126,7 @@ fn debtors_are_credited_once_but_not_twice() {
     let (_, context_id) = UiScanResponse::fmb(response).unwrap();
     assert_eq!(context_id, 1235);
     // Kill the real Node
-    node.kill_node();
+    cluster.kill_node(&node_name);
     // Use the receivable DAO to verify that the receivable's balance has been adjusted
     {
         let receivable_dao = receivable_dao(&node_name);
@@ -239,7 +239,7 @@ fn downed_nodes_not_offered_in_passes_or_introductions() {
     let (gossip, ip_addr) = debuter_node
         .wait_for_gossip(Duration::from_secs(2))
         .unwrap();
-    match parse_gossip(&gossip, ip_addr) {
+    match parse_gossip(&gossip, ip_addr).expect("Gossip could not be parsed") {
         GossipType::IntroductionGossip(introduction) => {
             // It's an Introduction of the one that didn't go down!
             assert_eq!(
@@ -44,7 +44,7 @@ fn graph_connects_but_does_not_over_connect() {
 
     mock_node.transmit_debut(last_node).unwrap();
     let (gossip, sender) = mock_node.wait_for_gossip(Duration::from_secs(2)).unwrap();
-    match parse_gossip(&gossip, sender) {
+    match parse_gossip(&gossip, sender).expect("Gossip could not be parsed") {
         GossipType::IntroductionGossip(_) => (),
         _ => panic!("Received unexpected Gossip when expecting Introduction"),
     }
@@ -56,7 +56,7 @@ fn graph_connects_but_does_not_over_connect() {
         .transmit_multinode_gossip(last_node, &standard_gossip)
         .unwrap();
     let (gossip, sender) = mock_node.wait_for_gossip(Duration::from_secs(2)).unwrap();
-    let standard_gossip = match parse_gossip(&gossip, sender) {
+    let standard_gossip = match parse_gossip(&gossip, sender).expect("Gossip could not be parsed") {
         GossipType::StandardGossip(standard_gossip) => standard_gossip,
         _ => panic!("Received unexpected Gossip when expecting Standard Gossip"),
     };
@@ -98,7 +98,7 @@ fn lots_of_stalled_nodes_dont_prevent_acceptance_of_new_node() {
     let (gossip, sender) = new_node
         .wait_for_gossip(Duration::from_millis(1000))
         .unwrap();
-    match parse_gossip(&gossip, sender) {
+    match parse_gossip(&gossip, sender).expect("Gossip could not be parsed") {
         GossipType::IntroductionGossip(introduction) => {
             assert_eq!(introduction.introducer_key(), root_node.main_public_key());
             assert_eq!(introduction.introducee_key(), full_neighbor_key);
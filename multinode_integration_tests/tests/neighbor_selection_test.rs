@@ -136,13 +136,13 @@ fn node_remembers_its_neighbors_across_a_bounce() {
     let (_, originating_node, _) = construct_neighborhood(&mut cluster, dest_db, vec![]);
     let relay1 = cluster.get_mock_node_by_name("mock_node_2").unwrap();
 
-    originating_node.kill_node();
+    cluster.kill_node(originating_node.name());
 
     let mut config = originating_node.get_startup_config();
     config.neighbors = vec![];
-    originating_node.restart_node(config);
+    cluster.restart_node(originating_node.name(), config);
     let (gossip, ip_addr) = relay1.wait_for_gossip(Duration::from_millis(2000)).unwrap();
-    match parse_gossip(&gossip, ip_addr) {
+    match parse_gossip(&gossip, ip_addr).expect("Gossip could not be parsed") {
         GossipType::DebutGossip(_) => (),
         gt => panic!("Expected GossipType::Debut, but found {:?}", gt),
     }
@@ -1,27 +1,40 @@
 // Copyright (c) 2019, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
 
+use crossbeam_channel::{unbounded, RecvTimeoutError};
 use multinode_integration_tests_lib::command::Command;
 use multinode_integration_tests_lib::main::CONTROL_STREAM_PORT;
 use multinode_integration_tests_lib::masq_cores_client::MASQCoresClient;
 use multinode_integration_tests_lib::masq_cores_server::MASQCoresServer;
 use multinode_integration_tests_lib::masq_node::MASQNode;
 use multinode_integration_tests_lib::masq_node::PortSelector;
-use multinode_integration_tests_lib::masq_node_cluster::MASQNodeCluster;
+use multinode_integration_tests_lib::masq_node_cluster::{MASQNodeCluster, NodeKind};
 use multinode_integration_tests_lib::masq_real_node::NodeStartupConfigBuilder;
+use multinode_integration_tests_lib::multinode_gossip::{
+    parse_gossip, GossipType, MultinodeGossip, SingleNode,
+};
+use node_lib::hopper::live_cores_package::LiveCoresPackage;
 use node_lib::json_masquerader::JsonMasquerader;
-use node_lib::sub_lib::cryptde::PublicKey;
+use node_lib::masquerader::Masquerader;
+use node_lib::neighborhood::AccessibleGossipRecord;
+use node_lib::sub_lib::cryptde::{encodex, PublicKey};
 use node_lib::sub_lib::dispatcher::Component;
-use node_lib::sub_lib::hopper::IncipientCoresPackage;
+use node_lib::sub_lib::hopper::{IncipientCoresPackage, MessageType};
+use node_lib::sub_lib::node_addr::NodeAddr;
 use node_lib::sub_lib::route::Route;
 use node_lib::sub_lib::route::RouteSegment;
+use node_lib::test_utils::data_hunk::DataHunk;
 use node_lib::test_utils::{main_cryptde, make_meaningless_message_type, make_paying_wallet};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
 use std::io::ErrorKind;
 use std::net::IpAddr;
 use std::net::Ipv4Addr;
 use std::net::SocketAddr;
 use std::net::TcpStream;
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::thread;
 use std::time::Duration;
 
 #[test]
@@ -45,6 +58,21 @@ fn establishes_masq_node_cluster_from_nothing() {
             .into_iter()
             .collect();
     assert_eq!(cluster.running_node_names(), expected_nodes);
+    assert_eq!(
+        cluster.real_node_names(),
+        vec![real_node_name.to_string()].into_iter().collect()
+    );
+    assert_eq!(
+        cluster.mock_node_names(),
+        vec![mock_node_name.to_string()].into_iter().collect()
+    );
+    let expected_kinds: HashMap<String, NodeKind> = vec![
+        (real_node_name.to_string(), NodeKind::Real),
+        (mock_node_name.to_string(), NodeKind::Mock),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(cluster.node_kinds(), expected_kinds);
     check_node(&cluster, real_node_name, "172.18.1.1", 8080);
     check_node(&cluster, mock_node_name, "172.18.1.2", CONTROL_STREAM_PORT);
 
@@ -60,6 +88,165 @@ fn establishes_masq_node_cluster_from_nothing() {
     assert_eq!(network_is_running(), false);
 }
 
+#[test]
+fn real_node_can_be_started_with_a_memorable_name() {
+    let mut cluster = MASQNodeCluster::start().unwrap();
+    cluster.start_mock_node_with_public_key(vec![2345], &PublicKey::new(&[1, 2, 3, 4]));
+
+    cluster.start_real_node_named(
+        "gateway",
+        NodeStartupConfigBuilder::standard()
+            .fake_public_key(&PublicKey::new(&[2, 3, 4, 5]))
+            .chain(cluster.chain)
+            .build(),
+    );
+
+    assert_eq!(cluster.next_index(), 3);
+    check_node(&cluster, "gateway", "172.18.1.2", 8080);
+    cluster.stop();
+}
+
+#[test]
+fn stopping_all_nodes_leaves_network_up_for_another_batch() {
+    let mut cluster = MASQNodeCluster::start().unwrap();
+    cluster.start_mock_node_with_public_key(vec![2345], &PublicKey::new(&[1, 2, 3, 4]));
+    cluster.start_mock_node_with_public_key(vec![2345], &PublicKey::new(&[2, 3, 4, 5]));
+    let first_ip_addr = IpAddr::V4(Ipv4Addr::new(172, 18, 1, 1));
+    let second_ip_addr = IpAddr::V4(Ipv4Addr::new(172, 18, 1, 2));
+
+    cluster.stop_all_nodes();
+
+    assert_eq!(cluster.running_node_names(), HashSet::new());
+    assert_eq!(cluster.next_index(), 3);
+    ensure_node_is_not_running("mock_node_1", first_ip_addr, CONTROL_STREAM_PORT);
+    ensure_node_is_not_running("mock_node_2", second_ip_addr, CONTROL_STREAM_PORT);
+    assert_eq!(network_is_running(), true);
+    cluster.start_mock_node_with_public_key(vec![2345], &PublicKey::new(&[3, 4, 5, 6]));
+    check_node(&cluster, "mock_node_3", "172.18.1.3", CONTROL_STREAM_PORT);
+    cluster.stop();
+}
+
+#[test]
+fn nodes_can_be_started_on_an_additional_network() {
+    let mut cluster = MASQNodeCluster::start().unwrap();
+    cluster
+        .create_additional_network("other_net", "172.19.0.0/16")
+        .unwrap();
+    assert_eq!(named_network_is_running("other_net"), true);
+
+    cluster.start_mock_node_with_public_key(vec![2345], &PublicKey::new(&[1, 2, 3, 4]));
+    cluster.start_mock_node_on_network(
+        vec![2345],
+        Some(&PublicKey::new(&[2, 3, 4, 5])),
+        Some("other_net"),
+    );
+
+    check_node(&cluster, "mock_node_1", "172.18.1.1", CONTROL_STREAM_PORT);
+    check_node(&cluster, "mock_node_2", "172.19.1.2", CONTROL_STREAM_PORT);
+    cluster.stop();
+    assert_eq!(network_is_running(), false);
+    assert_eq!(named_network_is_running("other_net"), false);
+}
+
+#[test]
+fn cluster_reports_node_whose_container_disappears() {
+    let mut cluster = MASQNodeCluster::start().unwrap();
+    let (tx, rx) = unbounded();
+    cluster.on_node_crash(move |name| tx.send(name.to_string()).unwrap());
+    cluster.start_mock_node_with_public_key(vec![2345], &PublicKey::new(&[1, 2, 3, 4]));
+    cluster.start_mock_node_with_public_key(vec![2345], &PublicKey::new(&[2, 3, 4, 5]));
+
+    let mut command = Command::new(
+        "docker",
+        Command::strings(vec!["stop", "-t", "0", "mock_node_2"]),
+    );
+    assert_eq!(command.wait_for_exit(), 0);
+
+    assert_eq!(
+        rx.recv_timeout(Duration::from_secs(5)),
+        Ok("mock_node_2".to_string())
+    );
+    cluster.stop_node("mock_node_1");
+    assert_eq!(
+        rx.recv_timeout(Duration::from_secs(2)),
+        Err(RecvTimeoutError::Timeout)
+    );
+    cluster.stop();
+}
+
+#[test]
+fn cluster_reports_real_node_whose_masq_node_process_dies() {
+    let mut cluster = MASQNodeCluster::start().unwrap();
+    let (tx, rx) = unbounded();
+    cluster.on_node_crash(move |name| tx.send(name.to_string()).unwrap());
+    let real_node = cluster.start_real_node(
+        NodeStartupConfigBuilder::zero_hop()
+            .chain(cluster.chain)
+            .build(),
+    );
+    assert_eq!(
+        rx.recv_timeout(Duration::from_secs(2)),
+        Err(RecvTimeoutError::Timeout)
+    );
+
+    real_node.kill_node();
+
+    assert_eq!(
+        rx.recv_timeout(Duration::from_secs(5)),
+        Ok(real_node.name().to_string())
+    );
+    cluster.stop();
+}
+
+#[test]
+fn cluster_does_not_report_real_node_it_was_told_to_kill() {
+    let mut cluster = MASQNodeCluster::start().unwrap();
+    let (tx, rx) = unbounded();
+    cluster.on_node_crash(move |name| tx.send(name.to_string()).unwrap());
+    let real_node = cluster.start_real_node(
+        NodeStartupConfigBuilder::zero_hop()
+            .chain(cluster.chain)
+            .build(),
+    );
+
+    cluster.kill_node(real_node.name());
+
+    assert_eq!(
+        rx.recv_timeout(Duration::from_secs(2)),
+        Err(RecvTimeoutError::Timeout)
+    );
+    cluster.stop();
+}
+
+#[test]
+fn files_can_be_copied_into_and_out_of_a_node() {
+    let mut cluster = MASQNodeCluster::start().unwrap();
+    cluster.start_mock_node_with_public_key(vec![2345], &PublicKey::new(&[1, 2, 3, 4]));
+    let local_dir = env::temp_dir().join("files_can_be_copied_into_and_out_of_a_node");
+    let _ = fs::remove_dir_all(&local_dir);
+    fs::create_dir_all(&local_dir).unwrap();
+    let original = local_dir.join("original.txt");
+    let retrieved = local_dir.join("retrieved.txt");
+    fs::write(&original, "Booga booga").unwrap();
+
+    cluster
+        .copy_into("mock_node_1", &original, "/tmp/fixture.txt")
+        .unwrap();
+    cluster
+        .copy_out("mock_node_1", "/tmp/fixture.txt", &retrieved)
+        .unwrap();
+
+    assert_eq!(
+        fs::read_to_string(&retrieved).unwrap(),
+        "Booga booga".to_string()
+    );
+    assert_eq!(
+        cluster.copy_out("mock_node_9", "/tmp/fixture.txt", &retrieved),
+        Err("Node mock_node_9 was not found in cluster".to_string())
+    );
+    cluster.stop();
+}
+
 #[test]
 fn server_relays_cores_package() {
     let cluster = MASQNodeCluster::start().unwrap();
@@ -158,6 +345,165 @@ fn one_mock_node_talks_to_another() {
     );
 }
 
+#[test]
+fn ping_round_trip_ignores_gossip_that_arrived_before_the_ping() {
+    let mut cluster = MASQNodeCluster::start().unwrap();
+    let pinger =
+        cluster.start_mock_node_with_public_key(vec![5550], &PublicKey::new(&[1, 2, 3, 4]));
+    let silent_target =
+        cluster.start_mock_node_with_public_key(vec![5551], &PublicKey::new(&[2, 3, 4, 5]));
+    silent_target.transmit_debut(&pinger).unwrap();
+    thread::sleep(Duration::from_millis(500));
+
+    let result = pinger.ping_round_trip(&silent_target, Duration::from_secs(1));
+
+    assert_eq!(result, None);
+    let (_, early_sender) = pinger.wait_for_gossip(Duration::from_millis(100)).unwrap();
+    assert_eq!(early_sender, silent_target.ip_address());
+    cluster.stop();
+}
+
+#[test]
+fn wait_for_any_gossip_keeps_gossip_that_straddles_listening_turns() {
+    let mut cluster = MASQNodeCluster::start().unwrap();
+    let sender =
+        cluster.start_mock_node_with_public_key(vec![5550], &PublicKey::new(&[1, 2, 3, 4]));
+    let listener =
+        cluster.start_mock_node_with_public_key(vec![5551], &PublicKey::new(&[2, 3, 4, 5]));
+    let target_key = listener.main_public_key();
+    let route = Route::single_hop(target_key, sender.signing_cryptde().unwrap()).unwrap();
+    let package = IncipientCoresPackage::new(
+        sender.signing_cryptde().unwrap(),
+        route,
+        MessageType::Gossip(SingleNode::new(&sender).render().into()),
+        target_key,
+    )
+    .unwrap();
+    let (lcp, _) =
+        LiveCoresPackage::from_incipient(package, sender.signing_cryptde().unwrap()).unwrap();
+    let encrypted_data = encodex(sender.signing_cryptde().unwrap(), target_key, &lcp).unwrap();
+    let masked_data = JsonMasquerader::new()
+        .mask(encrypted_data.as_slice())
+        .unwrap();
+    let (first_half, second_half) = masked_data.split_at(masked_data.len() / 2);
+    let from = sender.socket_addr(PortSelector::First);
+    let to = listener.socket_addr(PortSelector::First);
+    sender
+        .transmit_data(DataHunk::new(from, to, first_half.to_vec()))
+        .unwrap();
+
+    let early_result = cluster.wait_for_any_gossip(Duration::from_millis(500));
+
+    assert_eq!(early_result, None);
+    sender
+        .transmit_data(DataHunk::new(from, to, second_half.to_vec()))
+        .unwrap();
+    let (name, _, sender_ip) = cluster.wait_for_any_gossip(Duration::from_secs(2)).unwrap();
+    assert_eq!(name, listener.name().to_string());
+    assert_eq!(sender_ip, sender.ip_address());
+    cluster.stop();
+}
+
+#[test]
+fn real_node_log_is_found_in_a_configured_data_directory() {
+    let mut cluster = MASQNodeCluster::start().unwrap();
+    let real_node = cluster.start_real_node(
+        NodeStartupConfigBuilder::standard()
+            .data_directory(PathBuf::from("/node_root/elsewhere"))
+            .chain(cluster.chain)
+            .build(),
+    );
+
+    let result = real_node.wait_for_log("MASQ Node local descriptor", Duration::from_secs(5));
+
+    assert!(
+        result.is_ok(),
+        "Log wasn't found in the data directory: {:?}",
+        result
+    );
+    cluster.stop();
+}
+
+#[test]
+fn auto_bootstrapping_mock_node_introduces_a_real_node_to_its_introducee() {
+    let mut cluster = MASQNodeCluster::start().unwrap();
+    let bootstrapper =
+        cluster.start_mock_node_with_public_key(vec![5550], &PublicKey::new(&[1, 2, 3, 4]));
+    let introducee =
+        cluster.start_mock_node_with_public_key(vec![5551], &PublicKey::new(&[2, 3, 4, 5]));
+    bootstrapper.auto_bootstrap(vec![&introducee as &dyn MASQNode]);
+    let real_node = cluster.start_real_node(
+        NodeStartupConfigBuilder::standard()
+            .fake_public_key(&PublicKey::new(&[3, 4, 5, 6]))
+            .neighbor(bootstrapper.node_reference())
+            .chain(cluster.chain)
+            .build(),
+    );
+
+    let (_, debut_sender) = bootstrapper
+        .wait_for_gossip(Duration::from_secs(5))
+        .unwrap();
+
+    assert_eq!(debut_sender, real_node.ip_address());
+    let (gossip, sender) = introducee.wait_for_gossip(Duration::from_secs(5)).unwrap();
+    assert_eq!(sender, real_node.ip_address());
+    match parse_gossip(&gossip, sender) {
+        Ok(GossipType::DebutGossip(_)) => (),
+        unexpected => panic!("Expected Debut from the real Node, got {:?}", unexpected),
+    }
+    cluster.stop();
+}
+
+#[test]
+fn auto_bootstrapping_mock_node_leaves_a_debut_without_ports_unanswered() {
+    let mut cluster = MASQNodeCluster::start().unwrap();
+    let bootstrapper =
+        cluster.start_mock_node_with_public_key(vec![5550], &PublicKey::new(&[1, 2, 3, 4]));
+    let debuter =
+        cluster.start_mock_node_with_public_key(vec![5551], &PublicKey::new(&[2, 3, 4, 5]));
+    let introducee =
+        cluster.start_mock_node_with_public_key(vec![5552], &PublicKey::new(&[3, 4, 5, 6]));
+    bootstrapper.auto_bootstrap(vec![&introducee as &dyn MASQNode]);
+    let mut debuter_agr = AccessibleGossipRecord::from(&debuter as &dyn MASQNode);
+    debuter_agr.node_addr_opt = Some(NodeAddr::new(&debuter.ip_address(), &[]));
+    debuter
+        .transmit_multinode_gossip(&bootstrapper, &SingleNode::from(&debuter_agr))
+        .unwrap();
+
+    let (_, debut_sender) = bootstrapper
+        .wait_for_gossip(Duration::from_secs(2))
+        .unwrap();
+
+    assert_eq!(debut_sender, debuter.ip_address());
+    assert_eq!(debuter.wait_for_gossip(Duration::from_millis(500)), None);
+    cluster.stop();
+}
+
+#[test]
+fn real_node_survives_a_garbage_cores_package() {
+    let mut cluster = MASQNodeCluster::start().unwrap();
+    let real_node = cluster.start_real_node(
+        NodeStartupConfigBuilder::standard()
+            .fake_public_key(&PublicKey::new(&[1, 2, 3, 4]))
+            .chain(cluster.chain)
+            .build(),
+    );
+    let mock_node =
+        cluster.start_mock_node_with_public_key(vec![5550], &PublicKey::new(&[2, 3, 4, 5]));
+
+    mock_node
+        .transmit_cores_package(&real_node, b"not a CORES package at all".to_vec())
+        .unwrap();
+
+    thread::sleep(Duration::from_millis(500));
+    mock_node.transmit_debut(&real_node).unwrap();
+    let (_, sender) = mock_node
+        .wait_for_gossip(Duration::from_secs(5))
+        .expect("Real Node didn't answer a Debut after the garbage package");
+    assert_eq!(sender, real_node.ip_address());
+    cluster.stop();
+}
+
 fn check_node(cluster: &MASQNodeCluster, name: &str, ip_address: &str, port: u16) {
     let node = cluster
         .get_node_by_name(name)
@@ -185,10 +531,14 @@ fn node_is_running(ip_address: IpAddr, port: u16) -> bool {
 }
 
 fn network_is_running() -> bool {
+    named_network_is_running("integration_net")
+}
+
+fn named_network_is_running(network_name: &str) -> bool {
     let mut command = Command::new("docker", Command::strings(vec!["network", "ls"]));
     assert_eq!(command.wait_for_exit(), 0);
     let output = command.stdout_as_string();
-    output.contains("integration_net")
+    output.contains(network_name)
 }
 
 fn ensure_node_is_running(container_name: &str, ip_address: IpAddr, port: u16) {
@@ -5,7 +5,7 @@ use crate::actor_system_factory::ActorSystemFactoryReal;
 use crate::actor_system_factory::{ActorFactoryReal, ActorSystemFactoryToolsReal};
 use crate::crash_test_dummy::CrashTestDummy;
 use crate::database::db_initializer::DbInitializationConfig;
-use crate::database::db_initializer::{DbInitializer, DbInitializerReal};
+use crate::database::db_initializer::{DbInitializer, DbInitializerReal, DATABASE_FILE};
 use crate::db_config::config_dao::ConfigDaoReal;
 use crate::db_config::persistent_configuration::{
     PersistentConfiguration, PersistentConfigurationReal,
@@ -20,7 +20,7 @@ use crate::node_configurator::node_configurator_standard::{
     NodeConfiguratorStandardPrivileged, NodeConfiguratorStandardUnprivileged,
 };
 use crate::node_configurator::{initialize_database, DirsWrapper, NodeConfigurator};
-use crate::privilege_drop::{IdWrapper, IdWrapperReal};
+use crate::privilege_drop::{IdWrapper, IdWrapperReal, PrivilegeDropper, PrivilegeDropperReal};
 use crate::server_initializer::LoggerInitializerWrapper;
 use crate::stream_handler_pool::StreamHandlerPoolSubs;
 use crate::sub_lib::accountant;
@@ -41,7 +41,7 @@ use itertools::Itertools;
 use log::LevelFilter;
 use masq_lib::blockchains::chains::Chain;
 use masq_lib::command::StdStreams;
-use masq_lib::constants::DEFAULT_UI_PORT;
+use masq_lib::constants::{CURRENT_LOGFILE_NAME, DEFAULT_UI_PORT};
 use masq_lib::crash_point::CrashPoint;
 use masq_lib::logger::Logger;
 use masq_lib::multi_config::MultiConfig;
@@ -53,7 +53,7 @@ use std::env::var;
 use std::fmt;
 use std::fmt::{Debug, Display, Error, Formatter};
 use std::net::{Ipv4Addr, SocketAddr};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::vec::Vec;
 use tokio::prelude::stream::futures_unordered::FuturesUnordered;
@@ -146,17 +146,20 @@ impl EnvironmentWrapper for EnvironmentWrapperReal {
 
 pub struct RealUser {
     environment_wrapper: Box<dyn EnvironmentWrapper>,
+    id_wrapper: Box<dyn IdWrapper>,
     pub uid_opt: Option<i32>,
     pub gid_opt: Option<i32>,
     pub home_dir_opt: Option<PathBuf>,
+    // Windows has no uid or gid; files are handed over to an account SID instead
+    pub sid_opt: Option<String>,
 }
 
 impl Debug for RealUser {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
         write!(
             f,
-            "uid: {:?}, gid: {:?}, home_dir: {:?}",
-            self.uid_opt, self.gid_opt, self.home_dir_opt
+            "uid: {:?}, gid: {:?}, home_dir: {:?}, sid: {:?}",
+            self.uid_opt, self.gid_opt, self.home_dir_opt, self.sid_opt
         )
     }
 }
@@ -166,6 +169,7 @@ impl PartialEq for RealUser {
         self.uid_opt == other.uid_opt
             && self.gid_opt == other.gid_opt
             && self.home_dir_opt == other.home_dir_opt
+            && self.sid_opt == other.sid_opt
     }
 }
 
@@ -177,35 +181,57 @@ impl Default for RealUser {
 
 impl Clone for RealUser {
     fn clone(&self) -> Self {
-        RealUser::new(self.uid_opt, self.gid_opt, self.home_dir_opt.clone())
+        let mut clone = RealUser::new(self.uid_opt, self.gid_opt, self.home_dir_opt.clone());
+        clone.sid_opt = self.sid_opt.clone();
+        clone
     }
 }
 
 impl FromStr for RealUser {
-    type Err = ();
+    type Err = String;
 
     fn from_str(triple: &str) -> Result<Self, Self::Err> {
+        RealUser::from_str_with_id_wrapper(&IdWrapperReal {}, triple)
+    }
+}
+
+impl RealUser {
+    pub fn from_str_with_id_wrapper(
+        id_wrapper: &dyn IdWrapper,
+        triple: &str,
+    ) -> Result<RealUser, String> {
+        // Windows has no uids or gids: there, the real user is the account with this SID
+        if cfg!(target_os = "windows") && triple.starts_with("S-") {
+            let mut real_user = RealUser::new(None, None, None);
+            real_user.sid_opt = Some(triple.to_string());
+            return Ok(real_user);
+        }
+        // a bare user name is looked up in the system user database
+        if !triple.is_empty() && !triple.contains(':') {
+            return RealUser::from_user_name(id_wrapper, triple)
+                .ok_or_else(|| format!("No user named '{}' could be found", triple));
+        }
         let parts: Vec<&str> = triple.splitn(3, ':').collect_vec();
         // validator should have ensured that there are exactly three parts,
         // and that the first two are empty or numeric
         if parts.len() < 3 {
-            return Err(());
+            return Err(format!(
+                "Real user '{}' should look like <uid>:<gid>:<home directory>",
+                triple
+            ));
         }
+        let id_from = |part: &str, name: &str| match part {
+            s if s.is_empty() => Ok(None),
+            s => s.parse::<i32>().map(Some).map_err(|_| {
+                format!(
+                    "Real user '{}' has non-numeric {} id '{}'",
+                    triple, name, part
+                )
+            }),
+        };
         let real_user = RealUser::new(
-            match &parts[0] {
-                s if s.is_empty() => None,
-                s => match s.parse() {
-                    Ok(uid) => Some(uid),
-                    Err(_) => return Err(()),
-                },
-            },
-            match &parts[1] {
-                s if s.is_empty() => None,
-                s => match s.parse() {
-                    Ok(gid) => Some(gid),
-                    Err(_) => return Err(()),
-                },
-            },
+            id_from(parts[0], "user")?,
+            id_from(parts[1], "group")?,
             match &parts[2] {
                 s if s.is_empty() => None,
                 s => Some(s.into()),
@@ -213,9 +239,7 @@ impl FromStr for RealUser {
         );
         Ok(real_user)
     }
-}
 
-impl RealUser {
     pub fn new(
         uid_opt: Option<i32>,
         gid_opt: Option<i32>,
@@ -223,9 +247,11 @@ impl RealUser {
     ) -> RealUser {
         let mut result = RealUser {
             environment_wrapper: Box::new(EnvironmentWrapperReal),
+            id_wrapper: Box::new(IdWrapperReal {}),
             uid_opt: None,
             gid_opt: None,
             home_dir_opt,
+            sid_opt: None,
         };
         result.initialize_ids(Box::new(IdWrapperReal {}), uid_opt, gid_opt);
         result
@@ -234,21 +260,52 @@ impl RealUser {
     pub fn null() -> RealUser {
         RealUser {
             environment_wrapper: Box::new(EnvironmentWrapperReal),
+            id_wrapper: Box::new(IdWrapperReal {}),
             uid_opt: None,
             gid_opt: None,
             home_dir_opt: None,
+            sid_opt: None,
         }
     }
 
+    pub fn from_user_name(id_wrapper: &dyn IdWrapper, user_name: &str) -> Option<RealUser> {
+        id_wrapper
+            .getpwnam(user_name)
+            .map(|(uid, gid, home_dir)| RealUser::new(Some(uid), Some(gid), Some(home_dir)))
+    }
+
+    pub fn from_sudo_env() -> Option<RealUser> {
+        Self::from_sudo_env_wrapper(Box::new(EnvironmentWrapperReal))
+    }
+
+    fn from_sudo_env_wrapper(environment_wrapper: Box<dyn EnvironmentWrapper>) -> Option<RealUser> {
+        let mut probe = RealUser::null();
+        probe.environment_wrapper = environment_wrapper;
+        let uid = probe.id_from_env("SUDO_UID")?;
+        let gid = probe.id_from_env("SUDO_GID")?;
+        Some(RealUser::new(
+            Some(uid),
+            Some(gid),
+            probe.sudo_home_from_sudo_user_and_home(),
+        ))
+    }
+
     pub fn populate(&self, dirs_wrapper: &dyn DirsWrapper) -> RealUser {
         let uid = Self::first_present(vec![self.uid_opt, self.id_from_env("SUDO_UID")]);
         let gid = Self::first_present(vec![self.gid_opt, self.id_from_env("SUDO_GID")]);
-        let home_dir = Self::first_present(vec![
-            self.home_dir_opt.clone(),
-            self.sudo_home_from_sudo_user_and_home(),
-            dirs_wrapper.home_dir(),
-        ]);
-        RealUser::new(Some(uid), Some(gid), Some(home_dir))
+        let home_dir = match &self.home_dir_opt {
+            Some(home_dir) => home_dir.clone(),
+            None => Self::first_present(vec![
+                self.id_wrapper
+                    .getpwuid(uid)
+                    .map(|(_, _, home_dir)| home_dir),
+                self.sudo_home_from_sudo_user_and_home(),
+                dirs_wrapper.home_dir(),
+            ]),
+        };
+        let mut populated = RealUser::new(Some(uid), Some(gid), Some(home_dir));
+        populated.sid_opt = self.sid_opt.clone();
+        populated
     }
 
     #[cfg(not(target_os = "windows"))]
@@ -445,6 +502,7 @@ pub struct Bootstrapper {
     listener_handlers: FuturesUnordered<Box<dyn ListenerHandler<Item = (), Error = ()>>>,
     actor_system_factory: Box<dyn ActorSystemFactory>,
     logger_initializer: Box<dyn LoggerInitializerWrapper>,
+    privilege_dropper: Box<dyn PrivilegeDropper>,
     config: BootstrapperConfig,
 }
 
@@ -470,7 +528,11 @@ impl ConfiguredByPrivilege for Bootstrapper {
             &self.config.real_user,
             self.config.log_level,
             None,
-        );
+        )?;
+        self.privilege_dropper.chown_all(
+            &Self::node_files(&self.config.data_directory),
+            &self.config.real_user,
+        )?;
         self.listener_handlers =
             FuturesUnordered::<Box<dyn ListenerHandler<Item = (), Error = ()>>>::new();
         let port_configurations = self.config.port_configurations.clone();
@@ -537,10 +599,21 @@ impl Bootstrapper {
                 ActorSystemFactoryToolsReal::new(),
             ))),
             logger_initializer,
+            privilege_dropper: Box::new(PrivilegeDropperReal::new()),
             config: BootstrapperConfig::new(),
         }
     }
 
+    // The files the Node itself keeps in its data directory, if they're there yet; anything else
+    // in the directory belongs to whoever put it there.
+    fn node_files(data_directory: &Path) -> Vec<PathBuf> {
+        vec![DATABASE_FILE, "config.toml", CURRENT_LOGFILE_NAME]
+            .into_iter()
+            .map(|file_name| data_directory.join(file_name))
+            .filter(|file| std::fs::symlink_metadata(file).is_ok())
+            .collect()
+    }
+
     #[cfg(test)] // The real ones are private, but ActorSystemFactory needs to use them for testing
     pub fn pub_initialize_cryptdes_for_testing(
         main_cryptde_null_opt: &Option<&dyn CryptDE>,
@@ -727,7 +800,10 @@ mod tests {
     use crate::node_test_utils::make_stream_handler_pool_subs_from;
     use crate::node_test_utils::TestLogOwner;
     use crate::node_test_utils::{extract_log, DirsWrapperMock, IdWrapperMock};
-    use crate::server_initializer::test_utils::LoggerInitializerWrapperMock;
+    use crate::privilege_drop::PrivilegeError;
+    use crate::server_initializer::test_utils::{
+        LoggerInitializerWrapperMock, PrivilegeDropperMock,
+    };
     use crate::server_initializer::LoggerInitializerWrapper;
     use crate::stream_handler_pool::StreamHandlerPoolSubs;
     use crate::stream_messages::AddStreamMsg;
@@ -945,28 +1021,98 @@ mod tests {
     fn real_user_from_blank() {
         let result = RealUser::from_str("").err().unwrap();
 
-        assert_eq!(result, ());
+        assert_eq!(
+            result,
+            "Real user '' should look like <uid>:<gid>:<home directory>".to_string()
+        );
     }
 
     #[test]
     fn real_user_from_one_colon() {
         let result = RealUser::from_str(":").err().unwrap();
 
-        assert_eq!(result, ());
+        assert_eq!(
+            result,
+            "Real user ':' should look like <uid>:<gid>:<home directory>".to_string()
+        );
     }
 
     #[test]
     fn real_user_from_nonnumeric_uid() {
         let result = RealUser::from_str("booga:1234:").err().unwrap();
 
-        assert_eq!(result, ());
+        assert_eq!(
+            result,
+            "Real user 'booga:1234:' has non-numeric user id 'booga'".to_string()
+        );
     }
 
     #[test]
     fn real_user_from_nonnumeric_gid() {
         let result = RealUser::from_str("1234:booga:").err().unwrap();
 
-        assert_eq!(result, ());
+        assert_eq!(
+            result,
+            "Real user '1234:booga:' has non-numeric group id 'booga'".to_string()
+        );
+    }
+
+    #[test]
+    fn real_user_from_unknown_user_name() {
+        let getpwnam_params_arc = Arc::new(Mutex::new(vec![]));
+        let id_wrapper = IdWrapperMock::new()
+            .getpwnam_params(&getpwnam_params_arc)
+            .getpwnam_result(None);
+
+        let result = RealUser::from_str_with_id_wrapper(&id_wrapper, "booga")
+            .err()
+            .unwrap();
+
+        assert_eq!(result, "No user named 'booga' could be found".to_string());
+        let getpwnam_params = getpwnam_params_arc.lock().unwrap();
+        assert_eq!(*getpwnam_params, vec!["booga".to_string()]);
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn real_user_from_sid_on_windows() {
+        let id_wrapper = IdWrapperMock::new();
+
+        let result =
+            RealUser::from_str_with_id_wrapper(&id_wrapper, "S-1-5-21-1004336348-1177238915-1001")
+                .unwrap();
+
+        let mut expected = RealUser::new(None, None, None);
+        expected.sid_opt = Some("S-1-5-21-1004336348-1177238915-1001".to_string());
+        assert_eq!(result, expected);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn real_user_from_sid_outside_windows_is_unknown_user() {
+        let id_wrapper = IdWrapperMock::new().getpwnam_result(None);
+
+        let result = RealUser::from_str_with_id_wrapper(&id_wrapper, "S-1-5-21-1001")
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            result,
+            "No user named 'S-1-5-21-1001' could be found".to_string()
+        );
+    }
+
+    #[test]
+    fn real_user_from_known_user_name() {
+        let id_wrapper =
+            IdWrapperMock::new().getpwnam_result(Some((1001, 1002, PathBuf::from("/home/booga"))));
+
+        let result = RealUser::from_str_with_id_wrapper(&id_wrapper, "booga").unwrap();
+
+        assert_eq!(
+            result,
+            RealUser::new(Some(1001), Some(1002), Some(PathBuf::from("/home/booga")))
+        );
     }
 
     #[test]
@@ -997,6 +1143,13 @@ mod tests {
         assert_eq!(subject, RealUser::new(None, Some(456), None))
     }
 
+    #[test]
+    fn real_user_from_uid_and_gid_only() {
+        let subject = RealUser::from_str("1000:1000:").unwrap();
+
+        assert_eq!(subject, RealUser::new(Some(1000), Some(1000), None))
+    }
+
     #[test]
     fn real_user_from_home_dir_only() {
         let subject = RealUser::from_str("::booga").unwrap();
@@ -1124,6 +1277,83 @@ mod tests {
         assert_eq!(config.clandestine_discriminator_factories.is_empty(), true);
     }
 
+    #[test]
+    fn initialize_as_privileged_reports_log_file_that_could_not_be_handed_over() {
+        let _lock = INITIALIZATION.lock();
+        let data_dir = ensure_node_home_directory_exists(
+            "bootstrapper",
+            "initialize_as_privileged_reports_log_file_that_could_not_be_handed_over",
+        );
+        let log_file = data_dir.join("MASQNode_rCURRENT.log");
+        let logger_initializer =
+            LoggerInitializerWrapperMock::new().init_result(Err(PrivilegeError::ChownFailed {
+                path: log_file.clone(),
+                code: 1,
+            }));
+        let mut subject = Bootstrapper::new(Box::new(logger_initializer));
+
+        let result = subject.initialize_as_privileged(&make_simplified_multi_config([
+            "--data-directory",
+            data_dir.to_str().unwrap(),
+            "--ip",
+            "2.2.2.2",
+            "--real-user",
+            "123:456:/home/booga",
+        ]));
+
+        assert_eq!(
+            result,
+            Err(ConfiguratorError::required(
+                "real-user",
+                &format!("Error code 1 changing ownership of {:?}", log_file)
+            ))
+        );
+    }
+
+    #[test]
+    fn initialize_as_privileged_hands_the_node_files_over_to_the_real_user() {
+        let _lock = INITIALIZATION.lock();
+        let data_dir = ensure_node_home_directory_exists(
+            "bootstrapper",
+            "initialize_as_privileged_hands_the_node_files_over_to_the_real_user",
+        );
+        std::fs::write(data_dir.join(DATABASE_FILE), "booga").unwrap();
+        std::fs::write(data_dir.join("config.toml"), "booga").unwrap();
+        std::fs::write(data_dir.join("not_the_nodes"), "booga").unwrap();
+        let chown_all_params_arc = Arc::new(Mutex::new(vec![]));
+        let privilege_dropper = PrivilegeDropperMock::new().chown_all_params(&chown_all_params_arc);
+        let mut listener_handler_factory = ListenerHandlerFactoryMock::new();
+        listener_handler_factory.add(Box::new(
+            ListenerHandlerNull::new(vec![]).bind_port_result(Ok(())),
+        ));
+        listener_handler_factory.add(Box::new(
+            ListenerHandlerNull::new(vec![]).bind_port_result(Ok(())),
+        ));
+        let mut subject = Bootstrapper::new(Box::new(LoggerInitializerWrapperMock::new()));
+        subject.listener_handler_factory = Box::new(listener_handler_factory);
+        subject.privilege_dropper = Box::new(privilege_dropper);
+
+        subject
+            .initialize_as_privileged(&make_simplified_multi_config([
+                "--data-directory",
+                data_dir.to_str().unwrap(),
+                "--ip",
+                "2.2.2.2",
+                "--real-user",
+                "123:456:/home/booga",
+            ]))
+            .unwrap();
+
+        let chown_all_params = chown_all_params_arc.lock().unwrap();
+        assert_eq!(
+            *chown_all_params,
+            vec![(
+                vec![data_dir.join(DATABASE_FILE), data_dir.join("config.toml")],
+                RealUser::new(Some(123), Some(456), Some("/home/booga".into())),
+            )]
+        )
+    }
+
     #[test]
     fn initialize_as_privileged_points_logger_initializer_at_data_directory() {
         let _lock = INITIALIZATION.lock();
@@ -2112,6 +2342,51 @@ mod tests {
         assert_eq!(subject.home_dir_opt, None);
     }
 
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn from_sudo_env_reads_sudo_variables() {
+        let environment_wrapper =
+            EnvironmentWrapperMock::new(Some("123"), Some("456"), Some("username"));
+
+        let result = RealUser::from_sudo_env_wrapper(Box::new(environment_wrapper));
+
+        assert_eq!(
+            result,
+            Some(RealUser::new(
+                Some(123),
+                Some(456),
+                Some(PathBuf::from("/home/username"))
+            ))
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn from_sudo_env_tolerates_missing_sudo_user() {
+        let environment_wrapper = EnvironmentWrapperMock::new(Some("123"), Some("456"), None);
+
+        let result = RealUser::from_sudo_env_wrapper(Box::new(environment_wrapper));
+
+        assert_eq!(result, Some(RealUser::new(Some(123), Some(456), None)));
+    }
+
+    #[test]
+    fn from_sudo_env_needs_both_ids() {
+        let no_uid = EnvironmentWrapperMock::new(None, Some("456"), Some("username"));
+        let no_gid = EnvironmentWrapperMock::new(Some("123"), None, Some("username"));
+        let bad_uid = EnvironmentWrapperMock::new(Some("booga"), Some("456"), Some("username"));
+        let nothing = EnvironmentWrapperMock::new(None, None, None);
+
+        vec![no_uid, no_gid, bad_uid, nothing]
+            .into_iter()
+            .for_each(|environment_wrapper| {
+                assert_eq!(
+                    RealUser::from_sudo_env_wrapper(Box::new(environment_wrapper)),
+                    None
+                )
+            });
+    }
+
     #[test]
     fn configurator_beats_all() {
         let environment_wrapper =
@@ -2133,6 +2408,7 @@ mod tests {
         let mut from_configurator = RealUser::null();
         from_configurator.environment_wrapper = Box::new(environment_wrapper);
         from_configurator.initialize_ids(Box::new(id_wrapper), None, None);
+        from_configurator.id_wrapper = Box::new(IdWrapperMock::new().getpwuid_result(None));
 
         let result = from_configurator
             .populate(&DirsWrapperMock::new().home_dir_result(Some("/root".into())));
@@ -2152,6 +2428,7 @@ mod tests {
         let mut from_configurator = RealUser::null();
         from_configurator.environment_wrapper = Box::new(environment_wrapper);
         from_configurator.initialize_ids(Box::new(id_wrapper), None, None);
+        from_configurator.id_wrapper = Box::new(IdWrapperMock::new().getpwuid_result(None));
 
         let result = from_configurator
             .populate(&DirsWrapperMock::new().home_dir_result(Some("/var/root".into())));
@@ -2162,6 +2439,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn password_database_beats_environment_for_home_directory() {
+        let getpwuid_params_arc = Arc::new(Mutex::new(vec![]));
+        let environment_wrapper = EnvironmentWrapperMock::new(None, None, Some("username"));
+        let mut from_configurator = RealUser::null();
+        from_configurator.uid_opt = Some(101);
+        from_configurator.gid_opt = Some(202);
+        from_configurator.environment_wrapper = Box::new(environment_wrapper);
+        from_configurator.id_wrapper = Box::new(
+            IdWrapperMock::new()
+                .getpwuid_params(&getpwuid_params_arc)
+                .getpwuid_result(Some((101, 202, "/home/masq".into()))),
+        );
+
+        let result = from_configurator
+            .populate(&DirsWrapperMock::new().home_dir_result(Some("/root".into())));
+
+        assert_eq!(
+            result,
+            RealUser::new(Some(101), Some(202), Some(PathBuf::from("/home/masq")))
+        );
+        let getpwuid_params = getpwuid_params_arc.lock().unwrap();
+        assert_eq!(*getpwuid_params, vec![101]);
+    }
+
     #[test]
     fn unmodified_is_last_ditch() {
         let environment_wrapper = EnvironmentWrapperMock::new(None, None, None);
@@ -2169,6 +2471,7 @@ mod tests {
         let mut from_configurator = RealUser::null();
         from_configurator.initialize_ids(Box::new(id_wrapper), None, None);
         from_configurator.environment_wrapper = Box::new(environment_wrapper);
+        from_configurator.id_wrapper = Box::new(IdWrapperMock::new().getpwuid_result(None));
 
         let result = from_configurator
             .populate(&DirsWrapperMock::new().home_dir_result(Some("/wibble/whop/ooga".into())));
@@ -2289,6 +2592,7 @@ mod tests {
                     Box<dyn ListenerHandler<Item = (), Error = ()>>,
                 >::new(),
                 logger_initializer: self.log_initializer_wrapper,
+                privilege_dropper: Box::new(PrivilegeDropperMock::new()),
                 config: self.config,
             }
         }
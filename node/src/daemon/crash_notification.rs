@@ -8,6 +8,7 @@ lazy_static! {
     static ref RECOGNIZERS: Vec<Box<dyn Recognizer>> = vec![
         Box::new(ChildWaitFailureRecognizer {}),
         Box::new(NoInformationRecognizer {}),
+        Box::new(ExitCodeRecognizer {}),
         Box::new(UnrecognizedRecognizer {}),
     ];
 }
@@ -79,6 +80,20 @@ impl Recognizer for NoInformationRecognizer {
     }
 }
 
+struct ExitCodeRecognizer {}
+
+impl Recognizer for ExitCodeRecognizer {
+    fn try_convert(&self, exit_code: Option<i32>, stderr: &Option<String>) -> Option<CrashReason> {
+        match (exit_code, stderr) {
+            (Some(code), None) => Some(CrashReason::ExitedWithCode(code)),
+            (Some(code), Some(stderr)) if stderr.trim().is_empty() => {
+                Some(CrashReason::ExitedWithCode(code))
+            }
+            _ => None,
+        }
+    }
+}
+
 struct UnrecognizedRecognizer {}
 
 impl Recognizer for UnrecognizedRecognizer {
@@ -137,6 +152,28 @@ mod tests {
             })
     }
 
+    #[test]
+    fn recognizes_bare_exit_code() {
+        vec![None, Some("".to_string()), Some(" \n\t ".to_string())]
+            .into_iter()
+            .for_each(|stderr| {
+                let subject = CrashNotification {
+                    process_id: 0,
+                    exit_code: Some(137),
+                    stderr: stderr.clone(),
+                };
+
+                let result = subject.analyze();
+
+                assert_eq!(
+                    result,
+                    CrashReason::ExitedWithCode(137),
+                    "Did not recognize {:?} as ExitedWithCode",
+                    stderr
+                )
+            })
+    }
+
     #[test]
     fn eventually_gives_up() {
         let subject = CrashNotification {
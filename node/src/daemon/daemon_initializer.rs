@@ -16,6 +16,7 @@ use crossbeam_channel::{unbounded, Receiver, Sender};
 use flexi_logger::LevelFilter;
 use itertools::Itertools;
 use masq_lib::command::StdStreams;
+use masq_lib::logger::Logger;
 use masq_lib::shared_schema::ConfiguratorError;
 use std::collections::HashMap;
 
@@ -138,12 +139,14 @@ impl DaemonInitializerReal {
         let real_data_dir = PathBuf::from_str(real_home_dir)
             .expectv("path string")
             .join(relative_data_dir);
-        params.logger_initializer_wrapper.init(
+        if let Err(e) = params.logger_initializer_wrapper.init(
             real_data_dir.join("MASQ"),
             &real_user,
             LevelFilter::Trace,
             Some("daemon"),
-        );
+        ) {
+            error!(Logger::new("DaemonInitializer"), "{}", e);
+        }
         DaemonInitializerReal {
             config,
             channel_factory: params.channel_factory,
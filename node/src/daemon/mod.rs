@@ -277,6 +277,7 @@ impl Daemon {
             self.send_ui_message(
                 UiNodeCrashedBroadcast {
                     process_id: msg.process_id,
+                    exit_code_opt: msg.exit_code,
                     crash_reason: msg.analyze(),
                 }
                 .tmb(0),
@@ -1606,6 +1607,7 @@ mod tests {
             &record.body,
             &UiNodeCrashedBroadcast {
                 process_id: 54321,
+                exit_code_opt: Some(123),
                 crash_reason: CrashReason::Unrecognized("Standard error".to_string()),
             }
             .tmb(0)
@@ -39,7 +39,7 @@ impl DumpConfigRunner for DumpConfigRunnerReal {
         let (real_user, data_directory, chain, password_opt) =
             distill_args(&DirsWrapperReal {}, args)?;
         let cryptde = CryptDEReal::new(chain);
-        PrivilegeDropperReal::new().drop_privileges(&real_user);
+        PrivilegeDropperReal::new().drop_privileges(&real_user)?;
         let config_dao = make_config_dao(
             &data_directory,
             DbInitializationConfig::migration_suppressed(),
@@ -146,7 +146,7 @@ fn distill_args(
     ];
     let multi_config = make_new_multi_config(&app, vcls)?;
     let (real_user, data_directory_opt, chain) =
-        real_user_data_directory_opt_and_chain(dirs_wrapper, &multi_config);
+        real_user_data_directory_opt_and_chain(dirs_wrapper, &multi_config)?;
     let directory =
         data_directory_from_context(dirs_wrapper, &real_user, &data_directory_opt, chain);
     let password_opt = value_m!(multi_config, "db-password", String);
@@ -11,6 +11,7 @@ use crate::database::db_initializer::{DbInitializer, DbInitializerReal};
 use crate::db_config::persistent_configuration::{
     PersistentConfiguration, PersistentConfigurationReal,
 };
+use crate::privilege_drop::{IdWrapper, IdWrapperReal};
 use crate::sub_lib::utils::{db_connection_launch_panic, make_new_multi_config};
 use clap::{value_t, App};
 use dirs::{data_local_dir, home_dir};
@@ -57,7 +58,7 @@ pub fn determine_config_file_path(
     let config_file_path = value_m!(multi_config, "config-file", PathBuf).expectv("config-file");
     let user_specified = multi_config.occurrences_of("config-file") > 0;
     let (real_user, data_directory_opt, chain) =
-        real_user_data_directory_opt_and_chain(dirs_wrapper, &multi_config);
+        real_user_data_directory_opt_and_chain(dirs_wrapper, &multi_config)?;
     let directory =
         data_directory_from_context(dirs_wrapper, &real_user, &data_directory_opt, chain);
     Ok((directory.join(config_file_path), user_specified))
@@ -76,26 +77,35 @@ pub fn initialize_database(
 pub fn real_user_from_multi_config_or_populate(
     multi_config: &MultiConfig,
     dirs_wrapper: &dyn DirsWrapper,
-) -> RealUser {
-    match value_m!(multi_config, "real-user", RealUser) {
-        None => RealUser::new(None, None, None).populate(dirs_wrapper),
-        Some(real_user) => real_user.populate(dirs_wrapper),
+) -> Result<RealUser, ConfiguratorError> {
+    let real_user = real_user_from_multi_config(multi_config, &IdWrapperReal {})?;
+    Ok(real_user.populate(dirs_wrapper))
+}
+
+fn real_user_from_multi_config(
+    multi_config: &MultiConfig,
+    id_wrapper: &dyn IdWrapper,
+) -> Result<RealUser, ConfiguratorError> {
+    match value_m!(multi_config, "real-user", String) {
+        None => Ok(RealUser::new(None, None, None)),
+        Some(real_user_str) => RealUser::from_str_with_id_wrapper(id_wrapper, &real_user_str)
+            .map_err(|e| ConfiguratorError::required("real-user", &e)),
     }
 }
 
 pub fn real_user_data_directory_opt_and_chain(
     dirs_wrapper: &dyn DirsWrapper,
     multi_config: &MultiConfig,
-) -> (RealUser, Option<PathBuf>, Chain) {
-    let real_user = real_user_from_multi_config_or_populate(multi_config, dirs_wrapper);
+) -> Result<(RealUser, Option<PathBuf>, Chain), ConfiguratorError> {
+    let real_user = real_user_from_multi_config_or_populate(multi_config, dirs_wrapper)?;
     let chain_name = value_m!(multi_config, "chain", String)
         .unwrap_or_else(|| DEFAULT_CHAIN.rec().literal_identifier.to_string());
     let data_directory_opt = value_m!(multi_config, "data-directory", PathBuf);
-    (
+    Ok((
         real_user,
         data_directory_opt,
         Chain::from(chain_name.as_str()),
-    )
+    ))
 }
 
 pub fn data_directory_from_context(
@@ -159,7 +169,7 @@ impl DirsWrapper for DirsWrapperReal {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::node_test_utils::DirsWrapperMock;
+    use crate::node_test_utils::{DirsWrapperMock, IdWrapperMock};
     use crate::test_utils::ArgsBuilder;
     use masq_lib::test_utils::environment_guard::EnvironmentGuard;
     use masq_lib::utils::find_free_port;
@@ -201,6 +211,25 @@ mod tests {
         )
     }
 
+    #[test]
+    fn real_user_from_multi_config_reports_unknown_user_name() {
+        let args: Vec<String> = ArgsBuilder::new().param("--real-user", "booga").into();
+        let app = App::new("test").arg(real_user_arg());
+        let multi_config =
+            make_new_multi_config(&app, vec![Box::new(CommandLineVcl::new(args))]).unwrap();
+        let id_wrapper = IdWrapperMock::new().getpwnam_result(None);
+
+        let result = real_user_from_multi_config(&multi_config, &id_wrapper);
+
+        assert_eq!(
+            result,
+            Err(ConfiguratorError::required(
+                "real-user",
+                "No user named 'booga' could be found"
+            ))
+        );
+    }
+
     #[test]
     fn determine_config_file_path_finds_path_in_args() {
         let _guard = EnvironmentGuard::new();
@@ -158,7 +158,7 @@ pub fn server_initializer_collected_params<'a>(
         .parent()
         .map(|dir| dir.to_path_buf())
         .expectv("data_directory");
-    let real_user = real_user_from_multi_config_or_populate(&multi_config, dirs_wrapper);
+    let real_user = real_user_from_multi_config_or_populate(&multi_config, dirs_wrapper)?;
     Ok(GatheredParams::new(multi_config, data_directory, real_user))
 }
 
@@ -189,7 +189,7 @@ pub fn privileged_parse_args(
     privileged_config: &mut BootstrapperConfig,
 ) -> Result<(), ConfiguratorError> {
     let (real_user, data_directory_opt, chain) =
-        real_user_data_directory_opt_and_chain(dirs_wrapper, multi_config);
+        real_user_data_directory_opt_and_chain(dirs_wrapper, multi_config)?;
     let directory =
         data_directory_from_context(dirs_wrapper, &real_user, &data_directory_opt, chain);
     privileged_config.real_user = real_user;
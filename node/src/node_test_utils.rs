@@ -8,7 +8,7 @@ use crate::masquerader::MasqueradeError;
 use crate::masquerader::Masquerader;
 use crate::node_configurator::DirsWrapper;
 use crate::null_masquerader::NullMasquerader;
-use crate::privilege_drop::IdWrapper;
+use crate::privilege_drop::{CapabilityDropper, Chowner, IdWrapper, PrivilegeError};
 use crate::stream_handler_pool::StreamHandlerPoolSubs;
 use crate::stream_messages::*;
 use crate::sub_lib::framer::FramedChunk;
@@ -22,7 +22,7 @@ use actix::Addr;
 use masq_lib::test_utils::logging::TestLog;
 use masq_lib::ui_gateway::NodeFromUiMessage;
 use std::cell::RefCell;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -49,23 +49,116 @@ pub struct IdWrapperMock {
     getgid_results: RefCell<Vec<i32>>,
     setgid_params: Arc<Mutex<Vec<i32>>>,
     setgid_results: RefCell<Vec<i32>>,
+    setgroups_params: Arc<Mutex<Vec<Vec<i32>>>>,
+    setgroups_results: RefCell<Vec<i32>>,
+    getgroups_results: RefCell<Vec<Vec<i32>>>,
+    getgrouplist_params: Arc<Mutex<Vec<(i32, i32)>>>,
+    getgrouplist_results: RefCell<Vec<Option<Vec<i32>>>>,
+    getpwnam_params: Arc<Mutex<Vec<String>>>,
+    getpwnam_results: RefCell<Vec<Option<(i32, i32, PathBuf)>>>,
+    getpwuid_params: Arc<Mutex<Vec<i32>>>,
+    getpwuid_results: RefCell<Vec<Option<(i32, i32, PathBuf)>>>,
+    umask_params: Arc<Mutex<Vec<u32>>>,
+    umask_results: RefCell<Vec<u32>>,
+    geteuid_results: RefCell<Vec<i32>>,
+    getegid_results: RefCell<Vec<i32>>,
+    seteuid_params: Arc<Mutex<Vec<i32>>>,
+    seteuid_results: RefCell<Vec<i32>>,
+    setegid_params: Arc<Mutex<Vec<i32>>>,
+    setegid_results: RefCell<Vec<i32>>,
+    calls: Arc<Mutex<Vec<IdWrapperCall>>>,
+}
+
+// One entry per IdWrapperMock or CapabilityDropperMock call, in the order the calls were made
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IdWrapperCall {
+    Getuid,
+    Getgid,
+    Setuid(i32),
+    Setgid(i32),
+    Setgroups(Vec<i32>),
+    Getgroups,
+    Getgrouplist(i32, i32),
+    Getpwnam(String),
+    Getpwuid(i32),
+    Umask(u32),
+    Geteuid,
+    Getegid,
+    Seteuid(i32),
+    Setegid(i32),
+    DropBoundingSet,
+    DropCapabilities,
 }
 
 impl IdWrapper for IdWrapperMock {
     fn getuid(&self) -> i32 {
+        self.record(IdWrapperCall::Getuid);
         self.getuid_results.borrow_mut().remove(0)
     }
     fn getgid(&self) -> i32 {
+        self.record(IdWrapperCall::Getgid);
         self.getgid_results.borrow_mut().remove(0)
     }
     fn setuid(&self, uid: i32) -> i32 {
+        self.record(IdWrapperCall::Setuid(uid));
         self.setuid_params.lock().unwrap().push(uid);
         self.setuid_results.borrow_mut().remove(0)
     }
     fn setgid(&self, gid: i32) -> i32 {
+        self.record(IdWrapperCall::Setgid(gid));
         self.setgid_params.lock().unwrap().push(gid);
         self.setgid_results.borrow_mut().remove(0)
     }
+    fn setgroups(&self, gids: &[i32]) -> i32 {
+        self.record(IdWrapperCall::Setgroups(gids.to_vec()));
+        self.setgroups_params.lock().unwrap().push(gids.to_vec());
+        self.setgroups_results.borrow_mut().remove(0)
+    }
+    fn getgroups(&self) -> Vec<i32> {
+        self.record(IdWrapperCall::Getgroups);
+        self.getgroups_results.borrow_mut().remove(0)
+    }
+    fn getgrouplist(&self, uid: i32, gid: i32) -> Option<Vec<i32>> {
+        self.record(IdWrapperCall::Getgrouplist(uid, gid));
+        self.getgrouplist_params.lock().unwrap().push((uid, gid));
+        self.getgrouplist_results.borrow_mut().remove(0)
+    }
+    fn getpwnam(&self, user_name: &str) -> Option<(i32, i32, PathBuf)> {
+        self.record(IdWrapperCall::Getpwnam(user_name.to_string()));
+        self.getpwnam_params
+            .lock()
+            .unwrap()
+            .push(user_name.to_string());
+        self.getpwnam_results.borrow_mut().remove(0)
+    }
+    fn getpwuid(&self, uid: i32) -> Option<(i32, i32, PathBuf)> {
+        self.record(IdWrapperCall::Getpwuid(uid));
+        self.getpwuid_params.lock().unwrap().push(uid);
+        self.getpwuid_results.borrow_mut().remove(0)
+    }
+    fn umask(&self, mask: u32) -> u32 {
+        self.record(IdWrapperCall::Umask(mask));
+        self.umask_params.lock().unwrap().push(mask);
+        self.umask_results.borrow_mut().remove(0)
+    }
+    fn geteuid(&self) -> i32 {
+        self.record(IdWrapperCall::Geteuid);
+        self.geteuid_results.borrow_mut().remove(0)
+    }
+    fn getegid(&self) -> i32 {
+        self.record(IdWrapperCall::Getegid);
+        self.getegid_results.borrow_mut().remove(0)
+    }
+    fn seteuid(&self, uid: i32) -> i32 {
+        self.record(IdWrapperCall::Seteuid(uid));
+        self.seteuid_params.lock().unwrap().push(uid);
+        self.seteuid_results.borrow_mut().remove(0)
+    }
+    fn setegid(&self, gid: i32) -> i32 {
+        self.record(IdWrapperCall::Setegid(gid));
+        self.setegid_params.lock().unwrap().push(gid);
+        self.setegid_results.borrow_mut().remove(0)
+    }
 }
 
 #[allow(dead_code)]
@@ -103,6 +196,191 @@ impl IdWrapperMock {
         self.setgid_results.borrow_mut().push(gid_result);
         self
     }
+
+    pub fn setgroups_params(mut self, params: &Arc<Mutex<Vec<Vec<i32>>>>) -> Self {
+        self.setgroups_params = params.clone();
+        self
+    }
+
+    pub fn setgroups_result(self, setgroups_result: i32) -> Self {
+        self.setgroups_results.borrow_mut().push(setgroups_result);
+        self
+    }
+
+    pub fn getgroups_result(self, groups: Vec<i32>) -> Self {
+        self.getgroups_results.borrow_mut().push(groups);
+        self
+    }
+
+    pub fn getgrouplist_params(mut self, params: &Arc<Mutex<Vec<(i32, i32)>>>) -> Self {
+        self.getgrouplist_params = params.clone();
+        self
+    }
+
+    pub fn getgrouplist_result(self, result: Option<Vec<i32>>) -> Self {
+        self.getgrouplist_results.borrow_mut().push(result);
+        self
+    }
+
+    pub fn getpwnam_params(mut self, params: &Arc<Mutex<Vec<String>>>) -> Self {
+        self.getpwnam_params = params.clone();
+        self
+    }
+
+    pub fn getpwnam_result(self, result: Option<(i32, i32, PathBuf)>) -> Self {
+        self.getpwnam_results.borrow_mut().push(result);
+        self
+    }
+
+    pub fn getpwuid_params(mut self, params: &Arc<Mutex<Vec<i32>>>) -> Self {
+        self.getpwuid_params = params.clone();
+        self
+    }
+
+    pub fn getpwuid_result(self, result: Option<(i32, i32, PathBuf)>) -> Self {
+        self.getpwuid_results.borrow_mut().push(result);
+        self
+    }
+
+    pub fn umask_params(mut self, params: &Arc<Mutex<Vec<u32>>>) -> Self {
+        self.umask_params = params.clone();
+        self
+    }
+
+    pub fn umask_result(self, previous_mask: u32) -> Self {
+        self.umask_results.borrow_mut().push(previous_mask);
+        self
+    }
+
+    pub fn geteuid_result(self, euid: i32) -> Self {
+        self.geteuid_results.borrow_mut().push(euid);
+        self
+    }
+
+    pub fn getegid_result(self, egid: i32) -> Self {
+        self.getegid_results.borrow_mut().push(egid);
+        self
+    }
+
+    pub fn seteuid_params(mut self, params: &Arc<Mutex<Vec<i32>>>) -> Self {
+        self.seteuid_params = params.clone();
+        self
+    }
+
+    pub fn seteuid_result(self, seteuid_result: i32) -> Self {
+        self.seteuid_results.borrow_mut().push(seteuid_result);
+        self
+    }
+
+    pub fn setegid_params(mut self, params: &Arc<Mutex<Vec<i32>>>) -> Self {
+        self.setegid_params = params.clone();
+        self
+    }
+
+    pub fn setegid_result(self, setegid_result: i32) -> Self {
+        self.setegid_results.borrow_mut().push(setegid_result);
+        self
+    }
+
+    pub fn calls_params(mut self, params: &Arc<Mutex<Vec<IdWrapperCall>>>) -> Self {
+        self.calls = params.clone();
+        self
+    }
+
+    fn record(&self, call: IdWrapperCall) {
+        self.calls.lock().unwrap().push(call);
+    }
+}
+
+#[derive(Default)]
+pub struct CapabilityDropperMock {
+    drop_bounding_set_results: RefCell<Vec<Result<(), PrivilegeError>>>,
+    drop_capabilities_params: Arc<Mutex<Vec<()>>>,
+    drop_capabilities_results: RefCell<Vec<Result<(), PrivilegeError>>>,
+    calls: Arc<Mutex<Vec<IdWrapperCall>>>,
+}
+
+impl CapabilityDropper for CapabilityDropperMock {
+    fn drop_bounding_set(&self) -> Result<(), PrivilegeError> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(IdWrapperCall::DropBoundingSet);
+        self.drop_bounding_set_results.borrow_mut().remove(0)
+    }
+
+    fn drop_capabilities(&self) -> Result<(), PrivilegeError> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(IdWrapperCall::DropCapabilities);
+        self.drop_capabilities_params.lock().unwrap().push(());
+        self.drop_capabilities_results.borrow_mut().remove(0)
+    }
+}
+
+impl CapabilityDropperMock {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn drop_bounding_set_result(self, result: Result<(), PrivilegeError>) -> Self {
+        self.drop_bounding_set_results.borrow_mut().push(result);
+        self
+    }
+
+    pub fn drop_capabilities_params(mut self, params: &Arc<Mutex<Vec<()>>>) -> Self {
+        self.drop_capabilities_params = params.clone();
+        self
+    }
+
+    pub fn drop_capabilities_result(self, result: Result<(), PrivilegeError>) -> Self {
+        self.drop_capabilities_results.borrow_mut().push(result);
+        self
+    }
+
+    // Shares a log with IdWrapperMock::calls_params so the two can be ordered against each other
+    pub fn calls_params(mut self, params: &Arc<Mutex<Vec<IdWrapperCall>>>) -> Self {
+        self.calls = params.clone();
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct ChownerMock {
+    lchown_params: Arc<Mutex<Vec<(PathBuf, i32, i32)>>>,
+    lchown_results: RefCell<Vec<Result<(), i32>>>,
+}
+
+impl Chowner for ChownerMock {
+    fn lchown(&self, file: &Path, uid: i32, gid: i32) -> Result<(), i32> {
+        self.lchown_params
+            .lock()
+            .unwrap()
+            .push((file.to_path_buf(), uid, gid));
+        let mut results = self.lchown_results.borrow_mut();
+        if results.is_empty() {
+            Ok(())
+        } else {
+            results.remove(0)
+        }
+    }
+}
+
+impl ChownerMock {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn lchown_params(mut self, params: &Arc<Mutex<Vec<(PathBuf, i32, i32)>>>) -> Self {
+        self.lchown_params = params.clone();
+        self
+    }
+
+    pub fn lchown_result(self, result: Result<(), i32>) -> Self {
+        self.lchown_results.borrow_mut().push(result);
+        self
+    }
 }
 
 pub struct DirsWrapperMock {
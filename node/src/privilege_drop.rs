@@ -10,11 +10,30 @@ extern "C" {
     pub fn getgid() -> i32;
     pub fn setuid(uid: i32) -> i32;
     pub fn setgid(gid: i32) -> i32;
+    pub fn geteuid() -> i32;
+    pub fn getegid() -> i32;
+    pub fn seteuid(uid: i32) -> i32;
+    pub fn setegid(gid: i32) -> i32;
+}
+
+#[cfg(target_os = "linux")]
+extern "C" {
+    pub fn setgroups(size: usize, list: *const i32) -> i32;
+}
+
+#[cfg(target_os = "macos")]
+extern "C" {
+    pub fn setgroups(ngroups: i32, gidset: *const i32) -> i32;
 }
 
 use crate::bootstrapper::RealUser;
+use masq_lib::shared_schema::ConfiguratorError;
+#[cfg(not(target_os = "windows"))]
+use nix::unistd::{Gid, Uid};
 #[cfg(not(target_os = "windows"))]
 use nix::NixPath;
+use std::fmt;
+use std::fmt::{Display, Formatter};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
@@ -23,10 +42,24 @@ pub trait IdWrapper: Send {
     fn getgid(&self) -> i32;
     fn setuid(&self, uid: i32) -> i32;
     fn setgid(&self, gid: i32) -> i32;
+    fn setgroups(&self, gids: &[i32]) -> i32;
+    fn getgroups(&self) -> Vec<i32>;
+    fn getgrouplist(&self, uid: i32, gid: i32) -> Option<Vec<i32>>;
+    fn getpwnam(&self, user_name: &str) -> Option<(i32, i32, PathBuf)>;
+    fn getpwuid(&self, uid: i32) -> Option<(i32, i32, PathBuf)>;
+    fn umask(&self, mask: u32) -> u32;
+    fn geteuid(&self) -> i32;
+    fn getegid(&self) -> i32;
+    fn seteuid(&self, uid: i32) -> i32;
+    fn setegid(&self, gid: i32) -> i32;
 }
 
 pub struct IdWrapperReal;
 
+// Linux allows 65536 supplementary groups; nobody has more than that
+#[cfg(not(target_os = "windows"))]
+const MAX_GROUPS: usize = 65536;
+
 #[cfg(not(target_os = "windows"))]
 impl IdWrapper for IdWrapperReal {
     fn getuid(&self) -> i32 {
@@ -41,6 +74,75 @@ impl IdWrapper for IdWrapperReal {
     fn setgid(&self, gid: i32) -> i32 {
         unsafe { setgid(gid) }
     }
+    fn setgroups(&self, gids: &[i32]) -> i32 {
+        unsafe { setgroups(gids.len() as _, gids.as_ptr()) }
+    }
+    fn getgroups(&self) -> Vec<i32> {
+        let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+        if count <= 0 {
+            return vec![];
+        }
+        let mut groups = vec![0; count as usize];
+        let count = unsafe { libc::getgroups(count, groups.as_mut_ptr()) };
+        groups.truncate(count.max(0) as usize);
+        groups.into_iter().map(|group| group as i32).collect()
+    }
+    // None if the user can't be found by uid, which is what getgrouplist needs to be given
+    fn getgrouplist(&self, uid: i32, gid: i32) -> Option<Vec<i32>> {
+        let user = nix::unistd::User::from_uid(Uid::from_raw(uid as u32)).ok()??;
+        let user_name = std::ffi::CString::new(user.name).ok()?;
+        let mut capacity = 32;
+        loop {
+            let mut groups = vec![0; capacity];
+            let mut count = capacity as libc::c_int;
+            let result = unsafe {
+                libc::getgrouplist(
+                    user_name.as_ptr(),
+                    gid as _,
+                    groups.as_mut_ptr(),
+                    &mut count,
+                )
+            };
+            if result >= 0 {
+                groups.truncate(count.max(0) as usize);
+                return Some(groups.into_iter().map(|group| group as i32).collect());
+            }
+            if capacity >= MAX_GROUPS {
+                return None;
+            }
+            capacity = (count.max(0) as usize).max(capacity * 2).min(MAX_GROUPS);
+        }
+    }
+    fn getpwnam(&self, user_name: &str) -> Option<(i32, i32, PathBuf)> {
+        match nix::unistd::User::from_name(user_name) {
+            Ok(Some(user)) => {
+                let (uid, gid) = (user.uid.as_raw() as i32, user.gid.as_raw() as i32);
+                Some((uid, gid, user.dir))
+            }
+            _ => None,
+        }
+    }
+    fn getpwuid(&self, uid: i32) -> Option<(i32, i32, PathBuf)> {
+        match nix::unistd::User::from_uid(Uid::from_raw(uid as u32)) {
+            Ok(Some(user)) => Some((uid, user.gid.as_raw() as i32, user.dir)),
+            _ => None,
+        }
+    }
+    fn umask(&self, mask: u32) -> u32 {
+        unsafe { libc::umask(mask as libc::mode_t) as u32 }
+    }
+    fn geteuid(&self) -> i32 {
+        unsafe { geteuid() }
+    }
+    fn getegid(&self) -> i32 {
+        unsafe { getegid() }
+    }
+    fn seteuid(&self, uid: i32) -> i32 {
+        unsafe { seteuid(uid) }
+    }
+    fn setegid(&self, gid: i32) -> i32 {
+        unsafe { setegid(gid) }
+    }
 }
 
 #[cfg(target_os = "windows")]
@@ -57,30 +159,197 @@ impl IdWrapper for IdWrapperReal {
     fn setgid(&self, _gid: i32) -> i32 {
         -1
     }
+    fn setgroups(&self, _gids: &[i32]) -> i32 {
+        -1
+    }
+    fn getgroups(&self) -> Vec<i32> {
+        vec![]
+    }
+    fn getgrouplist(&self, _uid: i32, _gid: i32) -> Option<Vec<i32>> {
+        None
+    }
+    fn getpwnam(&self, _user_name: &str) -> Option<(i32, i32, PathBuf)> {
+        None
+    }
+    // There's no password database; the profile directory is the closest thing to a home
+    fn getpwuid(&self, uid: i32) -> Option<(i32, i32, PathBuf)> {
+        std::env::var("USERPROFILE")
+            .ok()
+            .map(|profile| (uid, -1, PathBuf::from(profile)))
+    }
+    fn umask(&self, _mask: u32) -> u32 {
+        0
+    }
+    fn geteuid(&self) -> i32 {
+        -1
+    }
+    fn getegid(&self) -> i32 {
+        -1
+    }
+    fn seteuid(&self, _uid: i32) -> i32 {
+        -1
+    }
+    fn setegid(&self, _gid: i32) -> i32 {
+        -1
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PrivilegeError {
+    SetgroupsFailed(i32),
+    SetgidFailed(i32),
+    SetuidFailed(i32),
+    StillRoot,
+    ChownFailed { path: PathBuf, code: i32 },
+    CapabilityDropFailed(String),
+}
+
+impl Display for PrivilegeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PrivilegeError::SetgroupsFailed(code) => {
+                write!(f, "Error code {} resetting supplementary groups", code)
+            }
+            PrivilegeError::SetgidFailed(code) => {
+                write!(f, "Error code {} resetting group id", code)
+            }
+            PrivilegeError::SetuidFailed(code) => {
+                write!(f, "Error code {} resetting user id", code)
+            }
+            PrivilegeError::StillRoot => write!(f, "Attempt to drop privileges failed: still root"),
+            PrivilegeError::ChownFailed { path, code } => {
+                write!(f, "Error code {} changing ownership of {:?}", code, path)
+            }
+            PrivilegeError::CapabilityDropFailed(msg) => {
+                write!(f, "Couldn't drop capabilities: {}", msg)
+            }
+        }
+    }
+}
+
+impl From<PrivilegeError> for ConfiguratorError {
+    fn from(error: PrivilegeError) -> Self {
+        ConfiguratorError::required("real-user", &error.to_string())
+    }
 }
 
 pub trait PrivilegeDropper: Send {
-    fn drop_privileges(&self, real_user: &RealUser);
-    fn chown(&self, file: &Path, real_user: &RealUser);
+    fn drop_privileges(&self, real_user: &RealUser) -> Result<(), PrivilegeError>;
+    fn chown(&self, file: &Path, real_user: &RealUser) -> Result<(), PrivilegeError>;
+    fn chown_recursive(&self, dir: &Path, real_user: &RealUser) -> Result<(), PrivilegeError>;
+    fn chown_all(&self, files: &[PathBuf], real_user: &RealUser) -> Result<(), PrivilegeError>;
+    fn set_umask(&self, mask: u32);
+    fn verify_dropped(&self, real_user: &RealUser) -> Result<(), String>;
     fn expect_privilege(&self, privilege_expected: bool) -> bool;
 }
 
+// Nothing the Node writes after bootstrapping is any business of other users
+pub const SECURE_UMASK: u32 = 0o077;
+
+pub trait CapabilityDropper: Send {
+    fn drop_bounding_set(&self) -> Result<(), PrivilegeError>;
+    fn drop_capabilities(&self) -> Result<(), PrivilegeError>;
+}
+
+pub struct CapabilityDropperReal;
+
+#[cfg(all(target_os = "linux", feature = "caps"))]
+impl CapabilityDropper for CapabilityDropperReal {
+    // The bounding set can only be shrunk while we still hold CAP_SETPCAP, and setuid away from
+    // root takes that with it, so this has to happen first.
+    fn drop_bounding_set(&self) -> Result<(), PrivilegeError> {
+        use caps::{CapSet, Capability};
+        if caps::has_cap(None, CapSet::Effective, Capability::CAP_SETPCAP).map_err(Self::failure)? {
+            caps::clear(None, CapSet::Bounding).map_err(Self::failure)?;
+        }
+        Ok(())
+    }
+
+    fn drop_capabilities(&self) -> Result<(), PrivilegeError> {
+        use caps::CapSet;
+        vec![
+            CapSet::Ambient,
+            CapSet::Inheritable,
+            CapSet::Effective,
+            CapSet::Permitted,
+        ]
+        .into_iter()
+        .try_for_each(|cap_set| caps::clear(None, cap_set).map_err(Self::failure))
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "caps"))]
+impl CapabilityDropperReal {
+    fn failure(e: caps::errors::CapsError) -> PrivilegeError {
+        PrivilegeError::CapabilityDropFailed(e.to_string())
+    }
+}
+
+#[cfg(not(all(target_os = "linux", feature = "caps")))]
+impl CapabilityDropper for CapabilityDropperReal {
+    fn drop_bounding_set(&self) -> Result<(), PrivilegeError> {
+        Ok(())
+    }
+
+    fn drop_capabilities(&self) -> Result<(), PrivilegeError> {
+        Ok(())
+    }
+}
+
+// Changes the owner of the file itself, never of what a symlink points to
+pub trait Chowner: Send {
+    fn lchown(&self, file: &Path, uid: i32, gid: i32) -> Result<(), i32>;
+}
+
+pub struct ChownerReal;
+
+#[cfg(not(target_os = "windows"))]
+impl Chowner for ChownerReal {
+    fn lchown(&self, file: &Path, uid: i32, gid: i32) -> Result<(), i32> {
+        nix::unistd::fchownat(
+            None,
+            file,
+            Some(Uid::from_raw(uid as u32)),
+            Some(Gid::from_raw(gid as u32)),
+            nix::unistd::FchownatFlags::NoFollowSymlink,
+        )
+        .map_err(|e| e as i32)
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Chowner for ChownerReal {
+    fn lchown(&self, _file: &Path, _uid: i32, _gid: i32) -> Result<(), i32> {
+        // Windows ownership goes by SID; see run_windows_chown
+        Ok(())
+    }
+}
+
 pub struct PrivilegeDropperReal {
     id_wrapper: Box<dyn IdWrapper>,
+    capability_dropper: Box<dyn CapabilityDropper>,
+    chowner: Box<dyn Chowner>,
 }
 
 impl PrivilegeDropper for PrivilegeDropperReal {
     #[cfg(not(target_os = "windows"))]
-    fn drop_privileges(&self, real_user: &RealUser) {
+    fn drop_privileges(&self, real_user: &RealUser) -> Result<(), PrivilegeError> {
+        self.set_umask(SECURE_UMASK);
+        self.capability_dropper.drop_bounding_set()?;
         if self.id_wrapper.getgid() == 0 {
-            let gid_result = self
-                .id_wrapper
-                .setgid(real_user.gid_opt.expect("Group-ID logic not working"));
+            let uid = real_user.uid_opt.expect("User-ID logic not working");
+            let gid = real_user.gid_opt.expect("Group-ID logic not working");
+            // Root's supplementary groups would otherwise survive the drop
+            let groups_result = self.id_wrapper.setgroups(&self.user_groups(uid, gid));
+            if groups_result != 0 {
+                return Err(PrivilegeError::SetgroupsFailed(groups_result));
+            }
+            let gid_result = self.id_wrapper.setgid(gid);
             if gid_result != 0 {
-                panic!("Error code {} resetting group id", gid_result)
+                return Err(PrivilegeError::SetgidFailed(gid_result));
             }
             if self.id_wrapper.getgid() == 0 {
-                panic!("Attempt to drop group privileges failed: still root")
+                return Err(PrivilegeError::StillRoot);
             }
         }
 
@@ -89,52 +358,99 @@ impl PrivilegeDropper for PrivilegeDropperReal {
                 .id_wrapper
                 .setuid(real_user.uid_opt.expect("User-ID logic not working"));
             if uid_result != 0 {
-                panic!("Error code {} resetting user id", uid_result)
+                return Err(PrivilegeError::SetuidFailed(uid_result));
             }
             if self.id_wrapper.getuid() == 0 {
-                panic!("Attempt to drop user privileges failed: still root")
+                return Err(PrivilegeError::StillRoot);
             }
         }
+        self.capability_dropper.drop_capabilities()
     }
 
     #[cfg(target_os = "windows")]
-    fn drop_privileges(&self, _real_user: &RealUser) {
+    fn drop_privileges(&self, _real_user: &RealUser) -> Result<(), PrivilegeError> {
         // Windows doesn't need drop_privileges: it runs as administrator the whole way
+        Ok(())
     }
 
     #[cfg(not(target_os = "windows"))]
-    fn chown(&self, file: &Path, real_user: &RealUser) {
-        // Don't bother trying if the file is blank
-        if file.is_empty() {
-            return;
-        }
+    fn chown(&self, file: &Path, real_user: &RealUser) -> Result<(), PrivilegeError> {
+        self.run_chown(file, real_user, false)
+    }
+
+    #[cfg(target_os = "windows")]
+    fn chown(&self, file: &Path, real_user: &RealUser) -> Result<(), PrivilegeError> {
+        Self::run_windows_chown(file, real_user, false)
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn chown_recursive(&self, dir: &Path, real_user: &RealUser) -> Result<(), PrivilegeError> {
+        self.run_chown(dir, real_user, true)
+    }
+
+    #[cfg(target_os = "windows")]
+    fn chown_recursive(&self, dir: &Path, real_user: &RealUser) -> Result<(), PrivilegeError> {
+        Self::run_windows_chown(dir, real_user, true)
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn chown_all(&self, files: &[PathBuf], real_user: &RealUser) -> Result<(), PrivilegeError> {
         // Don't bother trying to chown if we're not root
         if (self.id_wrapper.getgid() == 0) && (self.id_wrapper.getuid() == 0) {
-            let mut command = std::process::Command::new("chown");
-            let args = vec![
-                format!(
-                    "{}:{}",
-                    real_user.uid_opt.expect("User-ID logic not working"),
-                    real_user.gid_opt.expect("Group-ID logic not working")
-                ),
-                format!("{}", file.display()),
-            ];
-            command.args(args.clone());
-            let exit_status = command
-                .status()
-                .expect("Could not retrieve status from chown command");
-            if !exit_status.success() {
-                panic!(
-                    "As root, couldn't chown {:?} to {:?}: exit code {:?}",
-                    file, args, exit_status
-                );
-            }
+            let (uid, gid) = Self::target_ids(real_user);
+            files
+                .iter()
+                .filter(|file| !file.as_os_str().is_empty())
+                .try_for_each(|file| self.chown_path(file, uid, gid, false))
+        } else {
+            Ok(())
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    fn chown_all(&self, files: &[PathBuf], real_user: &RealUser) -> Result<(), PrivilegeError> {
+        files
+            .iter()
+            .try_for_each(|file| Self::run_windows_chown(file, real_user, false))
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn set_umask(&self, mask: u32) {
+        self.id_wrapper.umask(mask);
+    }
+
+    #[cfg(target_os = "windows")]
+    fn set_umask(&self, _mask: u32) {
+        // Windows has no umask: file permissions are governed by ACLs
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn verify_dropped(&self, real_user: &RealUser) -> Result<(), String> {
+        let uid = real_user.uid_opt.expect("User-ID logic not working");
+        let gid = real_user.gid_opt.expect("Group-ID logic not working");
+        let checks = vec![
+            ("user id", self.id_wrapper.getuid(), uid),
+            ("effective user id", self.id_wrapper.geteuid(), uid),
+            ("group id", self.id_wrapper.getgid(), gid),
+            ("effective group id", self.id_wrapper.getegid(), gid),
+        ];
+        match checks
+            .into_iter()
+            .find(|(_, actual, expected)| (*actual == 0) || (actual != expected))
+        {
+            None => self.verify_groups_dropped(uid, gid),
+            Some((name, 0, _)) => Err(format!("Privileges not dropped: {} is still root", name)),
+            Some((name, actual, expected)) => Err(format!(
+                "Privileges not dropped: {} is {} instead of {}",
+                name, actual, expected
+            )),
         }
     }
 
     #[cfg(target_os = "windows")]
-    fn chown(&self, _file: &Path, _real_user: &RealUser) {
-        // Windows doesn't need chown: it runs as administrator the whole way
+    fn verify_dropped(&self, _real_user: &RealUser) -> Result<(), String> {
+        // Windows doesn't drop privileges: it runs as administrator the whole way
+        Ok(())
     }
 
     #[cfg(target_os = "windows")]
@@ -157,8 +473,176 @@ impl PrivilegeDropperReal {
     pub fn new() -> PrivilegeDropperReal {
         PrivilegeDropperReal {
             id_wrapper: Box::new(IdWrapperReal {}),
+            capability_dropper: Box::new(CapabilityDropperReal {}),
+            chowner: Box::new(ChownerReal {}),
+        }
+    }
+
+    // A user missing from the user database still gets to keep its primary group
+    fn user_groups(&self, uid: i32, gid: i32) -> Vec<i32> {
+        self.id_wrapper
+            .getgrouplist(uid, gid)
+            .unwrap_or_else(|| vec![gid])
+    }
+
+    fn verify_groups_dropped(&self, uid: i32, gid: i32) -> Result<(), String> {
+        let user_groups = self.user_groups(uid, gid);
+        match self
+            .id_wrapper
+            .getgroups()
+            .into_iter()
+            .find(|group| (*group != gid) && !user_groups.contains(group))
+        {
+            None => Ok(()),
+            Some(0) => {
+                Err("Privileges not dropped: root group is still a supplementary group".to_string())
+            }
+            Some(group) => Err(format!(
+                "Privileges not dropped: supplementary group {} doesn't belong to the real user",
+                group
+            )),
         }
     }
+
+    #[cfg(not(target_os = "windows"))]
+    fn run_chown(
+        &self,
+        file: &Path,
+        real_user: &RealUser,
+        recursive: bool,
+    ) -> Result<(), PrivilegeError> {
+        // Don't bother trying if the file is blank
+        if file.is_empty() {
+            return Ok(());
+        }
+        // Don't bother trying to chown if we're not root
+        if (self.id_wrapper.getgid() == 0) && (self.id_wrapper.getuid() == 0) {
+            let (uid, gid) = Self::target_ids(real_user);
+            self.chown_path(file, uid, gid, recursive)
+        } else {
+            Ok(())
+        }
+    }
+
+    // Unlike drop_privileges, this only steps down the effective ids, so root can be regained
+    // once the work is done: for example, to touch files as the real user before binding
+    // privileged ports.
+    #[cfg(not(target_os = "windows"))]
+    pub fn with_effective_privileges_dropped<T>(
+        &self,
+        real_user: &RealUser,
+        work: impl FnOnce() -> T,
+    ) -> Result<T, PrivilegeError> {
+        let saved_euid = self.id_wrapper.geteuid();
+        let saved_egid = self.id_wrapper.getegid();
+        // The group has to change first, while we're still allowed to change it
+        let egid_result = self
+            .id_wrapper
+            .setegid(real_user.gid_opt.expect("Group-ID logic not working"));
+        if egid_result != 0 {
+            return Err(PrivilegeError::SetgidFailed(egid_result));
+        }
+        let euid_result = self
+            .id_wrapper
+            .seteuid(real_user.uid_opt.expect("User-ID logic not working"));
+        if euid_result != 0 {
+            self.id_wrapper.setegid(saved_egid);
+            return Err(PrivilegeError::SetuidFailed(euid_result));
+        }
+        let result = work();
+        // Both are attempted even if the first fails, so as little as possible stays dropped
+        let euid_result = self.id_wrapper.seteuid(saved_euid);
+        let egid_result = self.id_wrapper.setegid(saved_egid);
+        if euid_result != 0 {
+            return Err(PrivilegeError::SetuidFailed(euid_result));
+        }
+        if egid_result != 0 {
+            return Err(PrivilegeError::SetgidFailed(egid_result));
+        }
+        Ok(result)
+    }
+
+    #[cfg(target_os = "windows")]
+    fn run_windows_chown(
+        file: &Path,
+        real_user: &RealUser,
+        recursive: bool,
+    ) -> Result<(), PrivilegeError> {
+        match &real_user.sid_opt {
+            // Without an account to hand things over to, the administrator keeps them
+            None => Ok(()),
+            Some(_) if file.as_os_str().is_empty() => Ok(()),
+            Some(sid) => Self::set_owner_path(file, sid, recursive),
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    fn set_owner_path(file: &Path, sid: &str, recursive: bool) -> Result<(), PrivilegeError> {
+        let chown_failed = |path: &Path, code: i32| PrivilegeError::ChownFailed {
+            path: path.to_path_buf(),
+            code,
+        };
+        windows_ownership::set_owner(file, sid).map_err(|code| chown_failed(file, code))?;
+        if recursive && file.is_dir() {
+            let entries = std::fs::read_dir(file)
+                .map_err(|e| chown_failed(file, e.raw_os_error().unwrap_or(-1)))?;
+            for entry_result in entries {
+                let entry =
+                    entry_result.map_err(|e| chown_failed(file, e.raw_os_error().unwrap_or(-1)))?;
+                let descend = match entry.file_type() {
+                    Ok(file_type) => file_type.is_dir(),
+                    Err(_) => false,
+                };
+                Self::set_owner_path(&entry.path(), sid, descend)?;
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn target_ids(real_user: &RealUser) -> (Uid, Gid) {
+        (
+            Uid::from_raw(real_user.uid_opt.expect("User-ID logic not working") as u32),
+            Gid::from_raw(real_user.gid_opt.expect("Group-ID logic not working") as u32),
+        )
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn chown_path(
+        &self,
+        file: &Path,
+        uid: Uid,
+        gid: Gid,
+        recursive: bool,
+    ) -> Result<(), PrivilegeError> {
+        let chown_failed = |path: &Path, code: i32| PrivilegeError::ChownFailed {
+            path: path.to_path_buf(),
+            code,
+        };
+        // The real user can plant symlinks here, and root must not follow them
+        self.chowner
+            .lchown(file, uid.as_raw() as i32, gid.as_raw() as i32)
+            .map_err(|code| chown_failed(file, code))?;
+        let is_real_dir = match std::fs::symlink_metadata(file) {
+            Ok(metadata) => metadata.is_dir(),
+            Err(_) => false,
+        };
+        if recursive && is_real_dir {
+            let entries = std::fs::read_dir(file)
+                .map_err(|e| chown_failed(file, e.raw_os_error().unwrap_or(-1)))?;
+            for entry_result in entries {
+                let entry =
+                    entry_result.map_err(|e| chown_failed(file, e.raw_os_error().unwrap_or(-1)))?;
+                // file_type() doesn't follow symlinks, so links to directories aren't descended
+                let descend = match entry.file_type() {
+                    Ok(file_type) => file_type.is_dir(),
+                    Err(_) => false,
+                };
+                self.chown_path(&entry.path(), uid, gid, descend)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Default for PrivilegeDropperReal {
@@ -167,6 +651,86 @@ impl Default for PrivilegeDropperReal {
     }
 }
 
+#[cfg(target_os = "windows")]
+mod windows_ownership {
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use std::ptr::null_mut;
+    use winapi::shared::winerror::ERROR_SUCCESS;
+    use winapi::um::accctrl::SE_FILE_OBJECT;
+    use winapi::um::aclapi::{GetNamedSecurityInfoW, SetNamedSecurityInfoW};
+    use winapi::um::errhandlingapi::GetLastError;
+    use winapi::um::sddl::{ConvertSidToStringSidW, ConvertStringSidToSidW};
+    use winapi::um::winbase::LocalFree;
+    use winapi::um::winnt::{OWNER_SECURITY_INFORMATION, PSECURITY_DESCRIPTOR, PSID};
+
+    fn to_wide(s: &OsStr) -> Vec<u16> {
+        s.encode_wide().chain(std::iter::once(0)).collect()
+    }
+
+    pub fn set_owner(file: &Path, sid: &str) -> Result<(), i32> {
+        let sid_wide = to_wide(OsStr::new(sid));
+        let mut owner: PSID = null_mut();
+        if unsafe { ConvertStringSidToSidW(sid_wide.as_ptr(), &mut owner) } == 0 {
+            return Err(unsafe { GetLastError() } as i32);
+        }
+        let mut path_wide = to_wide(file.as_os_str());
+        let result = unsafe {
+            SetNamedSecurityInfoW(
+                path_wide.as_mut_ptr(),
+                SE_FILE_OBJECT,
+                OWNER_SECURITY_INFORMATION,
+                owner,
+                null_mut(),
+                null_mut(),
+                null_mut(),
+            )
+        };
+        unsafe { LocalFree(owner as _) };
+        if result == ERROR_SUCCESS {
+            Ok(())
+        } else {
+            Err(result as i32)
+        }
+    }
+
+    pub fn get_owner(file: &Path) -> Result<String, i32> {
+        let path_wide = to_wide(file.as_os_str());
+        let mut owner: PSID = null_mut();
+        let mut descriptor: PSECURITY_DESCRIPTOR = null_mut();
+        let result = unsafe {
+            GetNamedSecurityInfoW(
+                path_wide.as_ptr(),
+                SE_FILE_OBJECT,
+                OWNER_SECURITY_INFORMATION,
+                &mut owner,
+                null_mut(),
+                null_mut(),
+                null_mut(),
+                &mut descriptor,
+            )
+        };
+        if result != ERROR_SUCCESS {
+            return Err(result as i32);
+        }
+        let mut sid_wide = null_mut();
+        let outcome = if unsafe { ConvertSidToStringSidW(owner, &mut sid_wide) } == 0 {
+            Err(unsafe { GetLastError() } as i32)
+        } else {
+            let len = (0isize..)
+                .take_while(|&offset| unsafe { *sid_wide.offset(offset) } != 0)
+                .count();
+            let sid =
+                String::from_utf16_lossy(unsafe { std::slice::from_raw_parts(sid_wide, len) });
+            unsafe { LocalFree(sid_wide as _) };
+            Ok(sid)
+        };
+        unsafe { LocalFree(descriptor as _) };
+        outcome
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(unreachable_code)]
@@ -174,63 +738,100 @@ mod tests {
     #![allow(unused_imports)]
     use super::*;
     use crate::node_configurator::DirsWrapperReal;
-    use crate::node_test_utils::IdWrapperMock;
+    use crate::node_test_utils::{
+        CapabilityDropperMock, ChownerMock, IdWrapperCall, IdWrapperMock,
+    };
+    use masq_lib::test_utils::utils::ensure_node_home_directory_exists;
+    #[cfg(not(target_os = "windows"))]
+    use std::os::unix::fs::MetadataExt;
     use std::sync::{Arc, Mutex};
 
     #[cfg(not(target_os = "windows"))]
     #[test]
-    #[should_panic(expected = "Error code 47 resetting group id")]
-    fn gid_error_code_causes_panic() {
+    fn gid_error_code_is_reported() {
         let id_wrapper = IdWrapperMock::new()
+            .umask_result(0o022)
             .getuid_result(0)
             .getgid_result(0)
             .getuid_result(0)
             .getgid_result(0)
+            .getgrouplist_result(None)
+            .setgroups_result(0)
             .setgid_result(47);
         let mut subject = PrivilegeDropperReal::new();
         subject.id_wrapper = Box::new(id_wrapper);
 
-        subject.drop_privileges(&RealUser::new(None, None, None).populate(&DirsWrapperReal {}));
+        let result =
+            subject.drop_privileges(&RealUser::new(None, None, None).populate(&DirsWrapperReal {}));
+
+        assert_eq!(result, Err(PrivilegeError::SetgidFailed(47)));
     }
 
     #[cfg(not(target_os = "windows"))]
     #[test]
-    #[should_panic(expected = "Error code 47 resetting user id")]
-    fn uid_error_code_causes_panic() {
+    fn setgroups_error_code_is_reported() {
         let id_wrapper = IdWrapperMock::new()
+            .umask_result(0o022)
             .getuid_result(0)
             .getgid_result(0)
+            .getgrouplist_result(None)
+            .setgroups_result(47);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+
+        let result = subject.drop_privileges(&RealUser::new(Some(111), Some(222), None));
+
+        assert_eq!(result, Err(PrivilegeError::SetgroupsFailed(47)));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn uid_error_code_is_reported() {
+        let id_wrapper = IdWrapperMock::new()
+            .umask_result(0o022)
+            .getuid_result(0)
+            .getgid_result(0)
+            .getgrouplist_result(None)
+            .setgroups_result(0)
             .setgid_result(0)
             .getgid_result(202)
             .setuid_result(47);
         let mut subject = PrivilegeDropperReal::new();
         subject.id_wrapper = Box::new(id_wrapper);
 
-        subject.drop_privileges(&RealUser::new(Some(111), Some(222), None));
+        let result = subject.drop_privileges(&RealUser::new(Some(111), Some(222), None));
+
+        assert_eq!(result, Err(PrivilegeError::SetuidFailed(47)));
     }
 
     #[cfg(not(target_os = "windows"))]
     #[test]
-    #[should_panic(expected = "Attempt to drop group privileges failed: still root")]
-    fn final_gid_of_0_causes_panic() {
+    fn final_gid_of_0_is_reported() {
         let id_wrapper = IdWrapperMock::new()
+            .umask_result(0o022)
             .getuid_result(0)
             .getgid_result(0)
+            .getgrouplist_result(None)
+            .setgroups_result(0)
             .setgid_result(0)
             .getgid_result(0);
         let mut subject = PrivilegeDropperReal::new();
         subject.id_wrapper = Box::new(id_wrapper);
 
-        subject.drop_privileges(&RealUser::new(Some(111), Some(222), None));
+        let result = subject.drop_privileges(&RealUser::new(Some(111), Some(222), None));
+
+        assert_eq!(result, Err(PrivilegeError::StillRoot));
     }
 
     #[cfg(not(target_os = "windows"))]
     #[test]
-    #[should_panic(expected = "Attempt to drop user privileges failed: still root")]
-    fn final_uid_of_0_causes_panic() {
+    fn final_uid_of_0_is_reported() {
         let id_wrapper = IdWrapperMock::new()
+            .umask_result(0o022)
             .getuid_result(0)
             .getgid_result(0)
+            .getgrouplist_result(None)
+            .setgroups_result(0)
             .setgid_result(0)
             .getgid_result(202)
             .setuid_result(0)
@@ -238,7 +839,9 @@ mod tests {
         let mut subject = PrivilegeDropperReal::new();
         subject.id_wrapper = Box::new(id_wrapper);
 
-        subject.drop_privileges(&RealUser::new(Some(111), Some(222), None));
+        let result = subject.drop_privileges(&RealUser::new(Some(111), Some(222), None));
+
+        assert_eq!(result, Err(PrivilegeError::StillRoot));
     }
 
     #[cfg(not(target_os = "windows"))]
@@ -246,28 +849,154 @@ mod tests {
     fn works_okay_with_real_user() {
         let setuid_params_arc = Arc::new(Mutex::new(vec![]));
         let setgid_params_arc = Arc::new(Mutex::new(vec![]));
+        let setgroups_params_arc = Arc::new(Mutex::new(vec![]));
+        let getgrouplist_params_arc = Arc::new(Mutex::new(vec![]));
         let id_wrapper = IdWrapperMock::new()
+            .umask_result(0o022)
             .getuid_result(0)
             .getgid_result(0)
             .setuid_params(&setuid_params_arc)
             .setgid_params(&setgid_params_arc)
+            .setgroups_params(&setgroups_params_arc)
+            .getgrouplist_params(&getgrouplist_params_arc)
             .setuid_result(0)
             .setgid_result(0)
+            .getgrouplist_result(Some(vec![202, 303, 404]))
+            .setgroups_result(0)
             .getuid_result(101)
             .getgid_result(202);
         let mut subject = PrivilegeDropperReal::new();
         subject.id_wrapper = Box::new(id_wrapper);
 
-        subject.drop_privileges(&RealUser::new(
+        let result = subject.drop_privileges(&RealUser::new(
             Some(101),
             Some(202),
             Some("/home/user".into()),
         ));
 
+        assert_eq!(result, Ok(()));
         let setuid_params = setuid_params_arc.lock().unwrap();
         assert_eq!(*setuid_params, vec![101]);
         let setgid_params = setgid_params_arc.lock().unwrap();
         assert_eq!(*setgid_params, vec![202]);
+        let setgroups_params = setgroups_params_arc.lock().unwrap();
+        assert_eq!(*setgroups_params, vec![vec![202, 303, 404]]);
+        let getgrouplist_params = getgrouplist_params_arc.lock().unwrap();
+        assert_eq!(*getgrouplist_params, vec![(101, 202)]);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn primary_group_alone_is_kept_for_user_missing_from_user_database() {
+        let setgroups_params_arc = Arc::new(Mutex::new(vec![]));
+        let id_wrapper = IdWrapperMock::new()
+            .umask_result(0o022)
+            .getuid_result(0)
+            .getgid_result(0)
+            .setgroups_params(&setgroups_params_arc)
+            .setuid_result(0)
+            .setgid_result(0)
+            .getgrouplist_result(None)
+            .setgroups_result(0)
+            .getuid_result(101)
+            .getgid_result(202);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+
+        let result = subject.drop_privileges(&RealUser::new(Some(101), Some(202), None));
+
+        assert_eq!(result, Ok(()));
+        let setgroups_params = setgroups_params_arc.lock().unwrap();
+        assert_eq!(*setgroups_params, vec![vec![202]]);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn groups_are_dropped_before_the_user() {
+        let calls_arc = Arc::new(Mutex::new(vec![]));
+        let id_wrapper = IdWrapperMock::new()
+            .calls_params(&calls_arc)
+            .umask_result(0o022)
+            .getuid_result(0)
+            .getgid_result(0)
+            .setuid_result(0)
+            .setgid_result(0)
+            .getgrouplist_result(None)
+            .setgroups_result(0)
+            .getuid_result(101)
+            .getgid_result(202);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+
+        let result = subject.drop_privileges(&RealUser::new(Some(101), Some(202), None));
+
+        assert_eq!(result, Ok(()));
+        let calls = calls_arc.lock().unwrap();
+        // Once the uid is gone, so is the right to change groups
+        assert_eq!(
+            *calls,
+            vec![
+                IdWrapperCall::Umask(SECURE_UMASK),
+                IdWrapperCall::Getgid,
+                IdWrapperCall::Getgrouplist(101, 202),
+                IdWrapperCall::Setgroups(vec![202]),
+                IdWrapperCall::Setgid(202),
+                IdWrapperCall::Getgid,
+                IdWrapperCall::Getuid,
+                IdWrapperCall::Setuid(101),
+                IdWrapperCall::Getuid,
+            ]
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn works_okay_with_real_user_looked_up_by_name() {
+        let getpwnam_params_arc = Arc::new(Mutex::new(vec![]));
+        let lookup_wrapper = IdWrapperMock::new()
+            .getpwnam_params(&getpwnam_params_arc)
+            .getpwnam_result(Some((1234, 5678, "/home/masq".into())));
+        let setuid_params_arc = Arc::new(Mutex::new(vec![]));
+        let setgid_params_arc = Arc::new(Mutex::new(vec![]));
+        let id_wrapper = IdWrapperMock::new()
+            .umask_result(0o022)
+            .getuid_result(0)
+            .getgid_result(0)
+            .setuid_params(&setuid_params_arc)
+            .setgid_params(&setgid_params_arc)
+            .setuid_result(0)
+            .setgid_result(0)
+            .getgrouplist_result(None)
+            .setgroups_result(0)
+            .getuid_result(1234)
+            .getgid_result(5678);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+        let real_user = RealUser::from_user_name(&lookup_wrapper, "masq").unwrap();
+
+        let result = subject.drop_privileges(&real_user);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            real_user,
+            RealUser::new(Some(1234), Some(5678), Some("/home/masq".into()))
+        );
+        let getpwnam_params = getpwnam_params_arc.lock().unwrap();
+        assert_eq!(*getpwnam_params, vec!["masq".to_string()]);
+        let setuid_params = setuid_params_arc.lock().unwrap();
+        assert_eq!(*setuid_params, vec![1234]);
+        let setgid_params = setgid_params_arc.lock().unwrap();
+        assert_eq!(*setgid_params, vec![5678]);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn unknown_user_name_produces_no_real_user() {
+        let id_wrapper = IdWrapperMock::new().getpwnam_result(None);
+
+        let result = RealUser::from_user_name(&id_wrapper, "booga");
+
+        assert_eq!(result, None);
     }
 
     #[cfg(not(target_os = "windows"))]
@@ -276,6 +1005,7 @@ mod tests {
         let setuid_params_arc = Arc::new(Mutex::new(vec![]));
         let setgid_params_arc = Arc::new(Mutex::new(vec![]));
         let id_wrapper = IdWrapperMock::new()
+            .umask_result(0o022)
             .getuid_result(101)
             .getgid_result(202)
             .setuid_params(&setuid_params_arc)
@@ -283,14 +1013,767 @@ mod tests {
         let mut subject = PrivilegeDropperReal::new();
         subject.id_wrapper = Box::new(id_wrapper);
 
-        subject.drop_privileges(&RealUser::new(None, None, None).populate(&DirsWrapperReal {}));
+        let result =
+            subject.drop_privileges(&RealUser::new(None, None, None).populate(&DirsWrapperReal {}));
 
+        assert_eq!(result, Ok(()));
         let setuid_params = setuid_params_arc.lock().unwrap();
         assert!(setuid_params.is_empty());
         let setgid_params = setgid_params_arc.lock().unwrap();
         assert!(setgid_params.is_empty());
     }
 
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn chown_recursive_reaches_nested_files() {
+        let home_dir = ensure_node_home_directory_exists(
+            "privilege_drop",
+            "chown_recursive_reaches_nested_files",
+        );
+        let nested_dir = home_dir.join("nested");
+        std::fs::create_dir_all(&nested_dir).unwrap();
+        std::fs::write(home_dir.join("top.txt"), "top").unwrap();
+        std::fs::write(nested_dir.join("bottom.txt"), "bottom").unwrap();
+        let uid = IdWrapperReal {}.getuid();
+        let gid = IdWrapperReal {}.getgid();
+        let id_wrapper = IdWrapperMock::new().getgid_result(0).getuid_result(0);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+
+        let result = subject.chown_recursive(&home_dir, &RealUser::new(Some(uid), Some(gid), None));
+
+        assert_eq!(result, Ok(()));
+        vec![
+            home_dir.clone(),
+            home_dir.join("top.txt"),
+            nested_dir.clone(),
+            nested_dir.join("bottom.txt"),
+        ]
+        .into_iter()
+        .for_each(|path| {
+            let metadata = std::fs::metadata(&path).unwrap();
+            assert_eq!(
+                (metadata.uid() as i32, metadata.gid() as i32),
+                (uid, gid),
+                "{:?}",
+                path
+            );
+        });
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn chown_recursive_leaves_symlink_targets_outside_the_tree_alone() {
+        let home_dir = ensure_node_home_directory_exists(
+            "privilege_drop",
+            "chown_recursive_leaves_symlink_targets_outside_the_tree_alone",
+        );
+        let tree = home_dir.join("tree");
+        std::fs::create_dir_all(&tree).unwrap();
+        let outside = home_dir.join("outside.txt");
+        std::fs::write(&outside, "secret").unwrap();
+        std::os::unix::fs::symlink(&outside, tree.join("link")).unwrap();
+        let before = std::fs::metadata(&outside).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        // As root, hand the tree to someone else, so a followed link would change hands
+        let real_id_wrapper = IdWrapperReal {};
+        let (uid, gid) = match real_id_wrapper.getuid() {
+            0 => (12345, 12345),
+            uid => (uid, real_id_wrapper.getgid()),
+        };
+        let id_wrapper = IdWrapperMock::new().getgid_result(0).getuid_result(0);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+
+        let result = subject.chown_recursive(&tree, &RealUser::new(Some(uid), Some(gid), None));
+
+        assert_eq!(result, Ok(()));
+        let after = std::fs::metadata(&outside).unwrap();
+        assert_eq!((after.uid(), after.gid()), (before.uid(), before.gid()));
+        // chown marks the status change time even when the owner doesn't change
+        assert_eq!(
+            (after.ctime(), after.ctime_nsec()),
+            (before.ctime(), before.ctime_nsec())
+        );
+        let link_metadata = std::fs::symlink_metadata(tree.join("link")).unwrap();
+        assert_eq!(
+            (link_metadata.uid() as i32, link_metadata.gid() as i32),
+            (uid, gid)
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn chown_failure_is_reported() {
+        let id_wrapper = IdWrapperMock::new().getgid_result(0).getuid_result(0);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+
+        let result = subject.chown(
+            Path::new("/nonexistent/booga"),
+            &RealUser::new(Some(123), Some(456), None),
+        );
+
+        assert_eq!(
+            result,
+            Err(PrivilegeError::ChownFailed {
+                path: PathBuf::from("/nonexistent/booga"),
+                code: nix::errno::Errno::ENOENT as i32
+            })
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn chown_all_changes_every_file_after_a_single_root_check() {
+        let home_dir = ensure_node_home_directory_exists(
+            "privilege_drop",
+            "chown_all_changes_every_file_after_a_single_root_check",
+        );
+        let files = vec![
+            home_dir.join("database"),
+            PathBuf::new(),
+            home_dir.join("log"),
+            home_dir.join("config"),
+        ];
+        files
+            .iter()
+            .filter(|file| !file.as_os_str().is_empty())
+            .for_each(|file| std::fs::write(file, "booga").unwrap());
+        let uid = IdWrapperReal {}.getuid();
+        let gid = IdWrapperReal {}.getgid();
+        let id_wrapper = IdWrapperMock::new().getgid_result(0).getuid_result(0);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+
+        let result = subject.chown_all(&files, &RealUser::new(Some(uid), Some(gid), None));
+
+        assert_eq!(result, Ok(()));
+        files
+            .iter()
+            .filter(|file| !file.as_os_str().is_empty())
+            .for_each(|file| {
+                let metadata = std::fs::metadata(file).unwrap();
+                assert_eq!(
+                    (metadata.uid() as i32, metadata.gid() as i32),
+                    (uid, gid),
+                    "{:?}",
+                    file
+                );
+            });
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn chown_all_hands_each_file_to_the_chowner_exactly_once() {
+        let lchown_params_arc = Arc::new(Mutex::new(vec![]));
+        let chowner = ChownerMock::new().lchown_params(&lchown_params_arc);
+        let id_wrapper = IdWrapperMock::new().getgid_result(0).getuid_result(0);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+        subject.chowner = Box::new(chowner);
+        let files = vec![
+            PathBuf::from("/data/node-data.db"),
+            PathBuf::new(),
+            PathBuf::from("/data/MASQNode_rCURRENT.log"),
+            PathBuf::from("/data/config.toml"),
+        ];
+
+        let result = subject.chown_all(&files, &RealUser::new(Some(123), Some(456), None));
+
+        assert_eq!(result, Ok(()));
+        let lchown_params = lchown_params_arc.lock().unwrap();
+        assert_eq!(
+            *lchown_params,
+            vec![
+                (PathBuf::from("/data/node-data.db"), 123, 456),
+                (PathBuf::from("/data/MASQNode_rCURRENT.log"), 123, 456),
+                (PathBuf::from("/data/config.toml"), 123, 456),
+            ]
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn chown_all_reports_the_code_the_chowner_failed_with() {
+        let lchown_params_arc = Arc::new(Mutex::new(vec![]));
+        let chowner = ChownerMock::new()
+            .lchown_params(&lchown_params_arc)
+            .lchown_result(Ok(()))
+            .lchown_result(Err(13));
+        let id_wrapper = IdWrapperMock::new().getgid_result(0).getuid_result(0);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+        subject.chowner = Box::new(chowner);
+
+        let result = subject.chown_all(
+            &[
+                PathBuf::from("/data/first"),
+                PathBuf::from("/data/second"),
+                PathBuf::from("/data/third"),
+            ],
+            &RealUser::new(Some(123), Some(456), None),
+        );
+
+        assert_eq!(
+            result,
+            Err(PrivilegeError::ChownFailed {
+                path: PathBuf::from("/data/second"),
+                code: 13
+            })
+        );
+        assert_eq!(lchown_params_arc.lock().unwrap().len(), 2);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn chown_all_stops_at_first_failure() {
+        let home_dir =
+            ensure_node_home_directory_exists("privilege_drop", "chown_all_stops_at_first_failure");
+        std::fs::write(home_dir.join("present"), "booga").unwrap();
+        let id_wrapper = IdWrapperMock::new().getgid_result(0).getuid_result(0);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+        let uid = IdWrapperReal {}.getuid();
+        let gid = IdWrapperReal {}.getgid();
+
+        let result = subject.chown_all(
+            &[
+                home_dir.join("present"),
+                home_dir.join("missing"),
+                PathBuf::from("/nonexistent/booga"),
+            ],
+            &RealUser::new(Some(uid), Some(gid), None),
+        );
+
+        assert_eq!(
+            result,
+            Err(PrivilegeError::ChownFailed {
+                path: home_dir.join("missing"),
+                code: nix::errno::Errno::ENOENT as i32
+            })
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn chown_all_does_nothing_when_not_root() {
+        let id_wrapper = IdWrapperMock::new().getgid_result(202).getuid_result(101);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+
+        let result = subject.chown_all(
+            &[PathBuf::from("/nonexistent/booga")],
+            &RealUser::new(Some(123), Some(456), None),
+        );
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn drop_privileges_applies_secure_umask() {
+        let umask_params_arc = Arc::new(Mutex::new(vec![]));
+        let id_wrapper = IdWrapperMock::new()
+            .umask_params(&umask_params_arc)
+            .umask_result(0o022)
+            .getgid_result(202)
+            .getuid_result(101);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+
+        let result = subject.drop_privileges(&RealUser::new(Some(101), Some(202), None));
+
+        assert_eq!(result, Ok(()));
+        let umask_params = umask_params_arc.lock().unwrap();
+        assert_eq!(*umask_params, vec![0o077]);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn drop_privileges_drops_capabilities_after_switching_ids() {
+        let drop_capabilities_params_arc = Arc::new(Mutex::new(vec![]));
+        let id_wrapper = IdWrapperMock::new()
+            .umask_result(0o022)
+            .getgid_result(202)
+            .getuid_result(101);
+        let capability_dropper = CapabilityDropperMock::new()
+            .drop_bounding_set_result(Ok(()))
+            .drop_capabilities_params(&drop_capabilities_params_arc)
+            .drop_capabilities_result(Ok(()));
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+        subject.capability_dropper = Box::new(capability_dropper);
+
+        let result = subject.drop_privileges(&RealUser::new(Some(101), Some(202), None));
+
+        assert_eq!(result, Ok(()));
+        let drop_capabilities_params = drop_capabilities_params_arc.lock().unwrap();
+        assert_eq!(*drop_capabilities_params, vec![()]);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn bounding_set_is_dropped_while_still_root_and_the_rest_after() {
+        let calls_arc = Arc::new(Mutex::new(vec![]));
+        let id_wrapper = IdWrapperMock::new()
+            .calls_params(&calls_arc)
+            .umask_result(0o022)
+            .getgid_result(0)
+            .getgrouplist_result(None)
+            .setgroups_result(0)
+            .setgid_result(0)
+            .getgid_result(202)
+            .getuid_result(0)
+            .setuid_result(0)
+            .getuid_result(101);
+        let capability_dropper = CapabilityDropperMock::new()
+            .calls_params(&calls_arc)
+            .drop_bounding_set_result(Ok(()))
+            .drop_capabilities_result(Ok(()));
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+        subject.capability_dropper = Box::new(capability_dropper);
+
+        let result = subject.drop_privileges(&RealUser::new(Some(101), Some(202), None));
+
+        assert_eq!(result, Ok(()));
+        let calls = calls_arc.lock().unwrap();
+        assert_eq!(
+            *calls,
+            vec![
+                IdWrapperCall::Umask(SECURE_UMASK),
+                IdWrapperCall::DropBoundingSet,
+                IdWrapperCall::Getgid,
+                IdWrapperCall::Getgrouplist(101, 202),
+                IdWrapperCall::Setgroups(vec![202]),
+                IdWrapperCall::Setgid(202),
+                IdWrapperCall::Getgid,
+                IdWrapperCall::Getuid,
+                IdWrapperCall::Setuid(101),
+                IdWrapperCall::Getuid,
+                IdWrapperCall::DropCapabilities,
+            ]
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn bounding_set_drop_failure_stops_the_drop_before_ids_change() {
+        let id_wrapper = IdWrapperMock::new().umask_result(0o022);
+        let capability_dropper = CapabilityDropperMock::new().drop_bounding_set_result(Err(
+            PrivilegeError::CapabilityDropFailed("booga".to_string()),
+        ));
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+        subject.capability_dropper = Box::new(capability_dropper);
+
+        let result = subject.drop_privileges(&RealUser::new(Some(101), Some(202), None));
+
+        assert_eq!(
+            result,
+            Err(PrivilegeError::CapabilityDropFailed("booga".to_string()))
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn capability_drop_failure_is_reported() {
+        let id_wrapper = IdWrapperMock::new()
+            .umask_result(0o022)
+            .getgid_result(202)
+            .getuid_result(101);
+        let capability_dropper = CapabilityDropperMock::new()
+            .drop_bounding_set_result(Ok(()))
+            .drop_capabilities_result(Err(PrivilegeError::CapabilityDropFailed(
+                "booga".to_string(),
+            )));
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+        subject.capability_dropper = Box::new(capability_dropper);
+
+        let result = subject.drop_privileges(&RealUser::new(Some(101), Some(202), None));
+
+        assert_eq!(
+            result,
+            Err(PrivilegeError::CapabilityDropFailed("booga".to_string()))
+        );
+    }
+
+    #[cfg(all(target_os = "linux", feature = "caps"))]
+    #[test]
+    fn real_capability_dropper_clears_capabilities() {
+        use caps::CapSet;
+        // Capabilities are per-thread; keep the test runner's own thread intact
+        let (result, sets) = std::thread::spawn(|| {
+            let result = CapabilityDropperReal {}.drop_capabilities();
+            let sets = vec![
+                CapSet::Ambient,
+                CapSet::Inheritable,
+                CapSet::Effective,
+                CapSet::Permitted,
+            ]
+            .into_iter()
+            .map(|cap_set| caps::read(None, cap_set).unwrap())
+            .collect::<Vec<_>>();
+            (result, sets)
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(result, Ok(()));
+        sets.into_iter()
+            .for_each(|cap_set| assert!(cap_set.is_empty(), "{:?}", cap_set));
+    }
+
+    #[cfg(all(target_os = "linux", feature = "caps"))]
+    #[test]
+    fn real_capability_dropper_clears_bounding_set_if_allowed() {
+        use caps::{CapSet, Capability};
+        let (allowed, result, bounding_set) = std::thread::spawn(|| {
+            let allowed = caps::has_cap(None, CapSet::Effective, Capability::CAP_SETPCAP).unwrap();
+            let result = CapabilityDropperReal {}.drop_bounding_set();
+            (allowed, result, caps::read(None, CapSet::Bounding).unwrap())
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(bounding_set.is_empty(), allowed, "{:?}", bounding_set);
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn chown_hands_file_over_to_sid_on_windows() {
+        let home_dir = ensure_node_home_directory_exists(
+            "privilege_drop",
+            "chown_hands_file_over_to_sid_on_windows",
+        );
+        let file = home_dir.join("owned.txt");
+        std::fs::write(&file, "booga").unwrap();
+        let sid = windows_ownership::get_owner(&file).unwrap();
+        let mut real_user = RealUser::null();
+        real_user.sid_opt = Some(sid.clone());
+        let subject = PrivilegeDropperReal::new();
+
+        let result = subject.chown(&file, &real_user);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(windows_ownership::get_owner(&file), Ok(sid));
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn chown_with_malformed_sid_is_reported_on_windows() {
+        let home_dir = ensure_node_home_directory_exists(
+            "privilege_drop",
+            "chown_with_malformed_sid_is_reported_on_windows",
+        );
+        let file = home_dir.join("owned.txt");
+        std::fs::write(&file, "booga").unwrap();
+        let mut real_user = RealUser::null();
+        real_user.sid_opt = Some("booga".to_string());
+        let subject = PrivilegeDropperReal::new();
+
+        let result = subject.chown(&file, &real_user);
+
+        assert_eq!(
+            result,
+            Err(PrivilegeError::ChownFailed {
+                path: file,
+                code: 1337 // ERROR_INVALID_SID
+            })
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn verify_dropped_accepts_target_ids() {
+        let id_wrapper = IdWrapperMock::new()
+            .getuid_result(101)
+            .geteuid_result(101)
+            .getgid_result(202)
+            .getegid_result(202)
+            .getgrouplist_result(Some(vec![202, 303]))
+            .getgroups_result(vec![303, 202]);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+
+        let result = subject.verify_dropped(&RealUser::new(Some(101), Some(202), None));
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn verify_dropped_complains_about_lingering_root_group() {
+        let id_wrapper = IdWrapperMock::new()
+            .getuid_result(101)
+            .geteuid_result(101)
+            .getgid_result(202)
+            .getegid_result(202)
+            .getgrouplist_result(Some(vec![202, 303]))
+            .getgroups_result(vec![202, 0, 303]);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+
+        let result = subject.verify_dropped(&RealUser::new(Some(101), Some(202), None));
+
+        assert_eq!(
+            result,
+            Err("Privileges not dropped: root group is still a supplementary group".to_string())
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn verify_dropped_complains_about_supplementary_group_of_someone_else() {
+        let id_wrapper = IdWrapperMock::new()
+            .getuid_result(101)
+            .geteuid_result(101)
+            .getgid_result(202)
+            .getegid_result(202)
+            .getgrouplist_result(None)
+            .getgroups_result(vec![202, 404]);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+
+        let result = subject.verify_dropped(&RealUser::new(Some(101), Some(202), None));
+
+        assert_eq!(
+            result,
+            Err(
+                "Privileges not dropped: supplementary group 404 doesn't belong to the real user"
+                    .to_string()
+            )
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn real_getgrouplist_includes_primary_group_of_current_user() {
+        let subject = IdWrapperReal {};
+        let uid = subject.getuid();
+        let gid = subject.getgid();
+
+        let user_groups = match subject.getgrouplist(uid, gid) {
+            Some(groups) => groups,
+            None => return, // e.g. a container user that isn't in /etc/passwd
+        };
+
+        assert!(user_groups.contains(&gid), "{:?}", user_groups);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn verify_dropped_complains_about_lingering_root() {
+        let id_wrapper = IdWrapperMock::new()
+            .getuid_result(101)
+            .geteuid_result(0)
+            .getgid_result(202)
+            .getegid_result(202);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+
+        let result = subject.verify_dropped(&RealUser::new(Some(101), Some(202), None));
+
+        assert_eq!(
+            result,
+            Err("Privileges not dropped: effective user id is still root".to_string())
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn verify_dropped_complains_about_wrong_ids() {
+        let id_wrapper = IdWrapperMock::new()
+            .getuid_result(101)
+            .geteuid_result(101)
+            .getgid_result(303)
+            .getegid_result(202);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+
+        let result = subject.verify_dropped(&RealUser::new(Some(101), Some(202), None));
+
+        assert_eq!(
+            result,
+            Err("Privileges not dropped: group id is 303 instead of 202".to_string())
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn effective_privileges_are_dropped_and_regained() {
+        let seteuid_params_arc = Arc::new(Mutex::new(vec![]));
+        let setegid_params_arc = Arc::new(Mutex::new(vec![]));
+        let id_wrapper = IdWrapperMock::new()
+            .geteuid_result(0)
+            .getegid_result(0)
+            .seteuid_params(&seteuid_params_arc)
+            .setegid_params(&setegid_params_arc)
+            .setegid_result(0)
+            .seteuid_result(0)
+            .seteuid_result(0)
+            .setegid_result(0);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+
+        let result = subject
+            .with_effective_privileges_dropped(&RealUser::new(Some(101), Some(202), None), || {
+                "done"
+            });
+
+        assert_eq!(result, Ok("done"));
+        let seteuid_params = seteuid_params_arc.lock().unwrap();
+        assert_eq!(*seteuid_params, vec![101, 0]);
+        let setegid_params = setegid_params_arc.lock().unwrap();
+        assert_eq!(*setegid_params, vec![202, 0]);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn effective_group_is_dropped_first_and_regained_last() {
+        let calls_arc = Arc::new(Mutex::new(vec![]));
+        let id_wrapper = IdWrapperMock::new()
+            .calls_params(&calls_arc)
+            .geteuid_result(0)
+            .getegid_result(0)
+            .setegid_result(0)
+            .seteuid_result(0)
+            .seteuid_result(0)
+            .setegid_result(0);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+
+        let _ = subject
+            .with_effective_privileges_dropped(&RealUser::new(Some(101), Some(202), None), || ());
+
+        let calls = calls_arc.lock().unwrap();
+        assert_eq!(
+            *calls,
+            vec![
+                IdWrapperCall::Geteuid,
+                IdWrapperCall::Getegid,
+                IdWrapperCall::Setegid(202),
+                IdWrapperCall::Seteuid(101),
+                IdWrapperCall::Seteuid(0),
+                IdWrapperCall::Setegid(0),
+            ]
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn failure_to_drop_effective_uid_restores_effective_gid() {
+        let setegid_params_arc = Arc::new(Mutex::new(vec![]));
+        let id_wrapper = IdWrapperMock::new()
+            .geteuid_result(0)
+            .getegid_result(0)
+            .setegid_params(&setegid_params_arc)
+            .setegid_result(0)
+            .seteuid_result(47)
+            .setegid_result(0);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+        let mut work_done = false;
+
+        let result = subject
+            .with_effective_privileges_dropped(&RealUser::new(Some(101), Some(202), None), || {
+                work_done = true
+            });
+
+        assert_eq!(result, Err(PrivilegeError::SetuidFailed(47)));
+        assert!(!work_done);
+        let setegid_params = setegid_params_arc.lock().unwrap();
+        assert_eq!(*setegid_params, vec![202, 0]);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn failure_to_regain_effective_uid_still_tries_to_regain_effective_gid() {
+        let setegid_params_arc = Arc::new(Mutex::new(vec![]));
+        let id_wrapper = IdWrapperMock::new()
+            .geteuid_result(0)
+            .getegid_result(0)
+            .setegid_params(&setegid_params_arc)
+            .setegid_result(0)
+            .seteuid_result(0)
+            .seteuid_result(47)
+            .setegid_result(48);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+
+        let result = subject
+            .with_effective_privileges_dropped(&RealUser::new(Some(101), Some(202), None), || {
+                "done"
+            });
+
+        assert_eq!(result, Err(PrivilegeError::SetuidFailed(47)));
+        let setegid_params = setegid_params_arc.lock().unwrap();
+        assert_eq!(*setegid_params, vec![202, 0]);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn failure_to_regain_effective_gid_is_reported() {
+        let id_wrapper = IdWrapperMock::new()
+            .geteuid_result(0)
+            .getegid_result(0)
+            .setegid_result(0)
+            .seteuid_result(0)
+            .seteuid_result(0)
+            .setegid_result(48);
+        let mut subject = PrivilegeDropperReal::new();
+        subject.id_wrapper = Box::new(id_wrapper);
+
+        let result = subject
+            .with_effective_privileges_dropped(&RealUser::new(Some(101), Some(202), None), || {
+                "done"
+            });
+
+        assert_eq!(result, Err(PrivilegeError::SetgidFailed(48)));
+    }
+
+    #[test]
+    fn privilege_errors_are_displayed_and_converted() {
+        assert_eq!(
+            PrivilegeError::SetgroupsFailed(47).to_string(),
+            "Error code 47 resetting supplementary groups"
+        );
+        assert_eq!(
+            PrivilegeError::SetgidFailed(47).to_string(),
+            "Error code 47 resetting group id"
+        );
+        assert_eq!(
+            PrivilegeError::SetuidFailed(47).to_string(),
+            "Error code 47 resetting user id"
+        );
+        assert_eq!(
+            PrivilegeError::StillRoot.to_string(),
+            "Attempt to drop privileges failed: still root"
+        );
+        assert_eq!(
+            PrivilegeError::CapabilityDropFailed("booga".to_string()).to_string(),
+            "Couldn't drop capabilities: booga"
+        );
+        let chown_failed = PrivilegeError::ChownFailed {
+            path: PathBuf::from("/booga"),
+            code: 2,
+        };
+        assert_eq!(
+            chown_failed.to_string(),
+            "Error code 2 changing ownership of \"/booga\""
+        );
+        assert_eq!(
+            ConfiguratorError::from(chown_failed),
+            ConfiguratorError::required(
+                "real-user",
+                "Error code 2 changing ownership of \"/booga\""
+            )
+        );
+    }
+
     #[cfg(not(target_os = "windows"))]
     #[test]
     fn expect_privilege_works_outside_windows() {
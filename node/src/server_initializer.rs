@@ -1,6 +1,6 @@
 // Copyright (c) 2019, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
 use super::bootstrapper::Bootstrapper;
-use super::privilege_drop::{PrivilegeDropper, PrivilegeDropperReal};
+use super::privilege_drop::{PrivilegeDropper, PrivilegeDropperReal, PrivilegeError};
 use crate::bootstrapper::RealUser;
 use crate::entry_dns::dns_socket_server::DnsSocketServer;
 use crate::node_configurator::node_configurator_standard::server_initializer_collected_params;
@@ -37,6 +37,9 @@ pub struct ServerInitializerReal {
 impl ServerInitializer for ServerInitializerReal {
     fn go(&mut self, streams: &mut StdStreams<'_>, args: &[String]) -> RunModeResult {
         let params = server_initializer_collected_params(self.dirs_wrapper.as_ref(), args)?;
+        // A directory the Node didn't make, such as /etc, may hold anything: it isn't the Node's
+        // to give away. The Node's own files in it are handed over by the bootstrapper.
+        let node_made_data_directory = !params.data_directory.exists();
 
         let result: RunModeResult = Ok(())
             .combine_results(
@@ -50,9 +53,20 @@ impl ServerInitializer for ServerInitializerReal {
                     .initialize_as_privileged(&params.multi_config),
             );
 
-        self.privilege_dropper
-            .chown(&params.data_directory, &params.real_user);
-        self.privilege_dropper.drop_privileges(&params.real_user);
+        let chown_result = if node_made_data_directory {
+            self.privilege_dropper
+                .chown_recursive(&params.data_directory, &params.real_user)
+        } else {
+            Ok(())
+        };
+        if let Err(e) =
+            chown_result.and_then(|_| self.privilege_dropper.drop_privileges(&params.real_user))
+        {
+            return result.combine_results(Err(e.into()));
+        }
+        if let Err(msg) = self.privilege_dropper.verify_dropped(&params.real_user) {
+            return result.combine_results(Err(ConfiguratorError::required("real-user", &msg)));
+        }
 
         result
             .combine_results(
@@ -139,13 +153,14 @@ lazy_static! {
 }
 
 pub trait LoggerInitializerWrapper {
+    // Logging is up even if the log file couldn't be handed over to the real user
     fn init(
         &mut self,
         file_path: PathBuf,
         real_user: &RealUser,
         log_level: LevelFilter,
         discriminant_opt: Option<&str>,
-    );
+    ) -> Result<(), PrivilegeError>;
 }
 
 pub struct LoggerInitializerWrapperReal;
@@ -157,7 +172,7 @@ impl LoggerInitializerWrapper for LoggerInitializerWrapperReal {
         real_user: &RealUser,
         log_level: LevelFilter,
         discriminant_opt: Option<&str>,
-    ) {
+    ) -> Result<(), PrivilegeError> {
         let mut logger = Logger::with(
             LogSpecBuilder::new()
                 .default(log_level)
@@ -188,7 +203,7 @@ impl LoggerInitializerWrapper for LoggerInitializerWrapperReal {
                 None => "".to_string(),
             }
         ));
-        privilege_dropper.chown(&logfile_name, real_user);
+        let chown_result = privilege_dropper.chown(&logfile_name, real_user);
         *(Self::logfile_name_guard()) = logfile_name;
         std::panic::set_hook(Box::new(|panic_info| {
             panic_hook(AltPanicInfo::from(panic_info))
@@ -201,6 +216,7 @@ impl LoggerInitializerWrapper for LoggerInitializerWrapperReal {
             // This resets the format function after specialized formatting for the log heading is used.
             POINTER_TO_FORMAT_FUNCTION = real_format_function;
         }
+        chown_result
     }
 }
 
@@ -286,7 +302,7 @@ fn format_function(
 #[cfg(test)]
 pub mod test_utils {
     use crate::bootstrapper::RealUser;
-    use crate::privilege_drop::PrivilegeDropper;
+    use crate::privilege_drop::{PrivilegeDropper, PrivilegeError};
     use crate::server_initializer::LoggerInitializerWrapper;
     use log::LevelFilter;
     use std::cell::RefCell;
@@ -295,24 +311,70 @@ pub mod test_utils {
 
     pub struct PrivilegeDropperMock {
         drop_privileges_params: Arc<Mutex<Vec<RealUser>>>,
+        drop_privileges_results: RefCell<Vec<Result<(), PrivilegeError>>>,
         chown_params: Arc<Mutex<Vec<(PathBuf, RealUser)>>>,
+        chown_recursive_params: Arc<Mutex<Vec<(PathBuf, RealUser)>>>,
+        chown_all_params: Arc<Mutex<Vec<(Vec<PathBuf>, RealUser)>>>,
+        set_umask_params: Arc<Mutex<Vec<u32>>>,
+        verify_dropped_params: Arc<Mutex<Vec<RealUser>>>,
+        verify_dropped_results: RefCell<Vec<Result<(), String>>>,
         expect_privilege_params: Arc<Mutex<Vec<bool>>>,
         expect_privilege_results: RefCell<Vec<bool>>,
     }
 
     impl PrivilegeDropper for PrivilegeDropperMock {
-        fn drop_privileges(&self, real_user: &RealUser) {
+        fn drop_privileges(&self, real_user: &RealUser) -> Result<(), PrivilegeError> {
             self.drop_privileges_params
                 .lock()
                 .unwrap()
                 .push(real_user.clone());
+            let mut results = self.drop_privileges_results.borrow_mut();
+            if results.is_empty() {
+                Ok(())
+            } else {
+                results.remove(0)
+            }
         }
 
-        fn chown(&self, file: &Path, real_user: &RealUser) {
+        fn chown(&self, file: &Path, real_user: &RealUser) -> Result<(), PrivilegeError> {
             self.chown_params
                 .lock()
                 .unwrap()
                 .push((file.to_path_buf(), real_user.clone()));
+            Ok(())
+        }
+
+        fn chown_recursive(&self, dir: &Path, real_user: &RealUser) -> Result<(), PrivilegeError> {
+            self.chown_recursive_params
+                .lock()
+                .unwrap()
+                .push((dir.to_path_buf(), real_user.clone()));
+            Ok(())
+        }
+
+        fn chown_all(&self, files: &[PathBuf], real_user: &RealUser) -> Result<(), PrivilegeError> {
+            self.chown_all_params
+                .lock()
+                .unwrap()
+                .push((files.to_vec(), real_user.clone()));
+            Ok(())
+        }
+
+        fn set_umask(&self, mask: u32) {
+            self.set_umask_params.lock().unwrap().push(mask);
+        }
+
+        fn verify_dropped(&self, real_user: &RealUser) -> Result<(), String> {
+            self.verify_dropped_params
+                .lock()
+                .unwrap()
+                .push(real_user.clone());
+            let mut results = self.verify_dropped_results.borrow_mut();
+            if results.is_empty() {
+                Ok(())
+            } else {
+                results.remove(0)
+            }
         }
 
         fn expect_privilege(&self, privilege_expected: bool) -> bool {
@@ -328,7 +390,13 @@ pub mod test_utils {
         pub fn new() -> Self {
             Self {
                 drop_privileges_params: Arc::new(Mutex::new(vec![])),
+                drop_privileges_results: RefCell::new(vec![]),
                 chown_params: Arc::new(Mutex::new(vec![])),
+                chown_recursive_params: Arc::new(Mutex::new(vec![])),
+                chown_all_params: Arc::new(Mutex::new(vec![])),
+                set_umask_params: Arc::new(Mutex::new(vec![])),
+                verify_dropped_params: Arc::new(Mutex::new(vec![])),
+                verify_dropped_results: RefCell::new(vec![]),
                 expect_privilege_params: Arc::new(Mutex::new(vec![])),
                 expect_privilege_results: RefCell::new(vec![]),
             }
@@ -339,11 +407,47 @@ pub mod test_utils {
             self
         }
 
+        pub fn drop_privileges_result(self, result: Result<(), PrivilegeError>) -> Self {
+            self.drop_privileges_results.borrow_mut().push(result);
+            self
+        }
+
         pub fn chown_params(mut self, params: &Arc<Mutex<Vec<(PathBuf, RealUser)>>>) -> Self {
             self.chown_params = params.clone();
             self
         }
 
+        pub fn chown_recursive_params(
+            mut self,
+            params: &Arc<Mutex<Vec<(PathBuf, RealUser)>>>,
+        ) -> Self {
+            self.chown_recursive_params = params.clone();
+            self
+        }
+
+        pub fn chown_all_params(
+            mut self,
+            params: &Arc<Mutex<Vec<(Vec<PathBuf>, RealUser)>>>,
+        ) -> Self {
+            self.chown_all_params = params.clone();
+            self
+        }
+
+        pub fn set_umask_params(mut self, params: &Arc<Mutex<Vec<u32>>>) -> Self {
+            self.set_umask_params = params.clone();
+            self
+        }
+
+        pub fn verify_dropped_params(mut self, params: &Arc<Mutex<Vec<RealUser>>>) -> Self {
+            self.verify_dropped_params = params.clone();
+            self
+        }
+
+        pub fn verify_dropped_result(self, result: Result<(), String>) -> Self {
+            self.verify_dropped_results.borrow_mut().push(result);
+            self
+        }
+
         pub fn expect_privilege_params(mut self, params: &Arc<Mutex<Vec<bool>>>) -> Self {
             self.expect_privilege_params = params.clone();
             self
@@ -357,6 +461,7 @@ pub mod test_utils {
 
     pub struct LoggerInitializerWrapperMock {
         init_parameters: Arc<Mutex<Vec<(PathBuf, RealUser, LevelFilter, Option<String>)>>>,
+        init_results: RefCell<Vec<Result<(), PrivilegeError>>>,
     }
 
     impl LoggerInitializerWrapper for LoggerInitializerWrapperMock {
@@ -366,7 +471,7 @@ pub mod test_utils {
             real_user: &RealUser,
             log_level: LevelFilter,
             name_segment: Option<&str>,
-        ) {
+        ) -> Result<(), PrivilegeError> {
             self.init_parameters.lock().unwrap().push((
                 file_path,
                 real_user.clone(),
@@ -376,6 +481,12 @@ pub mod test_utils {
                     None => None,
                 },
             ));
+            let mut results = self.init_results.borrow_mut();
+            if results.is_empty() {
+                Ok(())
+            } else {
+                results.remove(0)
+            }
         }
     }
 
@@ -383,6 +494,7 @@ pub mod test_utils {
         pub fn new() -> LoggerInitializerWrapperMock {
             LoggerInitializerWrapperMock {
                 init_parameters: Arc::new(Mutex::new(vec![])),
+                init_results: RefCell::new(vec![]),
             }
         }
 
@@ -393,6 +505,11 @@ pub mod test_utils {
             self.init_parameters = parameters.clone();
             self
         }
+
+        pub fn init_result(self, result: Result<(), PrivilegeError>) -> Self {
+            self.init_results.borrow_mut().push(result);
+            self
+        }
     }
 }
 
@@ -402,6 +519,7 @@ pub mod tests {
     use crate::bootstrapper::BootstrapperConfig;
     use crate::crash_test_dummy::CrashTestDummy;
     use crate::node_test_utils::DirsWrapperMock;
+    use crate::privilege_drop::PrivilegeError;
     use crate::server_initializer::test_utils::PrivilegeDropperMock;
     use crate::test_utils::logfile_name_guard::LogfileNameGuard;
     use crate::test_utils::unshared_test_utils::make_pre_populated_mocked_directory_wrapper;
@@ -413,6 +531,7 @@ pub mod tests {
         ByteArrayReader, ByteArrayWriter, FakeStreamHolder,
     };
     use masq_lib::test_utils::logging::{init_test_logging, TestLogHandler};
+    use masq_lib::test_utils::utils::ensure_node_home_directory_exists;
     use masq_lib::utils::slice_of_strs_to_vec_of_strings;
     use std::cell::RefCell;
     use std::ops::Not;
@@ -806,10 +925,12 @@ pub mod tests {
             ]));
         let dirs_wrapper = make_pre_populated_mocked_directory_wrapper();
         let drop_privileges_params_arc = Arc::new(Mutex::new(vec![]));
-        let chown_params_arc = Arc::new(Mutex::new(vec![]));
+        let chown_recursive_params_arc = Arc::new(Mutex::new(vec![]));
+        let verify_dropped_params_arc = Arc::new(Mutex::new(vec![]));
         let privilege_dropper = PrivilegeDropperMock::new()
             .drop_privileges_params(&drop_privileges_params_arc)
-            .chown_params(&chown_params_arc);
+            .chown_recursive_params(&chown_recursive_params_arc)
+            .verify_dropped_params(&verify_dropped_params_arc);
         let stdin = &mut ByteArrayReader::new(&[0; 0]);
         let stdout = &mut ByteArrayWriter::new();
         let stderr = &mut ByteArrayWriter::new();
@@ -838,9 +959,9 @@ pub mod tests {
 
         assert!(result.is_ok());
         let real_user = RealUser::new(Some(123), Some(456), Some("/home/alice".into()));
-        let chown_params = chown_params_arc.lock().unwrap();
+        let chown_recursive_params = chown_recursive_params_arc.lock().unwrap();
         assert_eq!(
-            *chown_params,
+            *chown_recursive_params,
             vec![(
                 PathBuf::from(format!(
                     "/home/alice/mock_directory/MASQ/{}",
@@ -850,7 +971,9 @@ pub mod tests {
             )]
         );
         let drop_privileges_params = drop_privileges_params_arc.lock().unwrap();
-        assert_eq!(*drop_privileges_params, vec![real_user]);
+        assert_eq!(*drop_privileges_params, vec![real_user.clone()]);
+        let verify_dropped_params = verify_dropped_params_arc.lock().unwrap();
+        assert_eq!(*verify_dropped_params, vec![real_user]);
         let params_for_assertion_on_multi_config = vec!["5.5.6.6", "123:456:/home/alice"];
         [
             bootstrapper_init_privileged_params_arc,
@@ -868,6 +991,56 @@ pub mod tests {
         })
     }
 
+    #[test]
+    fn go_leaves_a_data_directory_it_did_not_make_to_its_owner() {
+        let _ = LogfileNameGuard::new(&PathBuf::from("uninitialized"));
+        let data_dir = ensure_node_home_directory_exists(
+            "server_initializer",
+            "go_leaves_a_data_directory_it_did_not_make_to_its_owner",
+        );
+        let dns_socket_server = ConfiguredByPrivilegeMock::default()
+            .initialize_as_privileged_result(Ok(()))
+            .initialize_as_unprivileged_result(Ok(()));
+        let bootstrapper = ConfiguredByPrivilegeMock::default()
+            .initialize_as_privileged_result(Ok(()))
+            .initialize_as_unprivileged_result(Ok(()));
+        let chown_params_arc = Arc::new(Mutex::new(vec![]));
+        let chown_recursive_params_arc = Arc::new(Mutex::new(vec![]));
+        let drop_privileges_params_arc = Arc::new(Mutex::new(vec![]));
+        let privilege_dropper = PrivilegeDropperMock::new()
+            .chown_params(&chown_params_arc)
+            .chown_recursive_params(&chown_recursive_params_arc)
+            .drop_privileges_params(&drop_privileges_params_arc);
+        let mut subject = ServerInitializerReal {
+            dns_socket_server: Box::new(dns_socket_server),
+            bootstrapper: Box::new(bootstrapper),
+            privilege_dropper: Box::new(privilege_dropper),
+            dirs_wrapper: Box::new(make_pre_populated_mocked_directory_wrapper()),
+        };
+        let args = slice_of_strs_to_vec_of_strings(&[
+            "MASQNode",
+            "--real-user",
+            "123:456:/home/alice",
+            "--data-directory",
+            data_dir.to_str().unwrap(),
+        ]);
+        let mut holder = FakeStreamHolder::new();
+
+        let result = subject.go(&mut holder.streams(), &args);
+
+        assert_eq!(result, Ok(()));
+        assert!(chown_params_arc.lock().unwrap().is_empty());
+        assert!(chown_recursive_params_arc.lock().unwrap().is_empty());
+        assert_eq!(
+            *drop_privileges_params_arc.lock().unwrap(),
+            vec![RealUser::new(
+                Some(123),
+                Some(456),
+                Some("/home/alice".into())
+            )]
+        );
+    }
+
     #[test]
     fn go_should_combine_errors() {
         let _ = LogfileNameGuard::new(&PathBuf::from("uninitialized"));
@@ -914,4 +1087,63 @@ pub mod tests {
             ]))
         );
     }
+    #[test]
+    fn go_should_stop_if_privileges_cannot_be_dropped() {
+        let _ = LogfileNameGuard::new(&PathBuf::from("uninitialized"));
+        let dns_socket_server =
+            ConfiguredByPrivilegeMock::default().initialize_as_privileged_result(Ok(()));
+        let bootstrapper = ConfiguredByPrivilegeMock::default().initialize_as_privileged_result(
+            Err(ConfiguratorError::required("boot-iap", "boot-iap-reason")),
+        );
+        let privilege_dropper =
+            PrivilegeDropperMock::new().drop_privileges_result(Err(PrivilegeError::StillRoot));
+        let mut subject = ServerInitializerReal {
+            dns_socket_server: Box::new(dns_socket_server),
+            bootstrapper: Box::new(bootstrapper),
+            privilege_dropper: Box::new(privilege_dropper),
+            dirs_wrapper: Box::new(make_pre_populated_mocked_directory_wrapper()),
+        };
+        let args =
+            slice_of_strs_to_vec_of_strings(&["MASQNode", "--real-user", "123:123:/home/alice"]);
+        let mut holder = FakeStreamHolder::new();
+
+        let result = subject.go(&mut holder.streams(), &args);
+
+        assert_eq!(
+            result,
+            Err(ConfiguratorError::new(vec![
+                ParamError::new("boot-iap", "boot-iap-reason"),
+                ParamError::new("real-user", "Attempt to drop privileges failed: still root"),
+            ]))
+        );
+    }
+    #[test]
+    fn go_should_stop_if_privilege_drop_cannot_be_verified() {
+        let _ = LogfileNameGuard::new(&PathBuf::from("uninitialized"));
+        let dns_socket_server =
+            ConfiguredByPrivilegeMock::default().initialize_as_privileged_result(Ok(()));
+        let bootstrapper =
+            ConfiguredByPrivilegeMock::default().initialize_as_privileged_result(Ok(()));
+        let privilege_dropper = PrivilegeDropperMock::new()
+            .verify_dropped_result(Err("Privileges not dropped: booga".to_string()));
+        let mut subject = ServerInitializerReal {
+            dns_socket_server: Box::new(dns_socket_server),
+            bootstrapper: Box::new(bootstrapper),
+            privilege_dropper: Box::new(privilege_dropper),
+            dirs_wrapper: Box::new(make_pre_populated_mocked_directory_wrapper()),
+        };
+        let args =
+            slice_of_strs_to_vec_of_strings(&["MASQNode", "--real-user", "123:123:/home/alice"]);
+        let mut holder = FakeStreamHolder::new();
+
+        let result = subject.go(&mut holder.streams(), &args);
+
+        assert_eq!(
+            result,
+            Err(ConfiguratorError::required(
+                "real-user",
+                "Privileges not dropped: booga"
+            ))
+        );
+    }
 }
@@ -8,7 +8,7 @@ use masq_lib::messages::{
     NODE_UI_PROTOCOL,
 };
 use masq_lib::messages::{UiFinancialsRequest, UiRedirect, UiStartOrder, UiStartResponse};
-use masq_lib::test_utils::ui_connection::UiConnection;
+use masq_lib::test_utils::ui_connection::{UiConnection, UiConnectionError};
 use masq_lib::test_utils::utils::TEST_DEFAULT_CHAIN;
 use masq_lib::test_utils::utils::{ensure_node_home_directory_exists, node_home_directory};
 use masq_lib::utils::find_free_port;
@@ -99,11 +99,16 @@ fn initialization_sequence_integration() {
         .transact_with_context_id(financials_request.clone(), context_id)
         .unwrap();
 
-    assert_eq!(not_running_financials_response.0, NODE_NOT_RUNNING_ERROR);
-    assert_eq!(
-        not_running_financials_response.1,
-        "Cannot handle financials request: Node is not running".to_string()
-    );
+    match not_running_financials_response {
+        UiConnectionError::Server { code, message, .. } => {
+            assert_eq!(code, NODE_NOT_RUNNING_ERROR);
+            assert_eq!(
+                message,
+                "Cannot handle financials request: Node is not running".to_string()
+            );
+        }
+        x => panic!("Expected a server error, got {:?}", x),
+    }
     assert_eq!(running_financials_response.opcode, "financials".to_string());
     assert_eq!(
         running_financials_response.port,